@@ -0,0 +1,152 @@
+//! Compares the happy path of `ensure!` against an inline error expansion.
+//!
+//! Run with `cargo bench`. Each benchmark validates the same buffer of
+//! records; only the code generated for the failure path differs.
+
+use io_ensure::ensure;
+use std::hint::black_box;
+use std::io::{self, ErrorKind};
+use std::time::{Duration, Instant};
+
+/// The expansion `ensure!` used to have, building the error inline.
+macro_rules! ensure_inline {
+    ($cond:expr, $kind:expr, $($arg:tt)+) => {
+        if !$cond {
+            return Err(io::Error::new($kind, format!($($arg)+)));
+        }
+    };
+}
+
+const RECORD: usize = 8;
+
+fn records() -> Vec<u8> {
+    (0..4096u32)
+        .flat_map(|i| {
+            let len = (i % 200) as u16;
+            let mut record = [0u8; RECORD];
+            record[0] = 0xa5;
+            record[1] = (i % 4) as u8;
+            record[2..4].copy_from_slice(&len.to_le_bytes());
+            record[4..8].copy_from_slice(&i.to_le_bytes());
+            record
+        })
+        .collect()
+}
+
+fn parse_ensure(buf: &[u8]) -> io::Result<u64> {
+    let mut sum = 0u64;
+    for (i, record) in buf.chunks(RECORD).enumerate() {
+        ensure!(
+            record.len() == RECORD,
+            ErrorKind::UnexpectedEof,
+            "record {} is truncated",
+            i
+        );
+        ensure!(
+            record[0] == 0xa5,
+            ErrorKind::InvalidData,
+            "record {} has bad magic {:#x}",
+            i,
+            record[0]
+        );
+        ensure!(
+            record[1] < 4,
+            ErrorKind::InvalidData,
+            "record {} has unknown type {}",
+            i,
+            record[1]
+        );
+        let len = u16::from_le_bytes([record[2], record[3]]);
+        ensure!(
+            len < 200,
+            ErrorKind::InvalidData,
+            "record {} has length {} over 200",
+            i,
+            len
+        );
+        let seq = u32::from_le_bytes([record[4], record[5], record[6], record[7]]);
+        ensure!(
+            seq as usize == i,
+            ErrorKind::InvalidData,
+            "record {} has sequence number {}",
+            i,
+            seq
+        );
+        sum += u64::from(len);
+    }
+    Ok(sum)
+}
+
+fn parse_inline(buf: &[u8]) -> io::Result<u64> {
+    let mut sum = 0u64;
+    for (i, record) in buf.chunks(RECORD).enumerate() {
+        ensure_inline!(
+            record.len() == RECORD,
+            ErrorKind::UnexpectedEof,
+            "record {} is truncated",
+            i
+        );
+        ensure_inline!(
+            record[0] == 0xa5,
+            ErrorKind::InvalidData,
+            "record {} has bad magic {:#x}",
+            i,
+            record[0]
+        );
+        ensure_inline!(
+            record[1] < 4,
+            ErrorKind::InvalidData,
+            "record {} has unknown type {}",
+            i,
+            record[1]
+        );
+        let len = u16::from_le_bytes([record[2], record[3]]);
+        ensure_inline!(
+            len < 200,
+            ErrorKind::InvalidData,
+            "record {} has length {} over 200",
+            i,
+            len
+        );
+        let seq = u32::from_le_bytes([record[4], record[5], record[6], record[7]]);
+        ensure_inline!(
+            seq as usize == i,
+            ErrorKind::InvalidData,
+            "record {} has sequence number {}",
+            i,
+            seq
+        );
+        sum += u64::from(len);
+    }
+    Ok(sum)
+}
+
+fn bench(name: &str, buf: &[u8], parse: fn(&[u8]) -> io::Result<u64>) {
+    // Warm up, then time batches until the measurement is long enough.
+    for _ in 0..100 {
+        black_box(parse(black_box(buf)).unwrap());
+    }
+    let mut iters = 0u32;
+    let mut elapsed = Duration::ZERO;
+    while elapsed < Duration::from_secs(2) {
+        let start = Instant::now();
+        for _ in 0..1000 {
+            black_box(parse(black_box(buf)).unwrap());
+        }
+        elapsed += start.elapsed();
+        iters += 1000;
+    }
+    println!(
+        "{:<8} {:>10.0} ns/iter",
+        name,
+        elapsed.as_nanos() as f64 / f64::from(iters)
+    );
+}
+
+fn main() {
+    let buf = records();
+    assert_eq!(parse_ensure(&buf).unwrap(), parse_inline(&buf).unwrap());
+    assert!(parse_ensure(&buf[..buf.len() - 1]).is_err());
+    bench("inline", &buf, parse_inline);
+    bench("ensure", &buf, parse_ensure);
+}
@@ -0,0 +1,305 @@
+//! Macros for validating buffers.
+
+/// Exits a function early with an [`io::Error`] if a buffer does not have
+/// enough spare capacity to hold `needed` more elements without reallocating.
+///
+/// The spare capacity is computed as `buf.capacity() - buf.len()`, so this
+/// works with any type exposing those two methods, such as [`Vec`],
+/// [`String`], and [`VecDeque`]. When no message is given the error reports
+/// both the needed and the available space.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`Vec`]: https://doc.rust-lang.org/std/vec/struct.Vec.html
+/// [`String`]: https://doc.rust-lang.org/std/string/struct.String.html
+/// [`VecDeque`]: https://doc.rust-lang.org/std/collections/struct.VecDeque.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let buf: Vec<u8> = Vec::with_capacity(16);
+/// let needed = 8;
+/// ensure_capacity!(buf, needed, ErrorKind::Other);
+/// ensure_capacity!(buf, needed, ErrorKind::Other, "buffer would reallocate: need {} slots, have {}", needed, buf.capacity() - buf.len());
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_capacity {
+    ($buf:expr, $needed:expr, $kind:expr $(,)?) => {
+        match (&$buf, $needed) {
+            (buf, needed) => {
+                let available = buf.capacity() - buf.len();
+                $crate::ensure!(
+                    available >= needed,
+                    $kind,
+                    "insufficient capacity: need {} slots, have {}",
+                    needed,
+                    available
+                );
+            }
+        }
+    };
+    ($buf:expr, $needed:expr, $kind:expr, $($arg:tt)+) => {
+        match (&$buf, $needed) {
+            (buf, needed) => {
+                $crate::ensure!(buf.capacity() - buf.len() >= needed, $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a buffer's spare capacity
+/// is not greater than or equal to `needed`.
+///
+/// This is the same check as [`ensure_capacity!`], under a name that spells out
+/// the comparison: `buf.capacity() - buf.len() >= needed`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_capacity!`]: crate::ensure_capacity
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let output_buf: Vec<u8> = Vec::with_capacity(64);
+/// ensure_capacity_ge!(output_buf, 32, ErrorKind::Other);
+/// ensure_capacity_ge!(output_buf, 32, ErrorKind::Other, "write would cause reallocation");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_capacity_ge {
+    ($($arg:tt)*) => {
+        $crate::ensure_capacity!($($arg)*)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a byte buffer does not
+/// start with the given magic bytes.
+///
+/// The buffer and the magic sequence may be anything implementing
+/// `AsRef<[u8]>`, such as `&[u8]`, byte string literals, and `Vec<u8>`. A
+/// buffer shorter than the magic sequence fails the check. When no message is
+/// given the error reports the expected magic and the actual leading bytes in
+/// hex.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let data = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+/// ensure_magic_bytes!(data, b"\x89PNG\r\n\x1a\n", ErrorKind::InvalidData);
+/// ensure_magic_bytes!(data, b"\x89PNG\r\n\x1a\n", ErrorKind::InvalidData, "not a valid PNG file");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_magic_bytes {
+    ($data:expr, $magic:expr, $kind:expr $(,)?) => {
+        match (
+            ::std::convert::AsRef::<[u8]>::as_ref(&$data),
+            ::std::convert::AsRef::<[u8]>::as_ref(&$magic),
+        ) {
+            (data, magic) => {
+                $crate::ensure!(
+                    data.starts_with(magic),
+                    $kind,
+                    "expected magic bytes {}, found {}",
+                    $crate::__private::Hex(magic),
+                    $crate::__private::Hex(&data[..data.len().min(magic.len())])
+                );
+            }
+        }
+    };
+    ($data:expr, $magic:expr, $kind:expr, $($arg:tt)+) => {
+        match (
+            ::std::convert::AsRef::<[u8]>::as_ref(&$data),
+            ::std::convert::AsRef::<[u8]>::as_ref(&$magic),
+        ) {
+            (data, magic) => {
+                $crate::ensure!(data.starts_with(magic), $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a buffer is shorter than
+/// the size of a type.
+///
+/// The check passes when `buf.len() >= std::mem::size_of::<T>()`, which is the
+/// precondition for reinterpreting the start of a byte buffer as a `T` with a
+/// crate such as `bytemuck`. The type is passed as the second argument. When
+/// no message is given the error reports the buffer length, the type, and its
+/// size.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// #[repr(C)]
+/// struct MyHeader {
+///     magic: u32,
+///     len: u32,
+/// }
+///
+/// # fn main() -> std::io::Result<()> {
+/// let buf = [0u8; 16];
+/// ensure_type_size_eq!(buf, MyHeader, ErrorKind::InvalidData);
+/// ensure_type_size_eq!(buf, MyHeader, ErrorKind::InvalidData, "buffer too small for MyHeader");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_type_size_eq {
+    ($buf:expr, $ty:ty, $kind:expr $(,)?) => {
+        match (&$buf, ::std::mem::size_of::<$ty>()) {
+            (buf, size) => {
+                $crate::ensure!(
+                    buf.len() >= size,
+                    $kind,
+                    "buffer of {} bytes is too small for {} ({} bytes)",
+                    buf.len(),
+                    stringify!($ty),
+                    size
+                );
+            }
+        }
+    };
+    ($buf:expr, $ty:ty, $kind:expr, $($arg:tt)+) => {
+        match &$buf {
+            buf => {
+                $crate::ensure!(buf.len() >= ::std::mem::size_of::<$ty>(), $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a buffer is not made of
+/// exactly `count` whole chunks of `chunk_size` elements.
+///
+/// Two checks are made: `buf.len() % chunk_size == 0`, and then
+/// `buf.len() / chunk_size == count`. When no message is given the error says
+/// which of the two failed. A custom message is used for either failure.
+///
+/// A chunk size of zero fails the check with `ErrorKind::InvalidInput` and the
+/// message "chunk size must be non-zero", whatever kind and message are given.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let data = [0u8; 64];
+/// let (block_size, expected_block_count) = (16, 4);
+/// ensure_chunk_count_eq!(data, block_size, expected_block_count, ErrorKind::InvalidData);
+/// ensure_chunk_count_eq!(data, block_size, expected_block_count, ErrorKind::InvalidData, "expected {} blocks of {} bytes", expected_block_count, block_size);
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_chunk_count_eq {
+    ($buf:expr, $chunk_size:expr, $count:expr, $kind:expr $(,)?) => {
+        match (&$buf, $chunk_size, $count) {
+            (buf, chunk_size, count) => {
+                let (len, chunk_size, count): (usize, usize, usize) = (buf.len(), chunk_size, count);
+                $crate::ensure!(
+                    chunk_size != 0,
+                    ::std::io::ErrorKind::InvalidInput,
+                    "chunk size must be non-zero"
+                );
+                $crate::ensure!(
+                    len % chunk_size == 0,
+                    $kind,
+                    "data length {} is not divisible by chunk size {}",
+                    len,
+                    chunk_size
+                );
+                $crate::ensure!(
+                    len / chunk_size == count,
+                    $kind,
+                    "wrong number of chunks: expected {}, got {}",
+                    count,
+                    len / chunk_size
+                );
+            }
+        }
+    };
+    ($buf:expr, $chunk_size:expr, $count:expr, $kind:expr, $($arg:tt)+) => {
+        match (&$buf, $chunk_size, $count) {
+            (buf, chunk_size, count) => {
+                let (len, chunk_size, count): (usize, usize, usize) = (buf.len(), chunk_size, count);
+                $crate::ensure!(
+                    chunk_size != 0,
+                    ::std::io::ErrorKind::InvalidInput,
+                    "chunk size must be non-zero"
+                );
+                $crate::ensure!(len % chunk_size == 0 && len / chunk_size == count, $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if the length of a buffer is
+/// not within a range.
+///
+/// The range can be any of the standard range types: `a..b`, `a..=b`, `a..`,
+/// `..b`, `..=b`, and `..`. It is checked with [`RangeBounds::contains`], so
+/// exclusive upper bounds are honoured. The buffer can be anything with a
+/// `len()` method returning `usize`. When no message is given the error
+/// reports the length and the range.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`RangeBounds::contains`]: https://doc.rust-lang.org/std/ops/trait.RangeBounds.html#method.contains
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let input = "hostname";
+/// ensure_len_range!(input, 1..=256, ErrorKind::InvalidInput);
+/// ensure_len_range!(input, 1..=256, ErrorKind::InvalidInput, "length {} not in allowed range 1..=256", input.len());
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_len_range {
+    ($buf:expr, $range:expr, $kind:expr $(,)?) => {
+        match (&$buf, $range) {
+            (buf, range) => {
+                let len: usize = buf.len();
+                $crate::ensure!(
+                    ::std::ops::RangeBounds::<usize>::contains(&range, &len),
+                    $kind,
+                    "length {} is not in range {:?}",
+                    len,
+                    range
+                );
+            }
+        }
+    };
+    ($buf:expr, $range:expr, $kind:expr, $($arg:tt)+) => {
+        match (&$buf, $range) {
+            (buf, range) => {
+                let len: usize = buf.len();
+                $crate::ensure!(::std::ops::RangeBounds::<usize>::contains(&range, &len), $kind, $($arg)+);
+            }
+        }
+    };
+}
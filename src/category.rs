@@ -0,0 +1,92 @@
+use std::io::{self, ErrorKind};
+
+/// A coarse classification of [`ErrorKind`] values.
+///
+/// Retry policies can match on the category rather than enumerating specific
+/// error kinds.
+///
+/// [`ErrorKind`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// let err = format_err!(ErrorKind::TimedOut, "dial timed out");
+/// assert_eq!(err.category(), IoErrorCategory::Transient);
+/// assert_eq!(IoErrorCategory::from(ErrorKind::NotFound), IoErrorCategory::Permanent);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IoErrorCategory {
+    /// The operation may succeed if retried: `WouldBlock`, `TimedOut`, and
+    /// `Interrupted`.
+    Transient,
+    /// Retrying the operation will not help: `NotFound`, `PermissionDenied`,
+    /// `AlreadyExists`, `InvalidInput`, `InvalidData`, `BrokenPipe`,
+    /// `ConnectionReset`, and `ConnectionAborted`.
+    Permanent,
+    /// Any other kind, including `Other`.
+    Unknown,
+}
+
+impl From<ErrorKind> for IoErrorCategory {
+    fn from(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted => {
+                IoErrorCategory::Transient
+            }
+            ErrorKind::NotFound
+            | ErrorKind::PermissionDenied
+            | ErrorKind::AlreadyExists
+            | ErrorKind::InvalidInput
+            | ErrorKind::InvalidData
+            | ErrorKind::BrokenPipe
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted => IoErrorCategory::Permanent,
+            _ => IoErrorCategory::Unknown,
+        }
+    }
+}
+
+impl From<&io::Error> for IoErrorCategory {
+    fn from(error: &io::Error) -> Self {
+        error.kind().into()
+    }
+}
+
+impl From<io::Error> for IoErrorCategory {
+    fn from(error: io::Error) -> Self {
+        error.kind().into()
+    }
+}
+
+/// Returns the [`IoErrorCategory`] of an [`io::Error`] or [`ErrorKind`].
+///
+/// The argument is taken by reference, so the error remains usable in the
+/// arms of a `match` on the category.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ErrorKind`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// let err = format_err!(ErrorKind::WouldBlock);
+/// let action = match categorize_io_err!(err) {
+///     IoErrorCategory::Transient => "retry",
+///     IoErrorCategory::Permanent => "fail",
+///     IoErrorCategory::Unknown => "log and fail",
+/// };
+/// assert_eq!(action, "retry");
+/// assert_eq!(categorize_io_err!(ErrorKind::Other), IoErrorCategory::Unknown);
+/// ```
+#[macro_export]
+macro_rules! categorize_io_err {
+    ($err:expr $(,)?) => {
+        $crate::__private::Categorize::io_category(&$err)
+    };
+}
@@ -0,0 +1,107 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// An explicitly linked chain of [`io::Error`]s.
+///
+/// The first error in the chain is the outermost one, and each following
+/// error is the cause of the one before it. Errors can be inspected with
+/// [`ErrorChain::iter`] without going through [`Error::source`].
+///
+/// The chain converts into a single `io::Error` with the kind of the first
+/// error, which can be downcast back into an `ErrorChain`. Its [`Display`]
+/// implementation prints every error in the chain, separated by `": "`. Its
+/// [`Error::source`] is the rest of the chain, starting at the next error, so
+/// error reporters that walk the sources reach every error in it.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`Error::source`]: https://doc.rust-lang.org/std/error/trait.Error.html#method.source
+/// [`Display`]: https://doc.rust-lang.org/std/fmt/trait.Display.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+///
+/// let chain = ErrorChain::new(format_err!(ErrorKind::InvalidData, "loading config"))
+///     .chain(format_err!(ErrorKind::NotFound, "opening config.toml"));
+/// assert_eq!(chain.to_string(), "loading config: opening config.toml");
+///
+/// let err: io::Error = chain.into();
+/// assert_eq!(err.kind(), ErrorKind::InvalidData);
+/// let chain = downcast_io_err!(err, ErrorChain).unwrap();
+/// assert_eq!(chain.iter().count(), 2);
+/// ```
+#[derive(Debug)]
+pub struct ErrorChain {
+    head: io::Error,
+    tail: Option<Box<ErrorChain>>,
+}
+
+impl ErrorChain {
+    /// Creates a chain containing a single error.
+    pub fn new(head: io::Error) -> Self {
+        Self { head, tail: None }
+    }
+
+    /// Appends an error to the end of the chain, as the cause of the last
+    /// error.
+    pub fn chain(mut self, next: io::Error) -> Self {
+        let mut last = &mut self;
+        while let Some(ref mut tail) = last.tail {
+            last = tail;
+        }
+        last.tail = Some(Box::new(ErrorChain::new(next)));
+        self
+    }
+
+    /// Returns the first error in the chain.
+    pub fn head(&self) -> &io::Error {
+        &self.head
+    }
+
+    /// Returns an iterator over the errors in the chain, starting with the
+    /// outermost one.
+    pub fn iter(&self) -> impl Iterator<Item = &io::Error> + '_ {
+        let mut next = Some(self);
+        std::iter::from_fn(move || {
+            let chain = next?;
+            next = chain.tail.as_deref();
+            Some(&chain.head)
+        })
+    }
+}
+
+impl fmt::Display for ErrorChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, err) in self.iter().enumerate() {
+            if index > 0 {
+                f.write_str(": ")?;
+            }
+            write!(f, "{}", err)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ErrorChain {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.tail {
+            Some(tail) => Some(&**tail),
+            None => None,
+        }
+    }
+}
+
+impl From<io::Error> for ErrorChain {
+    fn from(head: io::Error) -> Self {
+        Self::new(head)
+    }
+}
+
+impl From<ErrorChain> for io::Error {
+    fn from(chain: ErrorChain) -> Self {
+        io::Error::new(chain.head.kind(), chain)
+    }
+}
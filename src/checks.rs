@@ -0,0 +1,157 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::panic::Location;
+
+use crate::IntoIoError;
+
+/// A list of checks that are run together, returning the first failure.
+///
+/// This is an alternative to a sequence of [`ensure!`] calls for checks that
+/// are assembled at runtime, such as from a configuration table. Each `add`
+/// method mirrors one of the message forms of `ensure!`, and evaluates its
+/// condition right away. [`add_lazy`] defers the check until [`run`] is called.
+/// Checks are run in the order they were added, and `run` stops at the first
+/// one that fails.
+///
+/// [`ensure!`]: crate::ensure
+/// [`add_lazy`]: IoEnsureChain::add_lazy
+/// [`run`]: IoEnsureChain::run
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// let (len, max) = (512, 256);
+/// let err = IoEnsureChain::new()
+///     .add(len > 0, ErrorKind::InvalidInput, "empty input")
+///     .add_lazy(|| (len <= max, ErrorKind::InvalidInput, format!("{} exceeds {}", len, max)))
+///     .run()
+///     .unwrap_err();
+/// assert_eq!(err.to_string(), "512 exceeds 256");
+/// ```
+#[derive(Default)]
+pub struct IoEnsureChain<'a> {
+    checks: Vec<Check<'a>>,
+}
+
+enum Check<'a> {
+    Eager(Option<io::Error>),
+    Lazy(Box<dyn FnOnce() -> Option<io::Error> + 'a>),
+}
+
+impl<'a> IoEnsureChain<'a> {
+    /// Creates an empty chain, which passes when run.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a check failing with a message, like `ensure!(cond, kind, msg)`.
+    ///
+    /// The message may be a string or any error type, as with
+    /// `io::Error::new`.
+    #[track_caller]
+    pub fn add<M>(self, cond: bool, kind: ErrorKind, msg: M) -> Self
+    where
+        M: Into<Box<dyn Error + Send + Sync>> + 'static,
+    {
+        let err = match cond {
+            true => None,
+            false => Some(crate::__private::new_payload_error(kind, msg)),
+        };
+        self.push(err)
+    }
+
+    /// Adds a check failing without a message, like `ensure!(cond, kind)`.
+    ///
+    /// Anything implementing [`IntoIoError`] can be passed in place of the
+    /// kind.
+    ///
+    /// [`IntoIoError`]: crate::IntoIoError
+    pub fn add_kind(self, cond: bool, kind: impl IntoIoError) -> Self {
+        let err = match cond {
+            true => None,
+            false => Some(kind.into_io_error()),
+        };
+        self.push(err)
+    }
+
+    /// Adds a check failing with a formatted message, like
+    /// `ensure!(cond, kind, "{}", arg)`.
+    ///
+    /// The message is only formatted if the check fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use io_ensure::*;
+    /// use std::io::ErrorKind;
+    ///
+    /// let port = 80;
+    /// let res = IoEnsureChain::new()
+    ///     .add_fmt(port >= 1024, ErrorKind::PermissionDenied, format_args!("port {} is privileged", port))
+    ///     .run();
+    /// assert_eq!(res.unwrap_err().to_string(), "port 80 is privileged");
+    /// ```
+    #[track_caller]
+    pub fn add_fmt(self, cond: bool, kind: ErrorKind, args: fmt::Arguments<'_>) -> Self {
+        let err = match cond {
+            true => None,
+            false => Some(crate::__private::new_error_fmt(kind, args)),
+        };
+        self.push(err)
+    }
+
+    /// Adds a check that is evaluated when the chain is run.
+    ///
+    /// The closure returns the condition, the kind, and the message to use if
+    /// the condition is `false`. It is not called if an earlier check fails.
+    #[track_caller]
+    pub fn add_lazy<F, M>(mut self, check: F) -> Self
+    where
+        F: FnOnce() -> (bool, ErrorKind, M) + 'a,
+        M: Into<Box<dyn Error + Send + Sync>> + 'static,
+    {
+        let location = Location::caller();
+        self.checks.push(Check::Lazy(Box::new(move || {
+            let (cond, kind, msg) = check();
+            if cond {
+                return None;
+            }
+            let mut err = crate::__private::new_payload_error(kind, msg);
+            crate::__private::set_location(&mut err, location);
+            Some(err)
+        })));
+        self
+    }
+
+    /// Runs the checks in order, returning the error of the first one that
+    /// fails.
+    pub fn run(self) -> io::Result<()> {
+        for check in self.checks {
+            let err = match check {
+                Check::Eager(err) => err,
+                Check::Lazy(check) => check(),
+            };
+            if let Some(err) = err {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    fn push(mut self, err: Option<io::Error>) -> Self {
+        self.checks.push(Check::Eager(err));
+        self
+    }
+}
+
+impl fmt::Debug for IoEnsureChain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IoEnsureChain")
+            .field("checks", &self.checks.len())
+            .finish()
+    }
+}
@@ -0,0 +1,169 @@
+//! Macros combining multiple conditions.
+
+/// Exits a function early with an [`io::Error`] if any of the conditions is
+/// not satisfied.
+///
+/// The conditions are evaluated in order, stopping at the first one that
+/// fails. When no message is given the error reports the index and the source
+/// text of the failing condition.
+///
+/// Conditions can also be labeled as `"label" => condition`, in which case the
+/// label of the failing condition is used as the message when no message is
+/// given.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+///
+/// fn validate(val: i32) -> io::Result<()> {
+///     ensure_all_of!([val > 0, val % 2 == 0], ErrorKind::InvalidData, "validation failed");
+///     Ok(())
+/// }
+///
+/// fn validate_labeled(val: i32) -> io::Result<()> {
+///     ensure_all_of!([
+///         "must be positive" => val > 0,
+///         "must be even" => val % 2 == 0,
+///     ], ErrorKind::InvalidData);
+///     Ok(())
+/// }
+///
+/// assert!(validate(4).is_ok());
+/// assert_eq!(validate_labeled(3).unwrap_err().to_string(), "must be even");
+/// ```
+#[macro_export]
+macro_rules! ensure_all_of {
+    ([$($label:literal => $cond:expr),+ $(,)?], $kind:expr $(,)?) => {
+        match $crate::__first_failed!($($cond),+) {
+            ::std::option::Option::Some(index) => {
+                let labels: &[&str] = &[$($label),+];
+                $crate::__ensure_bail!($($cond)&&+, $kind, "{}", labels[index]);
+            }
+            ::std::option::Option::None => {}
+        }
+    };
+    ([$($label:literal => $cond:expr),+ $(,)?], $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!($crate::__first_failed!($($cond),+).is_none(), $kind, $($arg)+);
+    };
+    ([$($cond:expr),+ $(,)?], $kind:expr $(,)?) => {
+        match $crate::__first_failed!($($cond),+) {
+            ::std::option::Option::Some(index) => {
+                let conds: &[&str] = &[$(stringify!($cond)),+];
+                $crate::__ensure_bail!(
+                    $($cond)&&+,
+                    $kind,
+                    "condition {} failed: {}",
+                    index,
+                    conds[index]
+                );
+            }
+            ::std::option::Option::None => {}
+        }
+    };
+    ([$($cond:expr),+ $(,)?], $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!($crate::__first_failed!($($cond),+).is_none(), $kind, $($arg)+);
+    };
+}
+
+/// Evaluates conditions in order and returns the index of the first one that
+/// is false.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __first_failed {
+    ($($cond:expr),+) => {{
+        let mut index = 0usize;
+        let mut failed = ::std::option::Option::None;
+        $(
+            index += 1;
+            if failed.is_none() && !$cond {
+                failed = ::std::option::Option::Some(index - 1);
+            }
+        )+
+        failed
+    }};
+}
+
+/// Exits a function early with an [`io::Error`] if none of the conditions are
+/// satisfied.
+///
+/// The conditions are evaluated in order, stopping at the first one that
+/// holds. When no message is given the error reports how many conditions were
+/// checked.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (has_read_perm, has_admin_role, is_owner) = (false, true, false);
+/// ensure_any_of!([has_read_perm, has_admin_role, is_owner], ErrorKind::PermissionDenied);
+/// ensure_any_of!([has_read_perm, has_admin_role], ErrorKind::PermissionDenied, "no sufficient permission");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_any_of {
+    ([$($cond:expr),+ $(,)?], $kind:expr $(,)?) => {
+        $crate::ensure!(
+            $($cond)||+,
+            $kind,
+            "all {} conditions failed",
+            [$(stringify!($cond)),+].len()
+        );
+    };
+    ([$($cond:expr),+ $(,)?], $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!($($cond)||+, $kind, $($arg)+);
+    };
+}
+
+/// Runs a block of checks and reports every failure at once.
+///
+/// Each `check!(...)` takes the same arguments as [`ensure!`]. Unlike a
+/// sequence of `ensure!` calls, a failing check does not stop the remaining
+/// checks from running. The block evaluates to an `io::Result<()>`: `Ok(())` if
+/// every check passed, or an `io::Error` wrapping a [`MultiError`] with all the
+/// failures in order.
+///
+/// [`ensure!`]: crate::ensure
+/// [`MultiError`]: crate::MultiError
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+///
+/// fn validate(a: u8, b: u8, c: i8) -> io::Result<()> {
+///     batch_ensure! {
+///         check!(a == b, ErrorKind::InvalidInput, "a != b");
+///         check!(c > 0, ErrorKind::InvalidInput, "c must be positive");
+///     }?;
+///     Ok(())
+/// }
+///
+/// let err = validate(1, 2, -1).unwrap_err();
+/// assert_eq!(err.to_string(), "2 checks failed: a != b; c must be positive");
+/// ```
+#[macro_export]
+macro_rules! batch_ensure {
+    ($(check!($($check:tt)*));* $(;)?) => {{
+        let mut errors = ::std::vec::Vec::new();
+        $(
+            let check = || -> ::std::io::Result<()> {
+                $crate::ensure!($($check)*);
+                ::std::result::Result::Ok(())
+            };
+            if let ::std::result::Result::Err(err) = check() {
+                errors.push(err);
+            }
+        )*
+        $crate::__private::batch_result(errors)
+    }};
+}
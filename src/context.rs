@@ -0,0 +1,133 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::panic::Location;
+
+/// An [`io::Error`] with context messages collected from each layer it passed
+/// through.
+///
+/// This is produced by [`IoContext::context`] and [`IoContext::with_context`].
+/// Adding context to an error that already carries a `MultiContext` appends to
+/// its list instead of wrapping the error again, so the original error stays
+/// one level down no matter how many layers add context. It is kept as the
+/// [`source`], and the location where the first context was added is recorded
+/// like in an [`IoEnsureError`]. The [`Display`] implementation prints the original message
+/// followed by the contexts as a numbered list, in the order they were added.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`IoContext::context`]: crate::IoContext::context
+/// [`IoContext::with_context`]: crate::IoContext::with_context
+/// [`source`]: https://doc.rust-lang.org/std/error/trait.Error.html#method.source
+/// [`IoEnsureError`]: crate::IoEnsureError
+/// [`Display`]: https://doc.rust-lang.org/std/fmt/trait.Display.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::error::Error;
+/// use std::io::{self, ErrorKind};
+///
+/// let res: io::Result<()> = Err(format_err!(ErrorKind::UnexpectedEof, "early eof"));
+/// let err = res
+///     .context("reading index header")
+///     .context("opening database")
+///     .unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+/// assert_eq!(
+///     err.to_string(),
+///     "early eof\ncontext:\n    0: reading index header\n    1: opening database"
+/// );
+///
+/// let multi = downcast_io_err!(err, MultiContext).unwrap();
+/// assert_eq!(multi.contexts().len(), 2);
+/// assert_eq!(multi.source().unwrap().to_string(), "early eof");
+/// ```
+#[derive(Debug)]
+pub struct MultiContext {
+    contexts: Vec<String>,
+    source: io::Error,
+    location: &'static Location<'static>,
+}
+
+impl MultiContext {
+    /// Creates a `MultiContext` for an error, with no contexts yet.
+    ///
+    /// The location of the caller is recorded.
+    #[track_caller]
+    pub fn new(source: io::Error) -> Self {
+        Self {
+            contexts: Vec::new(),
+            source,
+            location: Location::caller(),
+        }
+    }
+
+    /// Adds a context message.
+    pub fn push(&mut self, context: impl Into<String>) {
+        self.contexts.push(context.into());
+    }
+
+    /// Returns the context messages in the order they were added.
+    pub fn contexts(&self) -> &[String] {
+        &self.contexts
+    }
+
+    /// Returns the location where the `MultiContext` was created.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    /// Returns the original error.
+    pub fn get_ref(&self) -> &io::Error {
+        &self.source
+    }
+
+    /// Returns the original error, discarding the contexts.
+    pub fn into_inner(self) -> io::Error {
+        self.source
+    }
+
+    /// Adds a context message to an error, reusing its `MultiContext` if it
+    /// already has one.
+    #[cold]
+    #[inline(never)]
+    #[track_caller]
+    pub(crate) fn add(mut error: io::Error, context: String) -> io::Error {
+        if let Some(multi) = error
+            .get_mut()
+            .and_then(|payload| payload.downcast_mut::<Self>())
+        {
+            multi.push(context);
+            return error;
+        }
+        let mut multi = Self::new(error);
+        multi.push(context);
+        multi.into()
+    }
+}
+
+impl fmt::Display for MultiContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)?;
+        if !self.contexts.is_empty() {
+            write!(f, "\ncontext:")?;
+        }
+        for (index, context) in self.contexts.iter().enumerate() {
+            write!(f, "\n    {}: {}", index, context)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for MultiContext {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<MultiContext> for io::Error {
+    fn from(multi: MultiContext) -> Self {
+        io::Error::new(multi.source.kind(), multi)
+    }
+}
@@ -0,0 +1,311 @@
+use std::error::Error;
+use std::io;
+use std::ops::ControlFlow;
+use std::task::Poll;
+
+/// Conversion into an [`io::Error`].
+///
+/// Implementing this trait lets a type choose its own [`ErrorKind`] when it is
+/// turned into an `io::Error`, without requiring a `From` implementation on
+/// `io::Error` itself. Any `IntoIoError` can be passed as the sole argument to
+/// [`format_err!`] and [`ensure!`], or as the payload following a kind, as in
+/// `format_err!(ErrorKind::Other, MyError)`. As a payload it is converted and
+/// attached as the source of an error with the given kind.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ErrorKind`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html
+/// [`format_err!`]: crate::format_err
+/// [`ensure!`]: crate::ensure
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+///
+/// struct QuotaExceeded(u64);
+///
+/// impl IntoIoError for QuotaExceeded {
+///     fn into_io_error(self) -> io::Error {
+///         io::Error::new(ErrorKind::Other, format!("quota of {} bytes exceeded", self.0))
+///     }
+/// }
+///
+/// let err = format_err!(QuotaExceeded(1024));
+/// assert_eq!(err.to_string(), "quota of 1024 bytes exceeded");
+///
+/// let err = format_err!(ErrorKind::PermissionDenied, QuotaExceeded(512));
+/// assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+/// assert_eq!(err.to_string(), "quota of 512 bytes exceeded");
+/// ```
+pub trait IntoIoError {
+    /// Converts `self` into an `io::Error`.
+    fn into_io_error(self) -> io::Error;
+}
+
+impl IntoIoError for io::Error {
+    fn into_io_error(self) -> io::Error {
+        self
+    }
+}
+
+/// Creates an error without a payload, which does not allocate.
+impl IntoIoError for io::ErrorKind {
+    fn into_io_error(self) -> io::Error {
+        io::Error::from(self)
+    }
+}
+
+/// Creates an error of kind `ErrorKind::Other`.
+impl IntoIoError for String {
+    fn into_io_error(self) -> io::Error {
+        io::Error::other(self)
+    }
+}
+
+/// Creates an error of kind `ErrorKind::Other`.
+impl IntoIoError for &'static str {
+    fn into_io_error(self) -> io::Error {
+        io::Error::other(self)
+    }
+}
+
+/// Creates an error of kind `ErrorKind::Other`.
+impl IntoIoError for Box<dyn Error + Send + Sync> {
+    fn into_io_error(self) -> io::Error {
+        io::Error::other(self)
+    }
+}
+
+/// Conversion from an [`io::Error`] back into the error stored inside it.
+///
+/// This is the reverse of [`IntoIoError`]. It is implemented for every error
+/// type that can be stored as an `io::Error` payload, and returns the original
+/// `io::Error` if the payload is of a different type.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::fmt;
+/// use std::io::{self, ErrorKind};
+///
+/// #[derive(Debug)]
+/// struct BadMagic;
+///
+/// impl fmt::Display for BadMagic {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         f.write_str("bad magic number")
+///     }
+/// }
+///
+/// impl std::error::Error for BadMagic {}
+///
+/// let err = format_err!(ErrorKind::InvalidData, BadMagic);
+/// assert!(BadMagic::from_io_error(err).is_ok());
+///
+/// let err = format_err!(ErrorKind::InvalidData, "something else");
+/// assert!(BadMagic::from_io_error(err).is_err());
+/// ```
+pub trait FromIoError: Sized {
+    /// Extracts `Self` from an `io::Error`, or returns the error unchanged.
+    fn from_io_error(error: io::Error) -> Result<Self, io::Error>;
+}
+
+impl<E> FromIoError for E
+where
+    E: Error + Send + Sync + 'static,
+{
+    fn from_io_error(error: io::Error) -> Result<Self, io::Error> {
+        error.downcast()
+    }
+}
+
+/// Conversion from an [`io::Error`] into the return value of a function.
+///
+/// [`ensure_in!`] returns its error through this trait, which lets the same
+/// checks be used in functions with any return type that knows how to hold an
+/// `io::Error`. It is implemented for `Result`, `Poll<Result>`,
+/// `Poll<Option<Result>>`, and `ControlFlow`, in each case for any error type
+/// that implements `From<io::Error>`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_in!`]: crate::ensure_in
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+///
+/// struct Response {
+///     status: u16,
+///     body: String,
+/// }
+///
+/// impl FromIoEnsureError for Response {
+///     fn from_io_ensure_error(err: io::Error) -> Self {
+///         let status = match err.kind() {
+///             ErrorKind::NotFound => 404,
+///             _ => 400,
+///         };
+///         Response { status, body: err.to_string() }
+///     }
+/// }
+///
+/// fn handle(path: &str) -> Response {
+///     ensure_in!(path.starts_with('/'), ErrorKind::InvalidInput, "path must be absolute");
+///     ensure_in!(path == "/", ErrorKind::NotFound, "no route for {}", path);
+///     Response { status: 200, body: "ok".into() }
+/// }
+///
+/// assert_eq!(handle("/").status, 200);
+/// assert_eq!(handle("/users").status, 404);
+/// assert_eq!(handle("users").body, "path must be absolute");
+/// ```
+pub trait FromIoEnsureError {
+    /// Converts a failed check's error into the return value.
+    fn from_io_ensure_error(err: io::Error) -> Self;
+}
+
+impl<T, E> FromIoEnsureError for Result<T, E>
+where
+    E: From<io::Error>,
+{
+    fn from_io_ensure_error(err: io::Error) -> Self {
+        Err(E::from(err))
+    }
+}
+
+impl<T, E> FromIoEnsureError for Poll<Result<T, E>>
+where
+    E: From<io::Error>,
+{
+    fn from_io_ensure_error(err: io::Error) -> Self {
+        Poll::Ready(Err(E::from(err)))
+    }
+}
+
+impl<T, E> FromIoEnsureError for Poll<Option<Result<T, E>>>
+where
+    E: From<io::Error>,
+{
+    fn from_io_ensure_error(err: io::Error) -> Self {
+        Poll::Ready(Some(Err(E::from(err))))
+    }
+}
+
+/// Breaks with the error.
+impl<B, C> FromIoEnsureError for ControlFlow<B, C>
+where
+    B: From<io::Error>,
+{
+    fn from_io_ensure_error(err: io::Error) -> Self {
+        ControlFlow::Break(B::from(err))
+    }
+}
+
+/// Exits a function early if the condition is not satisfied, converting the
+/// [`io::Error`] into the function's return type.
+///
+/// Takes the same arguments as [`ensure!`], but returns
+/// `FromIoEnsureError::from_io_ensure_error(err)` instead of `Err(err)`, so it
+/// works in any function whose return type implements [`FromIoEnsureError`].
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure!`]: crate::ensure
+/// [`FromIoEnsureError`]: crate::FromIoEnsureError
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+/// use std::ops::ControlFlow;
+///
+/// let lens = [3, 5, 0, 2];
+/// let res = lens.iter().try_for_each(|&len| -> ControlFlow<io::Error> {
+///     ensure_in!(len > 0, ErrorKind::InvalidData, "empty record");
+///     ControlFlow::Continue(())
+/// });
+/// assert!(matches!(res, ControlFlow::Break(err) if err.to_string() == "empty record"));
+/// ```
+#[macro_export]
+macro_rules! ensure_in {
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            let err = $crate::format_err!($($arg)+);
+            $crate::__ensure_failed!($cond, err);
+            return $crate::FromIoEnsureError::from_io_ensure_error(err);
+        }
+    };
+}
+
+/// Returns a reference to the payload of an [`io::Error`] if it is of the
+/// given type.
+///
+/// Expands to an `Option<&T>`, which is `None` if the error has no payload or
+/// if the payload is of a different type.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// let inner = format_err!(ErrorKind::NotFound, "missing");
+/// let err = format_err!(ErrorKind::Other, inner);
+///
+/// let inner = downcast_io_err!(err, std::io::Error).unwrap();
+/// assert_eq!(inner.kind(), ErrorKind::NotFound);
+/// ```
+#[macro_export]
+macro_rules! downcast_io_err {
+    ($err:expr, $ty:ty $(,)?) => {
+        ::std::io::Error::get_ref(&$err).and_then(|inner| inner.downcast_ref::<$ty>())
+    };
+}
+
+/// Remaps the [`ErrorKind`] of an [`io::Error`].
+///
+/// Takes an `io::Error` followed by `from => to` pairs, where `from` is a
+/// pattern matched against the error's kind. The first matching pair decides
+/// the new kind; errors whose kind matches no pair are returned unchanged.
+///
+/// The message and payload of the error are preserved, so the remapped error
+/// can still be downcast to its original payload type. An [`IoEnsureError`]
+/// payload is given the new kind as well, keeping its location, fields and
+/// source.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ErrorKind`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html
+/// [`IoEnsureError`]: crate::IoEnsureError
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// let err = format_err!(ErrorKind::TimedOut, "read timed out");
+/// let err = normalize_io_err!(err,
+///     ErrorKind::TimedOut | ErrorKind::Interrupted => ErrorKind::WouldBlock,
+///     ErrorKind::NotFound => ErrorKind::InvalidInput,
+/// );
+/// assert_eq!(err.kind(), ErrorKind::WouldBlock);
+/// assert_eq!(err.to_string(), "read timed out");
+/// ```
+#[macro_export]
+macro_rules! normalize_io_err {
+    ($err:expr, $($($from:pat)|+ => $to:expr),+ $(,)?) => {{
+        let err: ::std::io::Error = $err;
+        match err.kind() {
+            $($($from)|+ => $crate::__private::with_kind(err, $to),)+
+            _ => err,
+        }
+    }};
+}
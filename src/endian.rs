@@ -0,0 +1,108 @@
+//! Macros validating byte order.
+
+use std::fmt;
+
+/// The byte order indicated by a format's byte-order mark, as checked by
+/// [`ensure_byte_order!`].
+///
+/// A two-byte mark converts into a `ByteOrder` using the TIFF convention:
+/// `b"II"` (`0x4949`) is little-endian and `b"MM"` (`0x4d4d`) is big-endian.
+/// Any other mark becomes [`ByteOrder::Unknown`].
+///
+/// [`ensure_byte_order!`]: crate::ensure_byte_order
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::ByteOrder;
+///
+/// assert_eq!(ByteOrder::from(*b"II"), ByteOrder::LittleEndian);
+/// assert_eq!(ByteOrder::from([0x4d, 0x4d]), ByteOrder::BigEndian);
+/// assert_eq!(ByteOrder::from(*b"XY"), ByteOrder::Unknown(*b"XY"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ByteOrder {
+    /// The most significant byte comes first.
+    BigEndian,
+    /// The least significant byte comes first.
+    LittleEndian,
+    /// A byte-order mark that is not recognized.
+    Unknown([u8; 2]),
+}
+
+impl From<[u8; 2]> for ByteOrder {
+    fn from(mark: [u8; 2]) -> Self {
+        match &mark {
+            b"MM" => ByteOrder::BigEndian,
+            b"II" => ByteOrder::LittleEndian,
+            _ => ByteOrder::Unknown(mark),
+        }
+    }
+}
+
+impl fmt::Display for ByteOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ByteOrder::BigEndian => f.write_str("big-endian"),
+            ByteOrder::LittleEndian => f.write_str("little-endian"),
+            ByteOrder::Unknown(mark) => {
+                write!(f, "unknown byte-order mark {}", crate::__private::Hex(mark))
+            }
+        }
+    }
+}
+
+/// Exits a function early with an [`io::Error`] if a byte-order mark does not
+/// indicate the expected byte order.
+///
+/// The mark may be a [`ByteOrder`] or anything converting into one, such as a
+/// two-byte `[u8; 2]` array. The expected order is a `ByteOrder`. An unknown
+/// mark never matches. When no message is given the error reports both
+/// orders.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ByteOrder`]: crate::ByteOrder
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let byte_order_mark = *b"MM";
+/// ensure_byte_order!(byte_order_mark, ByteOrder::BigEndian, ErrorKind::InvalidData);
+/// ensure_byte_order!(byte_order_mark, ByteOrder::BigEndian, ErrorKind::InvalidData, "unexpected byte order: expected big-endian");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_byte_order {
+    ($mark:expr, $expected:expr, $kind:expr $(,)?) => {
+        match (
+            <$crate::ByteOrder as ::std::convert::From<_>>::from($mark),
+            $expected,
+        ) {
+            (actual, expected) => {
+                let expected: $crate::ByteOrder = expected;
+                $crate::ensure!(
+                    actual == expected,
+                    $kind,
+                    "expected {} byte order, found {}",
+                    expected,
+                    actual
+                );
+            }
+        }
+    };
+    ($mark:expr, $expected:expr, $kind:expr, $($arg:tt)+) => {
+        match (
+            <$crate::ByteOrder as ::std::convert::From<_>>::from($mark),
+            $expected,
+        ) {
+            (actual, expected) => {
+                let expected: $crate::ByteOrder = expected;
+                $crate::ensure!(actual == expected, $kind, $($arg)+);
+            }
+        }
+    };
+}
@@ -0,0 +1,82 @@
+//! Macros reading the environment.
+
+/// Reads an environment variable as a `String`, exiting a function early with
+/// an [`io::Error`] if it is not set.
+///
+/// The macro evaluates to the variable's value. It is read with
+/// [`std::env::var_os`]; a value that is not valid UTF-8 fails with
+/// `ErrorKind::InvalidData` whatever kind was passed. Use
+/// [`ensure_env_var_os!`] to accept any value. When no message is given the
+/// error names the variable.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`std::env::var_os`]: https://doc.rust-lang.org/std/env/fn.var_os.html
+/// [`ensure_env_var_os!`]: crate::ensure_env_var_os
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// # std::env::set_var("DATABASE_URL", "postgres://localhost/app");
+/// let db_url = ensure_env_var!("DATABASE_URL", ErrorKind::NotFound);
+/// let db_url = ensure_env_var!("DATABASE_URL", ErrorKind::NotFound, "DATABASE_URL must be set");
+/// # assert_eq!(db_url, "postgres://localhost/app");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_env_var {
+    ($name:expr, $($arg:tt)+) => {
+        match ::std::convert::AsRef::<::std::ffi::OsStr>::as_ref(&$name) {
+            name => match $crate::__private::env_string(name, $crate::ensure_env_var_os!(name, $($arg)+)) {
+                ::std::result::Result::Ok(value) => value,
+                ::std::result::Result::Err(err) => {
+                    $crate::__ensure_failed!($name, err);
+                    return ::std::result::Result::Err(::std::convert::From::from(err));
+                }
+            },
+        }
+    };
+}
+
+/// Reads an environment variable as an `OsString`, exiting a function early
+/// with an [`io::Error`] if it is not set.
+///
+/// Like [`ensure_env_var!`], but the value does not have to be valid UTF-8.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_env_var!`]: crate::ensure_env_var
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// # std::env::set_var("APP_DATA_DIR", "/var/lib/app");
+/// let dir = ensure_env_var_os!("APP_DATA_DIR", ErrorKind::NotFound);
+/// # assert_eq!(dir, "/var/lib/app");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_env_var_os {
+    ($name:expr, $kind:expr $(,)?) => {
+        match ::std::convert::AsRef::<::std::ffi::OsStr>::as_ref(&$name) {
+            name => $crate::ensure_env_var_os!(
+                name,
+                $kind,
+                "environment variable {:?} is not set",
+                name
+            ),
+        }
+    };
+    ($name:expr, $kind:expr, $($arg:tt)+) => {
+        match ::std::env::var_os(&$name) {
+            ::std::option::Option::Some(value) => value,
+            ::std::option::Option::None => $crate::__ensure_bail!($name, $kind, $($arg)+),
+        }
+    };
+}
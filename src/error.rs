@@ -0,0 +1,237 @@
+#[cfg(feature = "backtrace")]
+use std::backtrace::Backtrace;
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::panic::Location;
+
+use crate::Fields;
+
+/// The payload of errors built by [`format_err!`] and the `ensure*!` macros.
+///
+/// Every error these macros create from a message holds an `IoEnsureError`,
+/// so downstream code can inspect them the same way. It records:
+///
+/// - the [`ErrorKind`] and the message,
+/// - the source error, if one was attached with `; source = err` or by a
+///   check that wraps another error, such as [`ensure_ok!`],
+/// - the location of the macro call, which helps tell apart checks with
+///   similar messages,
+/// - the [`Fields`] given in a `format_err!` field block.
+///
+/// The payload is read back by calling [`downcast_ref`] on the result of
+/// [`io::Error::get_ref`], or with the [`downcast_io_err!`] shorthand. Errors
+/// built without a message, such as `format_err!(ErrorKind::NotFound)`, and
+/// errors that wrap another error, as in `format_err!(kind, err)`, keep their
+/// original payload instead.
+///
+/// A message given as a string literal without interpolation is stored as a
+/// `&'static str` rather than copied into a `String`. This saves a copy, but
+/// the error still allocates: `io::Error::new` boxes the payload, and std has
+/// no stable way to build an `io::Error` with a message without allocating.
+/// Only errors without a message, such as `format_err!(ErrorKind::NotFound)`,
+/// are built without allocating.
+///
+/// Its [`Display`] implementation writes only the message, so the error reads
+/// the same as one built with `io::Error::new`. The alternate form `{:#}`
+/// appends the fields and the location, and `Debug` includes them too.
+///
+/// With the `backtrace` feature enabled, the payload also captures a
+/// [`Backtrace`] when it is created, which the alternate `{:#?}` form prints.
+///
+/// [`format_err!`]: crate::format_err
+/// [`ensure_ok!`]: crate::ensure_ok
+/// [`downcast_io_err!`]: crate::downcast_io_err
+/// [`ErrorKind`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html
+/// [`downcast_ref`]: https://doc.rust-lang.org/std/error/trait.Error.html#method.downcast_ref
+/// [`io::Error::get_ref`]: https://doc.rust-lang.org/std/io/struct.Error.html#method.get_ref
+/// [`Display`]: https://doc.rust-lang.org/std/fmt/trait.Display.html
+/// [`Backtrace`]: https://doc.rust-lang.org/std/backtrace/struct.Backtrace.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::error::Error;
+/// use std::io::{self, ErrorKind};
+///
+/// fn parse_len(input: &str) -> io::Result<usize> {
+///     let len = ensure_ok!(input.parse::<usize>(), ErrorKind::InvalidData, "bad length {:?}", input);
+///     ensure!(len <= 64, ErrorKind::InvalidInput, "length {} is too large", len);
+///     Ok(len)
+/// }
+///
+/// let err = parse_len("100").unwrap_err();
+/// assert_eq!(err.to_string(), "length 100 is too large");
+///
+/// let payload = err.get_ref().unwrap().downcast_ref::<IoEnsureError>().unwrap();
+/// assert_eq!(payload.kind(), ErrorKind::InvalidInput);
+/// assert_eq!(payload.message(), "length 100 is too large");
+/// println!("check failed at {}", payload.location());
+/// assert!(format!("{:#}", err).starts_with("length 100 is too large at "));
+///
+/// let err = parse_len("x").unwrap_err();
+/// let payload = downcast_io_err!(err, IoEnsureError).unwrap();
+/// assert_eq!(payload.source().unwrap().to_string(), "invalid digit found in string");
+/// ```
+///
+/// An `IoEnsureError` can also be built directly and converted into an
+/// `io::Error`:
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+///
+/// let mut fields = Fields::new();
+/// fields.insert("retries", 3.into());
+/// let err: io::Error = IoEnsureError::new(ErrorKind::TimedOut, "gave up")
+///     .with_fields(fields)
+///     .into();
+/// assert_eq!(err.kind(), ErrorKind::TimedOut);
+/// ```
+pub struct IoEnsureError {
+    kind: ErrorKind,
+    message: Cow<'static, str>,
+    source: Option<Box<dyn Error + Send + Sync>>,
+    location: &'static Location<'static>,
+    fields: Fields,
+    #[cfg(feature = "backtrace")]
+    backtrace: Backtrace,
+}
+
+impl IoEnsureError {
+    /// Creates an error with a kind and a message, recording the location of
+    /// the caller.
+    #[track_caller]
+    pub fn new(kind: ErrorKind, message: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            source: None,
+            location: Location::caller(),
+            fields: Fields::new(),
+            #[cfg(feature = "backtrace")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    /// Attaches the error that caused this one, returned by
+    /// [`Error::source`].
+    ///
+    /// [`Error::source`]: https://doc.rust-lang.org/std/error/trait.Error.html#method.source
+    pub fn with_source(mut self, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Replaces the structured fields of the error.
+    pub fn with_fields(mut self, fields: Fields) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Replaces the recorded location, for errors built away from the call
+    /// they report.
+    pub(crate) fn set_location(&mut self, location: &'static Location<'static>) {
+        self.location = location;
+    }
+
+    /// Replaces the kind, for errors whose `io::Error` is given a new kind.
+    pub(crate) fn set_kind(&mut self, kind: ErrorKind) {
+        self.kind = kind;
+    }
+
+    /// Returns the kind of the error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the message if it was stored as a `&'static str`.
+    ///
+    /// Messages that were formatted at runtime return `None`; [`message`]
+    /// returns the message either way.
+    ///
+    /// [`message`]: IoEnsureError::message
+    pub fn static_message(&self) -> Option<&'static str> {
+        match self.message {
+            Cow::Borrowed(message) => Some(message),
+            Cow::Owned(_) => None,
+        }
+    }
+
+    /// Returns the error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the location of the macro call that created the error.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    /// Returns the structured fields of the error, which may be empty.
+    pub fn fields(&self) -> &Fields {
+        &self.fields
+    }
+
+    /// Returns the backtrace captured when the error was created.
+    ///
+    /// Whether a backtrace was actually recorded depends on the environment,
+    /// as described by [`Backtrace::capture`]; check [`Backtrace::status`].
+    ///
+    /// This method is only available with the `backtrace` feature enabled.
+    ///
+    /// [`Backtrace::capture`]: https://doc.rust-lang.org/std/backtrace/struct.Backtrace.html#method.capture
+    /// [`Backtrace::status`]: https://doc.rust-lang.org/std/backtrace/struct.Backtrace.html#method.status
+    #[cfg(feature = "backtrace")]
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.backtrace
+    }
+}
+
+impl fmt::Debug for IoEnsureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        #[cfg(feature = "backtrace")]
+        let alternate = f.alternate();
+        let mut s = f.debug_struct("IoEnsureError");
+        s.field("kind", &self.kind)
+            .field("message", &self.message)
+            .field("source", &self.source)
+            .field("location", &format_args!("{}", self.location))
+            .field("fields", &self.fields);
+        #[cfg(feature = "backtrace")]
+        if alternate {
+            s.field("backtrace", &self.backtrace);
+        }
+        s.finish()
+    }
+}
+
+impl fmt::Display for IoEnsureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if f.alternate() {
+            if !self.fields.is_empty() {
+                write!(f, " [{}]", self.fields)?;
+            }
+            write!(f, " at {}", self.location)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for IoEnsureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(source) => Some(&**source),
+            None => None,
+        }
+    }
+}
+
+impl From<IoEnsureError> for io::Error {
+    fn from(err: IoEnsureError) -> Self {
+        io::Error::new(err.kind, err)
+    }
+}
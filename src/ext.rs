@@ -0,0 +1,376 @@
+use std::borrow::Borrow;
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+
+use crate::{IoErrorCategory, MultiContext, PrettyIoError};
+
+/// Extension methods for [`io::Error`] and [`io::Result`].
+///
+/// The `is_*` predicates check the [`ErrorKind`] of the error. When called on
+/// an `io::Result` they return `false` for `Ok` values.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`io::Result`]: https://doc.rust-lang.org/std/io/type.Result.html
+/// [`ErrorKind`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::fs::File;
+///
+/// let res = File::open("/this/path/does/not/exist");
+/// assert!(res.is_not_found());
+/// assert!(res.unwrap_err().is_not_found());
+/// ```
+pub trait IoErrorExt: sealed::Sealed {
+    /// Wraps the error in a [`PrettyIoError`] for multi-line display.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use io_ensure::*;
+    /// use std::io::ErrorKind;
+    ///
+    /// let err = format_err!(ErrorKind::Other, "oh no").pretty();
+    /// println!("{}", err);
+    /// ```
+    fn pretty(self) -> PrettyIoError
+    where
+        Self: Sized + Into<io::Error>,
+    {
+        PrettyIoError::new(self.into())
+    }
+
+    /// Returns the [`IoErrorCategory`] of the error's kind.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use io_ensure::*;
+    /// use std::io::ErrorKind;
+    ///
+    /// let err = format_err!(ErrorKind::Interrupted);
+    /// assert_eq!(err.category(), IoErrorCategory::Transient);
+    /// ```
+    fn category(&self) -> IoErrorCategory
+    where
+        Self: Borrow<io::Error>,
+    {
+        IoErrorCategory::from(self.borrow())
+    }
+
+    /// Returns `true` if the error kind is `ErrorKind::NotFound`.
+    fn is_not_found(&self) -> bool {
+        self.error_kind() == Some(ErrorKind::NotFound)
+    }
+
+    /// Returns `true` if the error kind is `ErrorKind::PermissionDenied`.
+    fn is_permission_denied(&self) -> bool {
+        self.error_kind() == Some(ErrorKind::PermissionDenied)
+    }
+
+    /// Returns `true` if the error kind is `ErrorKind::WouldBlock`.
+    fn is_would_block(&self) -> bool {
+        self.error_kind() == Some(ErrorKind::WouldBlock)
+    }
+
+    /// Returns `true` if the error kind is `ErrorKind::TimedOut`.
+    fn is_timed_out(&self) -> bool {
+        self.error_kind() == Some(ErrorKind::TimedOut)
+    }
+
+    /// Returns `true` if the error kind is `ErrorKind::BrokenPipe`.
+    fn is_broken_pipe(&self) -> bool {
+        self.error_kind() == Some(ErrorKind::BrokenPipe)
+    }
+
+    /// Returns `true` if the error kind is `ErrorKind::ConnectionRefused`.
+    fn is_connection_refused(&self) -> bool {
+        self.error_kind() == Some(ErrorKind::ConnectionRefused)
+    }
+
+    /// Returns `true` if the error kind is `ErrorKind::AlreadyExists`.
+    fn is_already_exists(&self) -> bool {
+        self.error_kind() == Some(ErrorKind::AlreadyExists)
+    }
+
+    /// Returns `true` if the error kind is `ErrorKind::UnexpectedEof`.
+    fn is_unexpected_eof(&self) -> bool {
+        self.error_kind() == Some(ErrorKind::UnexpectedEof)
+    }
+
+    /// Returns `true` if the error kind is `ErrorKind::Interrupted`.
+    fn is_interrupted(&self) -> bool {
+        self.error_kind() == Some(ErrorKind::Interrupted)
+    }
+}
+
+impl IoErrorExt for io::Error {}
+
+impl<T> IoErrorExt for io::Result<T> {}
+
+/// Extension methods adding context to the error of an [`io::Result`].
+///
+/// The error keeps its kind and is wrapped in a [`MultiContext`], which holds
+/// the original error as its source and prints it followed by each context
+/// message. Adding context again appends to that `MultiContext` instead of
+/// wrapping the error a second time. The location of the first call adding
+/// context is recorded. `Ok` values are returned unchanged.
+///
+/// [`io::Result`]: https://doc.rust-lang.org/std/io/type.Result.html
+/// [`MultiContext`]: crate::MultiContext
+pub trait IoContext<T>: sealed::Sealed {
+    /// Adds a context message to the error, keeping its kind.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use io_ensure::*;
+    /// use std::fs::File;
+    /// use std::io::ErrorKind;
+    ///
+    /// let err = File::open("/this/path/does/not/exist")
+    ///     .context("loading config")
+    ///     .unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::NotFound);
+    /// assert!(err.to_string().ends_with("\ncontext:\n    0: loading config"));
+    /// ```
+    fn context(self, context: impl Into<String>) -> io::Result<T>;
+
+    /// Adds a context message built by a closure to the error, keeping its
+    /// kind.
+    ///
+    /// The closure is only called if the result is an `Err`, so it can format
+    /// a message without slowing down the `Ok` path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use io_ensure::*;
+    /// use std::fs::File;
+    /// use std::io::ErrorKind;
+    ///
+    /// let path = "/this/path/does/not/exist";
+    /// let err = File::open(path)
+    ///     .with_context(|| format!("loading config from {}", path))
+    ///     .unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::NotFound);
+    /// ```
+    fn with_context<C, F>(self, context: F) -> io::Result<T>
+    where
+        C: Into<String>,
+        F: FnOnce() -> C;
+}
+
+impl<T> IoContext<T> for io::Result<T> {
+    #[track_caller]
+    fn context(self, context: impl Into<String>) -> io::Result<T> {
+        match self {
+            Ok(value) => Ok(value),
+            Err(err) => Err(MultiContext::add(err, context.into())),
+        }
+    }
+
+    #[track_caller]
+    fn with_context<C, F>(self, context: F) -> io::Result<T>
+    where
+        C: Into<String>,
+        F: FnOnce() -> C,
+    {
+        match self {
+            Ok(value) => Ok(value),
+            Err(err) => Err(MultiContext::add(err, context().into())),
+        }
+    }
+}
+
+/// Extension methods converting an [`Option`] into an [`io::Result`].
+///
+/// These are the method forms of [`ensure_some!`], for iterator chains and
+/// closures where a macro cannot return early.
+///
+/// [`Option`]: https://doc.rust-lang.org/std/option/enum.Option.html
+/// [`io::Result`]: https://doc.rust-lang.org/std/io/type.Result.html
+/// [`ensure_some!`]: crate::ensure_some
+pub trait OptionExt<T> {
+    /// Converts `None` into an error of the given kind, without a message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use io_ensure::*;
+    /// use std::io::ErrorKind;
+    ///
+    /// let err = None::<u8>.ok_or_kind(ErrorKind::NotFound).unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::NotFound);
+    /// assert_eq!(Some(1).ok_or_kind(ErrorKind::NotFound).unwrap(), 1);
+    /// ```
+    fn ok_or_kind(self, kind: ErrorKind) -> io::Result<T>;
+
+    /// Converts `None` into an error of the given kind, with a message built
+    /// by a closure.
+    ///
+    /// The closure is only called if the option is `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use io_ensure::*;
+    /// use std::io::{self, ErrorKind};
+    ///
+    /// let entries = [("host", "localhost")];
+    /// let values: io::Result<Vec<_>> = ["host", "port"]
+    ///     .iter()
+    ///     .map(|key| {
+    ///         entries
+    ///             .iter()
+    ///             .find(|(k, _)| k == key)
+    ///             .ok_or_err(ErrorKind::NotFound, || format!("missing entry {}", key))
+    ///     })
+    ///     .collect();
+    /// assert_eq!(values.unwrap_err().to_string(), "missing entry port");
+    /// ```
+    fn ok_or_err<F>(self, kind: ErrorKind, msg: F) -> io::Result<T>
+    where
+        F: FnOnce() -> String;
+}
+
+impl<T> OptionExt<T> for Option<T> {
+    fn ok_or_kind(self, kind: ErrorKind) -> io::Result<T> {
+        self.ok_or_else(|| io::Error::from(kind))
+    }
+
+    #[track_caller]
+    fn ok_or_err<F>(self, kind: ErrorKind, msg: F) -> io::Result<T>
+    where
+        F: FnOnce() -> String,
+    {
+        match self {
+            Some(value) => Ok(value),
+            None => Err(crate::__private::new_error(kind, msg())),
+        }
+    }
+}
+
+/// Extension methods combining reads, writes, and seeks with a check of their
+/// outcome.
+///
+/// Each method takes a closure building the error to return when the check
+/// fails, such as one made with [`format_err!`]. Other errors from the
+/// underlying operation are returned unchanged. The methods are available on
+/// every [`Read`], [`Write`], or [`Seek`] implementor respectively.
+///
+/// [`format_err!`]: crate::format_err
+/// [`Read`]: https://doc.rust-lang.org/std/io/trait.Read.html
+/// [`Write`]: https://doc.rust-lang.org/std/io/trait.Write.html
+/// [`Seek`]: https://doc.rust-lang.org/std/io/trait.Seek.html
+pub trait IoEnsureExt {
+    /// Fills `buf` completely, returning the error from `err` if the reader
+    /// ends first.
+    ///
+    /// Returns the number of bytes read, which is always `buf.len()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use io_ensure::*;
+    /// use std::io::ErrorKind;
+    ///
+    /// let mut reader = &b"\x01\x02"[..];
+    /// let mut buf = [0; 4];
+    /// let err = reader
+    ///     .read_exact_ensuring(&mut buf, || format_err!(ErrorKind::UnexpectedEof, "incomplete frame"))
+    ///     .unwrap_err();
+    /// assert_eq!(err.to_string(), "incomplete frame");
+    /// ```
+    fn read_exact_ensuring<F>(&mut self, buf: &mut [u8], err: F) -> io::Result<usize>
+    where
+        Self: Read,
+        F: FnOnce() -> io::Error,
+    {
+        match self.read_exact(buf) {
+            Ok(()) => Ok(buf.len()),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => Err(err()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes all of `buf`, returning the error from `err` if the writer stops
+    /// accepting bytes first.
+    ///
+    /// Returns the number of bytes written, which is always `buf.len()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use io_ensure::*;
+    /// use std::io::ErrorKind;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut out = Vec::new();
+    /// let written = out.write_all_ensuring(b"frame", || format_err!(ErrorKind::WriteZero, "peer closed"))?;
+    /// assert_eq!(written, 5);
+    /// # Ok(()) }
+    /// ```
+    fn write_all_ensuring<F>(&mut self, buf: &[u8], err: F) -> io::Result<usize>
+    where
+        Self: Write,
+        F: FnOnce() -> io::Error,
+    {
+        match self.write_all(buf) {
+            Ok(()) => Ok(buf.len()),
+            Err(e) if e.kind() == ErrorKind::WriteZero => Err(err()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Seeks to `pos` bytes from the start, returning the error from `err` if
+    /// the stream ends up at a different position.
+    ///
+    /// Returns the new position, which is always `pos`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use io_ensure::*;
+    /// use std::io::{Cursor, ErrorKind};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut cursor = Cursor::new(vec![0; 64]);
+    /// let pos = cursor.seek_to_ensuring(16, || format_err!(ErrorKind::InvalidInput, "bad seek"))?;
+    /// assert_eq!(pos, 16);
+    /// # Ok(()) }
+    /// ```
+    fn seek_to_ensuring<F>(&mut self, pos: u64, err: F) -> io::Result<u64>
+    where
+        Self: Seek,
+        F: FnOnce() -> io::Error,
+    {
+        match self.seek(SeekFrom::Start(pos))? {
+            actual if actual == pos => Ok(actual),
+            _ => Err(err()),
+        }
+    }
+}
+
+impl<T: ?Sized> IoEnsureExt for T {}
+
+mod sealed {
+    use std::io::{self, ErrorKind};
+
+    pub trait Sealed {
+        fn error_kind(&self) -> Option<ErrorKind>;
+    }
+
+    impl Sealed for io::Error {
+        fn error_kind(&self) -> Option<ErrorKind> {
+            Some(self.kind())
+        }
+    }
+
+    impl<T> Sealed for io::Result<T> {
+        fn error_kind(&self) -> Option<ErrorKind> {
+            self.as_ref().err().map(io::Error::kind)
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Macros validating file descriptors.
+
+/// Exits a function early with an [`io::Error`] if a raw file descriptor is
+/// not open in the current process.
+///
+/// The descriptor is a [`RawFd`], such as one received over FFI. It is looked
+/// up in `/dev/fd`, so the check needs no unsafe code and performs no IO on
+/// the descriptor itself. Negative descriptors always fail. When no message is
+/// given the error reports the descriptor.
+///
+/// This macro is only available on Linux and macOS, where `/dev/fd` lists
+/// every open descriptor. Other Unixes may not mount a full `/dev/fd`; the
+/// default one on FreeBSD only has descriptors 0 to 2, so the lookup would
+/// report other open descriptors as invalid.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`RawFd`]: https://doc.rust-lang.org/std/os/unix/io/type.RawFd.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+/// use std::os::unix::io::AsRawFd;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let fd = std::io::stdin().as_raw_fd();
+/// ensure_fd_valid!(fd, ErrorKind::InvalidInput);
+/// ensure_fd_valid!(fd, ErrorKind::InvalidInput, "invalid file descriptor: {}", fd);
+/// # Ok(()) }
+/// ```
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[macro_export]
+macro_rules! ensure_fd_valid {
+    ($fd:expr, $kind:expr $(,)?) => {
+        match $fd {
+            fd => {
+                $crate::ensure!(
+                    $crate::__private::fd_is_open(fd),
+                    $kind,
+                    "invalid file descriptor: {}",
+                    fd
+                );
+            }
+        }
+    };
+    ($fd:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!($crate::__private::fd_is_open($fd), $kind, $($arg)+)
+    };
+}
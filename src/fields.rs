@@ -0,0 +1,156 @@
+use std::fmt;
+
+/// An ordered map of structured fields attached to an [`IoEnsureError`].
+///
+/// Fields are added with a trailing block in [`format_err!`], and read back
+/// through [`IoEnsureError::fields`]. Unlike the `; key = value` form, the
+/// fields are not added to the message, so a logging pipeline can record
+/// them separately. Keys keep the order they were written in.
+///
+/// [`IoEnsureError`]: crate::IoEnsureError
+/// [`IoEnsureError::fields`]: crate::IoEnsureError::fields
+/// [`format_err!`]: crate::format_err
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// let (addr, attempts) = ("10.0.0.1:443", 3);
+/// let err = format_err!(ErrorKind::TimedOut, "dial failed", { addr = %addr, attempt = attempts });
+/// assert_eq!(err.to_string(), "dial failed");
+///
+/// let fields = downcast_io_err!(err, IoEnsureError).unwrap().fields();
+/// assert_eq!(fields.get("addr"), Some(&FieldValue::Str("10.0.0.1:443".into())));
+/// assert_eq!(fields.get("attempt"), Some(&FieldValue::I64(3)));
+/// assert_eq!(fields.to_string(), "addr=10.0.0.1:443, attempt=3");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fields {
+    entries: Vec<(&'static str, FieldValue)>,
+}
+
+impl Fields {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a field, replacing the value of an existing field with the same
+    /// key in place.
+    pub fn insert(&mut self, key: &'static str, value: FieldValue) {
+        match self.entries.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => *v = value,
+            None => self.entries.push((key, value)),
+        }
+    }
+
+    /// Returns the value of a field.
+    pub fn get(&self, key: &str) -> Option<&FieldValue> {
+        self.entries.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Returns an iterator over the fields, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &FieldValue)> + '_ {
+        self.entries.iter().map(|(k, v)| (*k, v))
+    }
+
+    /// Returns the number of fields.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there are no fields.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// The value of a field in [`Fields`].
+///
+/// In a `format_err!` field block, a bare value is converted with `From`,
+/// which keeps integers, floats, and booleans typed. Values written as `%value`
+/// are formatted with `Display`, and `?value` with `Debug`, both producing
+/// [`FieldValue::Str`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    /// A boolean.
+    Bool(bool),
+    /// A signed integer.
+    I64(i64),
+    /// An unsigned integer.
+    U64(u64),
+    /// A floating point number.
+    F64(f64),
+    /// A string.
+    Str(String),
+}
+
+impl FieldValue {
+    /// Creates a string value from the `Display` output of a value.
+    pub fn display(value: &impl fmt::Display) -> Self {
+        FieldValue::Str(value.to_string())
+    }
+
+    /// Creates a string value from the `Debug` output of a value.
+    pub fn debug(value: &impl fmt::Debug) -> Self {
+        FieldValue::Str(format!("{:?}", value))
+    }
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Bool(v) => v.fmt(f),
+            FieldValue::I64(v) => v.fmt(f),
+            FieldValue::U64(v) => v.fmt(f),
+            FieldValue::F64(v) => v.fmt(f),
+            FieldValue::Str(v) => v.fmt(f),
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($($variant:ident($target:ty): $($ty:ty),+;)+) => {
+        $($(
+            impl From<$ty> for FieldValue {
+                fn from(value: $ty) -> Self {
+                    FieldValue::$variant(<$target>::from(value))
+                }
+            }
+        )+)+
+    };
+}
+
+impl_from! {
+    Bool(bool): bool;
+    I64(i64): i8, i16, i32, i64;
+    U64(u64): u8, u16, u32, u64;
+    F64(f64): f32, f64;
+    Str(String): &str, String;
+}
+
+impl From<isize> for FieldValue {
+    fn from(value: isize) -> Self {
+        FieldValue::I64(value as i64)
+    }
+}
+
+impl From<usize> for FieldValue {
+    fn from(value: usize) -> Self {
+        FieldValue::U64(value as u64)
+    }
+}
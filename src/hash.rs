@@ -0,0 +1,143 @@
+//! Macros for comparing secrets and checksums.
+
+/// Exits a function early with an [`io::Error`] if two byte sequences are not
+/// equal, comparing them in constant time.
+///
+/// Unlike [`ensure_eq!`], the comparison does not exit early on the first
+/// differing byte, so it does not leak how much of a digest, token, or MAC
+/// matched through timing. Sequences of different length are rejected without
+/// comparing their contents; only the length itself may leak.
+///
+/// Both expressions may be any type implementing `AsRef<[u8]>`, such as
+/// `&[u8]`, `[u8; N]`, or `Vec<u8>`. With the `subtle` feature enabled the
+/// comparison is performed by [`subtle::ConstantTimeEq`].
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_eq!`]: crate::ensure_eq
+/// [`subtle::ConstantTimeEq`]: https://docs.rs/subtle/2/subtle/trait.ConstantTimeEq.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let computed = [0xde, 0xad, 0xbe, 0xef];
+/// let expected = vec![0xde, 0xad, 0xbe, 0xef];
+/// ensure_hash_eq!(computed, expected, ErrorKind::PermissionDenied);
+/// ensure_hash_eq!(&computed[..], expected, ErrorKind::PermissionDenied, "authentication failed");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_hash_eq {
+    ($left:expr, $right:expr, $kind:expr $(,)?) => {
+        $crate::ensure!(
+            $crate::__private::constant_time_eq(
+                ::std::convert::AsRef::<[u8]>::as_ref(&$left),
+                ::std::convert::AsRef::<[u8]>::as_ref(&$right),
+            ),
+            $kind
+        );
+    };
+    ($left:expr, $right:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!(
+            $crate::__private::constant_time_eq(
+                ::std::convert::AsRef::<[u8]>::as_ref(&$left),
+                ::std::convert::AsRef::<[u8]>::as_ref(&$right),
+            ),
+            $kind,
+            $($arg)+
+        );
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if the checksum of some data
+/// does not equal the expected value.
+///
+/// The checksum algorithm is pluggable: it can be any function or closure
+/// taking `&[u8]` and returning a value comparable to `expected`, such as a
+/// CRC32, Adler-32, or xxHash implementation. The data may be anything
+/// implementing `AsRef<[u8]>`. When no message is given the error reports the
+/// expected and actual checksums in hex, which requires them to implement
+/// `LowerHex`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// fn sum(data: &[u8]) -> u32 {
+///     data.iter().map(|&b| u32::from(b)).sum()
+/// }
+///
+/// # fn main() -> std::io::Result<()> {
+/// let data = b"payload";
+/// let expected = 0x2ea;
+/// ensure_checksum!(data, expected, sum, ErrorKind::InvalidData);
+/// ensure_checksum!(data, expected, |d: &[u8]| sum(d), ErrorKind::InvalidData, "checksum mismatch");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_checksum {
+    ($data:expr, $expected:expr, $checksum:expr, $kind:expr $(,)?) => {
+        match (
+            ($checksum)(::std::convert::AsRef::<[u8]>::as_ref(&$data)),
+            &$expected,
+        ) {
+            (actual, expected) => {
+                $crate::ensure!(
+                    actual == *expected,
+                    $kind,
+                    "checksum mismatch: expected {:#x}, got {:#x}",
+                    expected,
+                    actual
+                );
+            }
+        }
+    };
+    ($data:expr, $expected:expr, $checksum:expr, $kind:expr, $($arg:tt)+) => {
+        match (
+            ($checksum)(::std::convert::AsRef::<[u8]>::as_ref(&$data)),
+            &$expected,
+        ) {
+            (actual, expected) => {
+                $crate::ensure!(actual == *expected, $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if the CRC-32 checksum of some
+/// data does not equal the expected value.
+///
+/// This is [`ensure_checksum!`] using the CRC-32/ISO-HDLC algorithm used by
+/// zlib, gzip, and PNG, as implemented by the [`crc`] crate.
+///
+/// This macro is only available with the `crc` feature enabled.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_checksum!`]: crate::ensure_checksum
+/// [`crc`]: https://docs.rs/crc
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// ensure_crc32!(b"123456789", 0xcbf4_3926_u32, ErrorKind::InvalidData);
+/// ensure_crc32!(b"123456789", 0xcbf4_3926_u32, ErrorKind::InvalidData, "corrupt frame");
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "crc")]
+#[macro_export]
+macro_rules! ensure_crc32 {
+    ($data:expr, $expected:expr, $($arg:tt)+) => {
+        $crate::ensure_checksum!($data, $expected, $crate::__private::crc32, $($arg)+)
+    };
+}
@@ -0,0 +1,98 @@
+use std::io;
+use std::panic::Location;
+use std::sync::{OnceLock, PoisonError, RwLock};
+
+/// A hook called with the error and location of every failed check.
+///
+/// See [`set_hook`].
+pub type FailureHook = fn(&io::Error, &Location<'_>);
+
+static HOOK: RwLock<Option<FailureHook>> = RwLock::new(None);
+
+/// Registers a hook that is called whenever a check fails.
+///
+/// Like [`std::panic::set_hook`], but for the failure path of [`ensure!`] and
+/// the other checks: the hook is called with the error and the location of the
+/// check, just before the error is returned. This makes it possible to record
+/// every failure centrally, for example in a crash reporter, without changing
+/// the call sites. `bail!` and `format_err!` are not checks and do not call
+/// the hook.
+///
+/// There is one hook for the whole process, replacing any hook set before. The
+/// default hook does nothing. Use [`take_hook`] to restore it.
+///
+/// The hook is called without holding any lock, so it may call `set_hook` or
+/// `take_hook` itself. It should not fail a check, as that would call the hook
+/// again.
+///
+/// [`std::panic::set_hook`]: https://doc.rust-lang.org/std/panic/fn.set_hook.html
+/// [`ensure!`]: crate::ensure
+/// [`take_hook`]: crate::take_hook
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// static FAILURES: AtomicUsize = AtomicUsize::new(0);
+///
+/// set_hook(|err, location| {
+///     eprintln!("check failed at {}: {}", location, err);
+///     FAILURES.fetch_add(1, Ordering::Relaxed);
+/// });
+///
+/// fn check(len: usize) -> io::Result<()> {
+///     ensure!(len > 0, ErrorKind::InvalidInput, "empty input");
+///     Ok(())
+/// }
+/// assert!(check(0).is_err());
+/// assert_eq!(FAILURES.load(Ordering::Relaxed), 1);
+///
+/// let _ = take_hook();
+/// ```
+pub fn set_hook(hook: FailureHook) {
+    *HOOK.write().unwrap_or_else(PoisonError::into_inner) = Some(hook);
+}
+
+/// Unregisters the current failure hook and returns it, restoring the default
+/// hook that does nothing.
+///
+/// If no hook was set, the returned hook does nothing.
+///
+/// See [`set_hook`].
+///
+/// [`set_hook`]: crate::set_hook
+pub fn take_hook() -> FailureHook {
+    HOOK.write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+        .unwrap_or(|_, _| {})
+}
+
+/// Runs on the failure path of every check, before the error is returned.
+#[cold]
+#[inline(never)]
+#[track_caller]
+pub fn ensure_failed(err: &io::Error) {
+    let location = Location::caller();
+    // Copied out so the lock is released before the hook runs.
+    let hook = *HOOK.read().unwrap_or_else(PoisonError::into_inner);
+    if let Some(hook) = hook {
+        hook(err, location);
+    }
+    if panic_mode() {
+        panic!("check failed: {}", err);
+    }
+}
+
+/// Returns whether failed checks panic, as set by `IO_ENSURE_PANIC`.
+///
+/// The variable is read once, on the first failure.
+fn panic_mode() -> bool {
+    static PANIC_MODE: OnceLock<bool> = OnceLock::new();
+    *PANIC_MODE.get_or_init(|| {
+        std::env::var_os("IO_ENSURE_PANIC").is_some_and(|value| !value.is_empty() && value != "0")
+    })
+}
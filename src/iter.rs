@@ -0,0 +1,87 @@
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::panic::Location;
+
+/// Extension methods for validating the elements of an [`Iterator`].
+///
+/// [`Iterator`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html
+pub trait EnsureAllExt: Iterator + Sized {
+    /// Checks each element against a predicate as it is yielded.
+    ///
+    /// Elements that satisfy the predicate are yielded as `Ok`. The first
+    /// element that does not yields an `Err` reporting the message, its index,
+    /// and its `Debug` representation, after which the adapter stops without
+    /// consuming the rest of the iterator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use io_ensure::*;
+    /// use std::io::{self, ErrorKind};
+    ///
+    /// let lens = vec![3, 8, 0, 4];
+    /// let mut iter = lens.iter().ensure_all(|len| **len > 0, ErrorKind::InvalidData, "empty frame");
+    /// assert_eq!(iter.next().unwrap().unwrap(), &3);
+    /// assert_eq!(iter.next().unwrap().unwrap(), &8);
+    /// let err = iter.next().unwrap().unwrap_err();
+    /// assert_eq!(err.to_string(), "empty frame: element 2 is 0");
+    /// assert!(iter.next().is_none());
+    ///
+    /// let all: io::Result<Vec<_>> = lens.iter().ensure_all(|len| **len < 10, ErrorKind::InvalidData, "too long").collect();
+    /// assert_eq!(all.unwrap().len(), 4);
+    /// ```
+    #[track_caller]
+    fn ensure_all<P, M>(self, predicate: P, kind: ErrorKind, msg: M) -> EnsureAll<Self, P, M>
+    where
+        P: FnMut(&Self::Item) -> bool,
+        M: fmt::Display,
+    {
+        EnsureAll {
+            iter: Some(self),
+            predicate,
+            kind,
+            msg,
+            index: 0,
+            location: Location::caller(),
+        }
+    }
+}
+
+impl<I: Iterator> EnsureAllExt for I {}
+
+/// An iterator that validates the elements of another iterator.
+///
+/// This `struct` is created by [`EnsureAllExt::ensure_all`].
+#[derive(Debug)]
+pub struct EnsureAll<I, P, M> {
+    iter: Option<I>,
+    predicate: P,
+    kind: ErrorKind,
+    msg: M,
+    index: usize,
+    location: &'static Location<'static>,
+}
+
+impl<I, P, M> Iterator for EnsureAll<I, P, M>
+where
+    I: Iterator,
+    I::Item: fmt::Debug,
+    P: FnMut(&I::Item) -> bool,
+    M: fmt::Display,
+{
+    type Item = io::Result<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.as_mut()?.next()?;
+        let index = self.index;
+        self.index += 1;
+        if (self.predicate)(&item) {
+            return Some(Ok(item));
+        }
+        self.iter = None;
+        let mut err =
+            crate::format_err!(self.kind, "{}: element {} is {:?}", self.msg, index, item);
+        crate::__private::set_location(&mut err, self.location);
+        Some(Err(err))
+    }
+}
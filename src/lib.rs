@@ -17,21 +17,180 @@
 //! ensure!(a == b, ErrorKind::Interrupted, "we are testing addition with {} and {}", a, b);
 //! # Ok(()) }
 //! ```
+//!
+//! Errors built by the macros carry an [`IoEnsureError`] payload recording
+//! where they were created.
+//!
+//! The macros that return early convert the error with `From`, as `?` does,
+//! so they also work in functions returning a custom error type that
+//! implements `From<io::Error>`.
+//!
+//! # Panicking on failure
+//!
+//! When the `IO_ENSURE_PANIC` environment variable is set to anything other
+//! than `0` or an empty string, failing checks panic with their error message
+//! instead of returning it. The panic reports the location of the check, which
+//! helps when an early return in a test hides where parsing went wrong. Only
+//! conditional checks such as `ensure!` panic; `bail!` and `format_err!` are
+//! unaffected. The variable is read on the first failure.
+//!
+//! ```sh
+//! IO_ENSURE_PANIC=1 cargo test
+//! ```
+//!
+//! To observe failures without changing how they are handled, register a
+//! hook with [`set_hook`]. It is called with the error and location of every
+//! failing check, before the panic described above.
+//!
+//! # Features
+//!
+//! - `backtrace`: capture a [`Backtrace`] in every [`IoEnsureError`], shown
+//!   by its alternate `{:#?}` formatting. As with `Backtrace::capture`, a
+//!   backtrace is only recorded when `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`
+//!   enables it.
+//! - `tracing`: report `io_warn!` and `ensure_or_warn!` warnings as
+//!   [`tracing`] events.
+//! - `log`: report those warnings through [`log`] when `tracing` is not
+//!   enabled.
+//! - `warn-stderr`: print those warnings to standard error when neither
+//!   `tracing` nor `log` is enabled.
+//! - `tracing-warn`: emit a [`tracing`] event at `WARN` level whenever an
+//!   `ensure!` check fails, with the failing condition and the error kind as
+//!   structured fields.
+//! - `subtle`: use [`subtle`] for the constant-time comparison in
+//!   `ensure_hash_eq!`.
+//! - `crc`: enable `ensure_crc32!`, computing checksums with [`crc`].
+//! - `regex`: enable `ensure_regex!`, matching input against a [`regex`].
+//! - `glob`: enable `ensure_glob!`, matching paths against a [`glob`] pattern.
+//! - `semver`: enable `ensure_version_compat!`, checking versions with
+//!   [`semver`].
+//! - `serde_json`: enable `ensure_json_valid!` and `ensure_json_valid_as!`,
+//!   parsing payloads with [`serde_json`].
+//! - `url`: enable `ensure_url_valid!`, `ensure_https_url!`, and
+//!   `ensure_http_url!`, parsing input with [`url`].
+//! - `uuid`: enable `ensure_uuid_valid!`, parsing input with [`uuid`].
+//!
+//! [`Backtrace`]: https://doc.rust-lang.org/std/backtrace/struct.Backtrace.html
+//! [`tracing`]: https://docs.rs/tracing
+//! [`log`]: https://docs.rs/log
+//! [`subtle`]: https://docs.rs/subtle
+//! [`crc`]: https://docs.rs/crc
+//! [`regex`]: https://docs.rs/regex
+//! [`glob`]: https://docs.rs/glob
+//! [`semver`]: https://docs.rs/semver
+//! [`serde_json`]: https://docs.rs/serde_json
+//! [`url`]: https://docs.rs/url
+//! [`uuid`]: https://docs.rs/uuid
+
+mod buf;
+mod category;
+mod chain;
+mod checks;
+mod cond;
+mod context;
+mod convert;
+mod endian;
+mod env;
+mod error;
+mod ext;
+mod fd;
+mod fields;
+mod hash;
+mod hook;
+mod iter;
+mod metadata;
+mod multi;
+mod net;
+mod num;
+mod parse;
+mod path;
+mod poll;
+mod pretty;
+mod range;
+mod rate;
+mod retry;
+mod rw;
+mod seq;
+mod state;
+mod text;
+mod time;
+mod warn;
+
+pub use category::IoErrorCategory;
+pub use chain::ErrorChain;
+pub use checks::IoEnsureChain;
+pub use context::MultiContext;
+pub use convert::{FromIoEnsureError, FromIoError, IntoIoError};
+pub use endian::ByteOrder;
+pub use error::IoEnsureError;
+pub use ext::{IoContext, IoEnsureExt, IoErrorExt, OptionExt};
+pub use fields::{FieldValue, Fields};
+pub use hook::{set_hook, take_hook, FailureHook};
+pub use iter::{EnsureAll, EnsureAllExt};
+pub use metadata::IoErrorMetadata;
+pub use multi::MultiError;
+pub use pretty::PrettyIoError;
+pub use rate::RateLimiter;
+pub use retry::{RetryIo, RetryableError};
+pub use rw::SeekMode;
+
+/// The previous name of [`IoContext`].
+#[doc(hidden)]
+pub use ext::IoContext as IoResultExt;
+
+#[doc(hidden)]
+#[path = "private.rs"]
+pub mod __private;
 
 /// Creates an [`io::Error`] using optional interpolation of runtime expressions.
 ///
-/// Arguments to `format_err!` can either be literals which are passed to
-/// `io::Error::new`, or directly interpolated strings constructed through the
-/// `format!` macro.
+/// Arguments to `format_err!` are interpolated like those of the `format!`
+/// macro. A string literal message is always a format string, even without
+/// further arguments, so `{name}` captures a variable in scope and `{{`
+/// escapes a brace, as with `println!`. A message without placeholders is
+/// stored without being copied. Any other expression given as the message,
+/// such as another error, is passed to `io::Error::new`.
+///
+/// When only a single argument is given it is converted using [`IntoIoError`].
+/// This allows creating errors from an [`ErrorKind`] without a payload, or from
+/// any type that knows how to turn itself into an `io::Error`.
+///
+/// A literal message may be followed by a semicolon and a list of `key = value`
+/// fields, which are appended to the message as `[key=value, ...]`. Each value
+/// is formatted using [`Display`].
+///
+/// Structured fields can instead be given in a block after the message and
+/// its arguments, as in `{ addr = %addr, attempt = attempts }`. These are not
+/// added to the message, but stored as [`Fields`] in the error's
+/// [`IoEnsureError`] payload. Values are converted to a [`FieldValue`] with
+/// `From`, or formatted with `Display` when prefixed with `%` and with `Debug`
+/// when prefixed with `?`.
+///
+/// Instead of fields, `source = err` attaches another error as the
+/// [`source`] of the new one, keeping the message, so the cause can still be
+/// inspected by walking the error chain. It may also follow a field block. It
+/// accepts anything that converts into `Box<dyn Error + Send + Sync>`.
+///
+/// The kind may be left out when the first argument is a string literal, in
+/// which case the error is created with `io::Error::other` and has kind
+/// `ErrorKind::Other`.
+///
+/// Errors created from a string message carry an [`IoEnsureError`] payload,
+/// which records where the macro was called.
 ///
 /// See [`std::fmt`] for more information.
 ///
 /// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`IoEnsureError`]: crate::IoEnsureError
+/// [`Fields`]: crate::Fields
+/// [`FieldValue`]: crate::FieldValue
 /// [`std::fmt`]: ../std/fmt/index.html
+/// [`ErrorKind`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html
 /// [`print!`]: ../std/macro.print.html
 /// [`write!`]: core::write
 /// [`to_string`]: crate::string::ToString
 /// [`Display`]: core::fmt::Display
+/// [`source`]: https://doc.rust-lang.org/std/error/trait.Error.html#method.source
 ///
 /// # Panics
 ///
@@ -48,25 +207,186 @@
 /// // errors can be created from format strings
 /// let custom_error = format_err!(ErrorKind::Other, "hello {}", "world!");
 ///
+/// // variables can be captured by name
+/// let len = 16;
+/// let capture_error = format_err!(ErrorKind::UnexpectedEof, "expected {len} bytes");
+/// assert_eq!(capture_error.to_string(), "expected 16 bytes");
+///
 /// // errors can also be created from other errors
 /// let custom_error2 = format_err!(ErrorKind::Interrupted, custom_error);
 ///
 /// // errors can also be created without payload (and without memory allocation)
 /// let eof_error = format_err!(ErrorKind::UnexpectedEof);
+///
+/// // key-value fields can be appended after a semicolon
+/// let (id, retry) = ("abc123", 3);
+/// let field_error = format_err!(ErrorKind::Other, "request failed"; request_id = id, retry = retry);
+/// assert_eq!(field_error.to_string(), "request failed [request_id=abc123, retry=3]");
+///
+/// // structured fields can be stored in the payload
+/// let addr = std::net::Ipv4Addr::LOCALHOST;
+/// let dial_error = format_err!(ErrorKind::TimedOut, "dial failed", { addr = %addr, attempt = 2 });
+/// assert_eq!(dial_error.to_string(), "dial failed");
+///
+/// // the underlying error can be attached as the source
+/// let inner = "x".parse::<u32>().unwrap_err();
+/// let source_error = format_err!(ErrorKind::InvalidData, "decoding frame {}", 3; source = inner);
+/// assert_eq!(source_error.to_string(), "decoding frame 3");
+/// assert!(source_error.get_ref().unwrap().source().is_some());
+///
+/// // the kind can be left out, defaulting to `ErrorKind::Other`
+/// let other_error = format_err!("request {} failed", id);
+/// assert_eq!(other_error.kind(), ErrorKind::Other);
 /// ```
 #[macro_export]
 macro_rules! format_err {
+    ($msg:literal $(,)?) => {{
+        $crate::__private::new_error_fmt(::std::io::ErrorKind::Other, format_args!($msg))
+    }};
+    ($msg:literal, $($rest:tt)+) => {{
+        $crate::__format_err!([::std::io::ErrorKind::Other, $msg] [] , $($rest)+)
+    }};
+    ($msg:literal ; $($rest:tt)+) => {{
+        $crate::__format_err!([::std::io::ErrorKind::Other, $msg] [] ; $($rest)+)
+    }};
     ($kind:expr, $msg:literal $(,)?) => {{
-        ::std::io::Error::new($kind, $msg)
+        $crate::__private::new_error_fmt($kind, format_args!($msg))
+    }};
+    ($kind:expr, $msg:literal, $($rest:tt)+) => {{
+        $crate::__format_err!([$kind, $msg] [] , $($rest)+)
+    }};
+    ($kind:expr, $msg:literal ; $($rest:tt)+) => {{
+        $crate::__format_err!([$kind, $msg] [] ; $($rest)+)
     }};
     ($kind:expr, $msg:expr $(,)?) => {{
-        ::std::io::Error::new($kind, $msg)
+        #[allow(unused_imports)]
+        use $crate::__private::payload::{ConvertKind as _, MessageKind as _, WrapKind as _};
+        let kind = $kind;
+        let payload = $msg;
+        (&&&$crate::__private::payload::Payload(&payload))
+            .payload_kind()
+            .new_error(kind, payload)
     }};
     ($kind:expr, $msg:expr, $($arg:tt)*) => {{
-        ::std::io::Error::new($kind, format!($msg, $($arg)*))
+        $crate::__private::new_error_fmt($kind, format_args!($msg, $($arg)*))
     }};
     ($kind:expr $(,)?) => {{
-        ::std::io::Error::from($kind)
+        $crate::IntoIoError::into_io_error($kind)
+    }};
+}
+
+/// Splits the format arguments of `format_err!` from a trailing block of
+/// fields, or the fields or source following a semicolon.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_err {
+    ([$kind:expr, $msg:literal] [$($arg:tt)*] { $key:ident = $($fields:tt)* } $(,)?) => {{
+        let mut fields = $crate::Fields::new();
+        $crate::__format_err_fields!(fields $key = $($fields)*);
+        $crate::__private::new_error_with_fields($kind, format_args!($msg $($arg)*), fields)
+    }};
+    ([$kind:expr, $msg:literal] [$($arg:tt)*] { $($fields:tt)+ } ; source = $source:expr $(,)?) => {{
+        $crate::__private::with_source(
+            $crate::__format_err!([$kind, $msg] [$($arg)*] { $($fields)+ }),
+            $source,
+        )
+    }};
+    ([$kind:expr, $msg:literal] [$($arg:tt)*] ; source = $source:expr $(,)?) => {{
+        $crate::__private::with_source($crate::__format_err!([$kind, $msg] [$($arg)*]), $source)
+    }};
+    ([$kind:expr, $msg:literal] [$($arg:tt)*] ; $key:ident = $value:expr $(, $keys:ident = $values:expr)* $(,)?) => {{
+        $crate::__private::new_error_fmt(
+            $kind,
+            format_args!(
+                concat!($msg, " [", stringify!($key), "={}" $(, ", ", stringify!($keys), "={}")*, "]")
+                $($arg)*, $value $(, $values)*
+            ),
+        )
+    }};
+    ([$kind:expr, $msg:literal] [$($arg:tt)*]) => {{
+        $crate::__private::new_error_fmt($kind, format_args!($msg $($arg)*))
+    }};
+    ([$($head:tt)*] [$($arg:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::__format_err!([$($head)*] [$($arg)* $next] $($rest)*)
+    };
+}
+
+/// Adds the fields of a `format_err!` field block to a `Fields` map.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_err_fields {
+    ($fields:ident $(,)?) => {};
+    ($fields:ident $key:ident = % $value:expr $(, $($rest:tt)*)?) => {
+        $fields.insert(stringify!($key), $crate::FieldValue::display(&$value));
+        $crate::__format_err_fields!($fields $($($rest)*)?);
+    };
+    ($fields:ident $key:ident = ? $value:expr $(, $($rest:tt)*)?) => {
+        $fields.insert(stringify!($key), $crate::FieldValue::debug(&$value));
+        $crate::__format_err_fields!($fields $($($rest)*)?);
+    };
+    ($fields:ident $key:ident = $value:expr $(, $($rest:tt)*)?) => {
+        $fields.insert(stringify!($key), $crate::FieldValue::from($value));
+        $crate::__format_err_fields!($fields $($($rest)*)?);
+    };
+}
+
+/// Creates an [`io::Error`] from a format string without building a `String`.
+///
+/// The message is formatted into a fixed-size 256-byte buffer rather than a
+/// heap-allocated `String`, which avoids reallocating while the message is
+/// written. Messages that do not fit are truncated at a character boundary and
+/// end with `"..."`. The buffer itself is still boxed when it is stored in the
+/// `io::Error`.
+///
+/// Takes the same arguments as [`format!`], preceded by an [`ErrorKind`].
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`format!`]: https://doc.rust-lang.org/std/macro.format.html
+/// [`ErrorKind`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::format_err_stack;
+/// use std::io::ErrorKind;
+///
+/// let offset = 4096;
+/// let err = format_err_stack!(ErrorKind::InvalidInput, "offset {} is out of range", offset);
+/// assert_eq!(err.to_string(), "offset 4096 is out of range");
+/// ```
+#[macro_export]
+macro_rules! format_err_stack {
+    ($kind:expr, $($arg:tt)+) => {{
+        let mut msg = $crate::__private::StackMessage::default();
+        let _ = ::std::fmt::Write::write_fmt(&mut msg, format_args!($($arg)+));
+        ::std::io::Error::new($kind, msg)
+    }};
+}
+
+/// Creates an [`io::Error`] with a message returned by a closure.
+///
+/// Like `format_err!(kind, msg)`, but the message is built by calling the
+/// closure, which may return a `String` or a `&'static str`. This is mostly
+/// useful through [`ensure_with!`], which only calls the closure when its
+/// check fails.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_with!`]: crate::ensure_with
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::format_err_with;
+/// use std::io::ErrorKind;
+///
+/// let buf = [0xde, 0xad];
+/// let err = format_err_with!(ErrorKind::InvalidData, || format!("bad frame: {:02x?}", buf));
+/// assert_eq!(err.to_string(), "bad frame: [de, ad]");
+/// ```
+#[macro_export]
+macro_rules! format_err_with {
+    ($kind:expr, $f:expr $(,)?) => {{
+        $crate::__private::new_error($kind, ($f)())
     }};
 }
 
@@ -76,9 +396,19 @@ macro_rules! format_err {
 /// if the condition fails. Unlike `assert!`, `ensure!` returns an `io::Error`,
 /// it does not panic.
 ///
+/// The error is built with [`format_err!`], so the kind may be left out when
+/// the message is a string literal, giving an error of kind `ErrorKind::Other`.
+///
+/// The error is returned through [`From`], like the `?` operator does, so
+/// `ensure!` works in any function returning `Result<T, E>` where
+/// `E: From<io::Error>`. In a closure, this means the error type has to be
+/// known from its signature or use.
+///
 /// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`From`]: https://doc.rust-lang.org/std/convert/trait.From.html
 /// [`PartialEq`]: https://doc.rust-lang.org/std/cmp/trait.PartialEq.html
 /// [`assert!`]: https://doc.rust-lang.org/std/macro.assert.html
+/// [`format_err!`]: crate::format_err
 ///
 /// # Examples
 ///
@@ -91,38 +421,430 @@ macro_rules! format_err {
 /// let b = 1 + 2;
 /// ensure!(a == b, ErrorKind::Other);
 /// ensure!(a == b, ErrorKind::Interrupted, "we are testing addition with {} and {}", a, b);
+/// ensure!(a == b, "we are testing addition with {} and {}", a, b);
 /// # Ok(()) }
 /// ```
 #[macro_export]
 macro_rules! ensure {
+    ($cond:expr, $msg:literal, $($arg:tt)+) => {
+        if !$cond {
+            $crate::__ensure_bail!($cond, $msg, $($arg)+);
+        }
+    };
     ($cond:expr, $kind:expr, $msg:literal $(,)?) => {
         if !$cond {
-            return ::std::result::Result::Err($crate::format_err!($kind, $msg));
+            $crate::__ensure_bail!($cond, $kind, $msg);
         }
     };
     ($cond:expr, $kind:expr, $msg:expr $(,)?) => {
         if !$cond {
-            return ::std::result::Result::Err($crate::format_err!($kind, $msg));
+            $crate::__ensure_bail!($cond, $kind, $msg);
         }
     };
     ($cond:expr, $kind:expr $(,)?) => {
         if !$cond {
-            return ::std::result::Result::Err($crate::format_err!($kind));
+            $crate::__ensure_bail!($cond, $kind);
+        }
+    };
+    ($cond:expr, $kind:expr, $msg:literal, $($arg:tt)+) => {
+        if !$cond {
+            $crate::__ensure_bail!($cond, $kind, $msg, $($arg)+);
         }
     };
     ($cond:expr, $kind:expr, $msg:expr, $($arg:tt)*) => {
         if !$cond {
-            return ::std::result::Result::Err(format_err!($kind, $msg, $($arg)*));
+            $crate::__ensure_bail!($cond, $kind, $msg, $($arg)*);
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if the condition is not
+/// satisfied, running a closure before returning.
+///
+/// Takes the same arguments as [`ensure!`], followed by a closure. When the
+/// condition fails the error is constructed first, then the closure is called,
+/// and then the error is returned. This is useful for cleanup, logging, or
+/// recording metrics on the failure path.
+///
+/// The closure either takes no arguments, or takes the error as
+/// `&mut io::Error` so it can inspect or replace it.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure!`]: crate::ensure
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut failures = 0;
+/// let valid = true;
+/// ensure_then!(valid, ErrorKind::Other, "lock invalidated", || failures += 1);
+/// ensure_then!(valid, ErrorKind::Other, "lock {} invalidated", 7, |err: &mut io::Error| {
+///     eprintln!("{}", err);
+/// });
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_then {
+    (@munch $cond:tt $kind:tt [$($arg:expr),*] $f:expr $(,)?) => {
+        if !$cond {
+            let mut err = $crate::format_err!($kind, $($arg),*);
+            $crate::__private::EnsureThen::call($f, &mut err);
+            $crate::__ensure_failed!($cond, err);
+            return ::std::result::Result::Err(::std::convert::From::from(err));
+        }
+    };
+    (@munch $cond:tt $kind:tt [$($arg:expr),*] $next:expr, $($rest:tt)+) => {
+        $crate::ensure_then!(@munch $cond $kind [$($arg,)* $next] $($rest)+)
+    };
+    ($cond:expr, $kind:expr, $f:expr $(,)?) => {
+        if !$cond {
+            let mut err = $crate::format_err!($kind);
+            $crate::__private::EnsureThen::call($f, &mut err);
+            $crate::__ensure_failed!($cond, err);
+            return ::std::result::Result::Err(::std::convert::From::from(err));
+        }
+    };
+    ($cond:expr, $kind:expr, $($rest:tt)+) => {
+        $crate::ensure_then!(@munch $cond $kind [] $($rest)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if the condition is not
+/// satisfied, building the message with a closure.
+///
+/// The closure is only called when the condition fails, so the message may be
+/// expensive to build, such as a hex dump of a buffer. It may return a `String`
+/// or a `&'static str`. The error is built as with [`format_err_with!`].
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`format_err_with!`]: crate::format_err_with
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+///
+/// fn check_magic(buf: &[u8]) -> io::Result<()> {
+///     ensure_with!(buf.starts_with(b"\x7fELF"), ErrorKind::InvalidData, || {
+///         let dump: Vec<_> = buf.iter().map(|b| format!("{:02x}", b)).collect();
+///         format!("bad magic: {}", dump.join(" "))
+///     });
+///     Ok(())
+/// }
+///
+/// assert!(check_magic(b"\x7fELF\x02").is_ok());
+/// assert_eq!(check_magic(b"MZ").unwrap_err().to_string(), "bad magic: 4d 5a");
+/// ```
+#[macro_export]
+macro_rules! ensure_with {
+    ($cond:expr, $kind:expr, $f:expr $(,)?) => {
+        if !$cond {
+            let err = $crate::format_err_with!($kind, $f);
+            $crate::__ensure_failed!($cond, err);
+            return ::std::result::Result::Err(::std::convert::From::from(err));
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`].
+///
+/// Takes the same arguments as [`format_err!`] and returns the error it builds.
+/// This is an unconditional [`ensure!`], for `match` arms and branches that
+/// already know the input is invalid.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`format_err!`]: crate::format_err
+/// [`ensure!`]: crate::ensure
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+///
+/// fn opcode(byte: u8) -> io::Result<&'static str> {
+///     match byte {
+///         0x1 => Ok("text"),
+///         0x2 => Ok("binary"),
+///         0x8 => bail!(ErrorKind::ConnectionAborted),
+///         other => bail!(ErrorKind::InvalidData, "unknown opcode {:#x}", other),
+///     }
+/// }
+///
+/// assert_eq!(opcode(0x1).unwrap(), "text");
+/// assert_eq!(opcode(0x3).unwrap_err().to_string(), "unknown opcode 0x3");
+/// ```
+#[macro_export]
+macro_rules! bail {
+    ($($arg:tt)+) => {
+        return ::std::result::Result::Err(::std::convert::From::from($crate::format_err!($($arg)+)))
+    };
+}
+
+/// Creates an `Err` holding an [`io::Error`], without returning.
+///
+/// Takes the same arguments as [`format_err!`] and expands to
+/// `Err(format_err!(...))`. Unlike [`bail!`] it is an expression, for closures
+/// such as `map_err` and `and_then` and for `match` arms that produce a
+/// `Result`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`format_err!`]: crate::format_err
+/// [`bail!`]: crate::bail
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+///
+/// let lens = ["3", "x", "7"];
+/// let parsed: io::Result<Vec<u32>> = lens
+///     .iter()
+///     .map(|len| match len.parse() {
+///         Ok(len) => Ok(len),
+///         Err(_) => err!(ErrorKind::InvalidData, "invalid length {:?}", len),
+///     })
+///     .collect();
+/// assert_eq!(parsed.unwrap_err().to_string(), "invalid length \"x\"");
+///
+/// let res: io::Result<()> = err!(ErrorKind::WouldBlock);
+/// assert_eq!(res.unwrap_err().kind(), ErrorKind::WouldBlock);
+/// ```
+#[macro_export]
+macro_rules! err {
+    ($($arg:tt)+) => {
+        ::std::result::Result::Err($crate::format_err!($($arg)+))
+    };
+}
+
+/// Exits a function early with the [`io::Error`] returned by a closure.
+///
+/// The closure is always called; this is an unconditional early return for
+/// errors that are too involved to build with a single [`format_err!`] call.
+/// The closure may return an `io::Error`, or an `io::Result<io::Error>` if
+/// building the error can itself fail, in which case either error is returned.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`format_err!`]: crate::format_err
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+///
+/// fn parse(header: &[u8]) -> io::Result<()> {
+///     if header.len() < 4 {
+///         bail_with!(|| {
+///             let dump: Vec<_> = header.iter().map(|b| format!("{:02x}", b)).collect();
+///             io::Error::new(ErrorKind::InvalidData, format!("short header: {}", dump.join(" ")))
+///         });
+///     }
+///     Ok(())
+/// }
+///
+/// assert_eq!(parse(&[1, 2]).unwrap_err().to_string(), "short header: 01 02");
+/// ```
+#[macro_export]
+macro_rules! bail_with {
+    ($f:expr $(,)?) => {
+        return ::std::result::Result::Err(::std::convert::From::from(
+            $crate::__private::BailWith::into_bail(($f)()),
+        ))
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if an expression does not
+/// match a pattern.
+///
+/// Like `assert_matches!`, the pattern may have alternatives separated by `|`
+/// and an `if` guard. The bindings it introduces are only visible in the
+/// guard. When no message is given the error reports the value and the
+/// pattern, which requires the value to implement `Debug`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// #[derive(Debug)]
+/// enum Frame {
+///     Data(Vec<u8>),
+///     Ping,
+/// }
+///
+/// # fn main() -> std::io::Result<()> {
+/// let value = Some(Frame::Data(vec![1, 2, 3]));
+/// ensure_matches!(value, Some(Frame::Data(_)) | Some(Frame::Ping), ErrorKind::InvalidData);
+/// ensure_matches!(
+///     value,
+///     Some(Frame::Data(ref payload)) if !payload.is_empty(),
+///     ErrorKind::InvalidData,
+///     "unexpected frame {:?}",
+///     value
+/// );
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_matches {
+    ($value:expr, $($pat:pat)|+ $(if $guard:expr)?, $kind:expr $(,)?) => {
+        match $value {
+            $($pat)|+ $(if $guard)? => {}
+            ref value => $crate::__ensure_bail!(
+                $value,
+                $kind,
+                "{:?} does not match {}",
+                value,
+                stringify!($($pat)|+ $(if $guard)?)
+            ),
+        }
+    };
+    ($value:expr, $($pat:pat)|+ $(if $guard:expr)?, $kind:expr, $($arg:tt)+) => {
+        match $value {
+            $($pat)|+ $(if $guard)? => {}
+            _ => $crate::__ensure_bail!($value, $kind, $($arg)+),
         }
     };
 }
 
+/// Destructures a value with a pattern, exiting a function early with an
+/// [`io::Error`] if it does not match.
+///
+/// This is a `let ... else` statement whose `else` branch returns an error:
+/// the bindings of the pattern are available in the rest of the enclosing
+/// scope. When no message is given the error shows the expression and the
+/// pattern.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// enum Frame {
+///     Data { stream: u32, payload: Vec<u8> },
+///     Ping,
+/// }
+///
+/// # fn main() -> std::io::Result<()> {
+/// let frame = Frame::Data { stream: 1, payload: vec![1, 2] };
+/// ensure_let!(Frame::Data { stream, payload } = frame, ErrorKind::InvalidData, "expected a data frame");
+/// assert_eq!((stream, payload.len()), (1, 2));
+///
+/// let pair = Some((4, 8));
+/// ensure_let!(Some((start, end)) = pair, ErrorKind::InvalidInput);
+/// assert_eq!(end - start, 4);
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_let {
+    ($($pat:pat)|+ = $value:expr, $kind:expr $(,)?) => {
+        $crate::ensure_let!(
+            $($pat)|+ = $value,
+            $kind,
+            concat!(stringify!($value), " does not match ", stringify!($($pat)|+))
+        )
+    };
+    ($($pat:pat)|+ = $value:expr, $kind:expr, $($arg:tt)+) => {
+        let ($($pat)|+) = $value else {
+            $crate::__ensure_bail!($value, $kind, $($arg)+);
+        };
+    };
+}
+
+/// Unwraps an [`Option`], exiting a function early with an [`io::Error`] if it
+/// is `None`.
+///
+/// The macro evaluates to the value inside `Some`. When no message is given
+/// the error names the option by its expression.
+///
+/// [`Option`]: https://doc.rust-lang.org/std/option/enum.Option.html
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::collections::HashMap;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let entries: HashMap<_, _> = [("host", "localhost")].iter().copied().collect();
+/// let key = "host";
+/// let host = ensure_some!(entries.get(key), ErrorKind::NotFound);
+/// let host = ensure_some!(entries.get(key), ErrorKind::NotFound, "missing entry {}", key);
+/// assert_eq!(*host, "localhost");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_some {
+    ($option:expr, $kind:expr $(,)?) => {
+        $crate::ensure_some!($option, $kind, concat!(stringify!($option), " is None"))
+    };
+    ($option:expr, $kind:expr, $($arg:tt)+) => {
+        match $option {
+            ::std::option::Option::Some(value) => value,
+            ::std::option::Option::None => $crate::__ensure_bail!($option, $kind, $($arg)+),
+        }
+    };
+}
+
+/// Unwraps a [`Result`], exiting a function early with an [`io::Error`] if it
+/// is `Err`.
+///
+/// The macro evaluates to the value inside `Ok`. The result may have any error
+/// type that converts into `Box<dyn Error + Send + Sync>`, such as the errors
+/// of parsers and codecs, and the error is attached as the source of the
+/// returned `io::Error`, which has the given kind. When no message is given
+/// the error names the result by its expression.
+///
+/// [`Result`]: https://doc.rust-lang.org/std/result/enum.Result.html
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let header = "42";
+/// let len: u32 = ensure_ok!(header.parse(), ErrorKind::InvalidData);
+/// let len: u32 = ensure_ok!(header.parse(), ErrorKind::InvalidData, "bad header");
+/// assert_eq!(len, 42);
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_ok {
+    ($result:expr, $kind:expr $(,)?) => {
+        $crate::ensure_ok!($result, $kind, concat!(stringify!($result), " failed"))
+    };
+    ($result:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::__ensure_unwrap!($result, $result, $kind, $($arg)+)
+    };
+}
+
 /// Exits a function early with an [`io::Error`] if two expressions are not equal
 /// to each other.
 ///
 /// The comparison is performed using [`PartialEq`]. Similar to [`assert_eq!`],
 /// `ensure_eq!` takes two expressions and exits the function if the comparison
 /// fails. Unlike `assert_eq!`, `ensure!` returns an `io::Error`, it does not panic.
+/// Both expressions are evaluated exactly once.
+///
+/// The error message shows the comparison and the `Debug` representation of
+/// both values, followed by the message if one is given.
 ///
 /// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
 /// [`PartialEq`]: https://doc.rust-lang.org/std/cmp/trait.PartialEq.html
@@ -145,16 +867,16 @@ macro_rules! ensure {
 #[macro_export]
 macro_rules! ensure_eq {
     ($left:expr, $right:expr, $kind:expr, $msg:literal $(,)?) => {
-        $crate::ensure!($left == $right, $kind, $msg);
+        $crate::__ensure_cmp!(@append ==, $left, $right, $kind, $msg)
     };
     ($left:expr, $right:expr, $kind:expr, $msg:expr $(,)?) => {
-        $crate::ensure!($left == $right, $kind, $msg);
+        $crate::__ensure_cmp!(@append ==, $left, $right, $kind, "{}", $msg)
     };
     ($left:expr, $right:expr, $kind:expr, $msg:expr, $($arg:tt)*) => {
-        $crate::ensure!($left == $right, $kind, $msg, $($arg)*);
+        $crate::__ensure_cmp!(@append ==, $left, $right, $kind, $msg, $($arg)*)
     };
     ($left:expr, $right:expr, $kind:expr $(,)?) => {
-        $crate::ensure!($left == $right, $kind);
+        $crate::__ensure_cmp!(==, $left, $right, $kind)
     };
 }
 
@@ -164,6 +886,10 @@ macro_rules! ensure_eq {
 /// The comparison is performed using [`PartialEq`]. Similar to [`assert_eq!`],
 /// `ensure_eq!` takes two expressions and exits the function if the comparison
 /// fails. Unlike `assert_eq!`, `ensure!` returns an `io::Error`, it does not panic.
+/// Both expressions are evaluated exactly once.
+///
+/// The error message shows the comparison and the `Debug` representation of
+/// both values, followed by the message if one is given.
 ///
 /// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
 /// [`PartialEq`]: https://doc.rust-lang.org/std/cmp/trait.PartialEq.html
@@ -185,15 +911,280 @@ macro_rules! ensure_eq {
 #[macro_export]
 macro_rules! ensure_ne {
     ($left:expr, $right:expr, $kind:expr, $msg:literal $(,)?) => {
-        $crate::ensure!($left != $right, $kind, $msg);
+        $crate::__ensure_cmp!(@append !=, $left, $right, $kind, $msg)
     };
     ($left:expr, $right:expr, $kind:expr, $msg:expr $(,)?) => {
-        $crate::ensure!($left != $right, $kind, $msg);
+        $crate::__ensure_cmp!(@append !=, $left, $right, $kind, "{}", $msg)
     };
     ($left:expr, $right:expr, $kind:expr, $msg:expr, $($arg:tt)*) => {
-        $crate::ensure!($left != $right, $kind, $msg, $($arg)*);
+        $crate::__ensure_cmp!(@append !=, $left, $right, $kind, $msg, $($arg)*)
     };
     ($left:expr, $right:expr, $kind:expr $(,)?) => {
-        $crate::ensure!($left != $right, $kind);
+        $crate::__ensure_cmp!(!=, $left, $right, $kind)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if the condition is not
+/// satisfied, in builds with debug assertions enabled.
+///
+/// Like [`ensure!`], but only checked when `debug_assertions` is on, as with
+/// [`debug_assert!`]. This is meant for internal invariants that are too
+/// expensive to check in release builds. In optimized builds the check is
+/// compiled out, though its arguments are still type checked, so it must not
+/// guard anything the function relies on.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure!`]: crate::ensure
+/// [`debug_assert!`]: https://doc.rust-lang.org/std/macro.debug_assert.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let buf = vec![0u64; 4];
+/// debug_ensure!((buf.as_ptr() as usize).is_multiple_of(8), ErrorKind::Other, "buffer is misaligned");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! debug_ensure {
+    ($($arg:tt)+) => {
+        if ::std::cfg!(debug_assertions) {
+            $crate::ensure!($($arg)+);
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if two expressions are not
+/// equal, in builds with debug assertions enabled.
+///
+/// Like [`ensure_eq!`], but only checked when `debug_assertions` is on. See
+/// [`debug_ensure!`].
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_eq!`]: crate::ensure_eq
+/// [`debug_ensure!`]: crate::debug_ensure
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (written, expected) = (16, 16);
+/// debug_ensure_eq!(written, expected, ErrorKind::Other, "cursor out of sync");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! debug_ensure_eq {
+    ($($arg:tt)+) => {
+        if ::std::cfg!(debug_assertions) {
+            $crate::ensure_eq!($($arg)+);
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if two expressions are equal,
+/// in builds with debug assertions enabled.
+///
+/// Like [`ensure_ne!`], but only checked when `debug_assertions` is on. See
+/// [`debug_ensure!`].
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_ne!`]: crate::ensure_ne
+/// [`debug_ensure!`]: crate::debug_ensure
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (state, closed) = ("open", "closed");
+/// debug_ensure_ne!(state, closed, ErrorKind::Other);
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! debug_ensure_ne {
+    ($($arg:tt)+) => {
+        if ::std::cfg!(debug_assertions) {
+            $crate::ensure_ne!($($arg)+);
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] unless the first expression is
+/// greater than the second.
+///
+/// The comparison is performed using [`PartialOrd`], and both expressions are
+/// evaluated exactly once. When no message is given the error shows the
+/// comparison and both values, like the message of a failed [`assert!`],
+/// which requires the values to implement `Debug`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`PartialOrd`]: https://doc.rust-lang.org/std/cmp/trait.PartialOrd.html
+/// [`assert!`]: https://doc.rust-lang.org/std/macro.assert.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (len, min) = (8, 4);
+/// ensure_gt!(len, min, ErrorKind::UnexpectedEof);
+/// ensure_gt!(len, min, ErrorKind::UnexpectedEof, "need more than {} bytes, got {}", min, len);
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_gt {
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::__ensure_cmp!(>, $left, $right, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] unless the first expression is
+/// greater than or equal to the second.
+///
+/// The comparison is performed using [`PartialOrd`], and both expressions are
+/// evaluated exactly once. When no message is given the error shows the
+/// comparison and both values, like the message of a failed [`assert!`],
+/// which requires the values to implement `Debug`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`PartialOrd`]: https://doc.rust-lang.org/std/cmp/trait.PartialOrd.html
+/// [`assert!`]: https://doc.rust-lang.org/std/macro.assert.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (len, header_len) = (16, 16);
+/// ensure_ge!(len, header_len, ErrorKind::UnexpectedEof);
+/// ensure_ge!(len, header_len, ErrorKind::UnexpectedEof, "truncated header: {} bytes", len);
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_ge {
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::__ensure_cmp!(>=, $left, $right, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] unless the first expression is
+/// less than the second.
+///
+/// The comparison is performed using [`PartialOrd`], and both expressions are
+/// evaluated exactly once. When no message is given the error shows the
+/// comparison and both values, like the message of a failed [`assert!`],
+/// which requires the values to implement `Debug`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`PartialOrd`]: https://doc.rust-lang.org/std/cmp/trait.PartialOrd.html
+/// [`assert!`]: https://doc.rust-lang.org/std/macro.assert.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (offset, len) = (12, 16);
+/// ensure_lt!(offset, len, ErrorKind::InvalidInput);
+/// ensure_lt!(offset, len, ErrorKind::InvalidInput, "offset {} is past the end", offset);
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_lt {
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::__ensure_cmp!(<, $left, $right, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] unless the first expression is
+/// less than or equal to the second.
+///
+/// The comparison is performed using [`PartialOrd`], and both expressions are
+/// evaluated exactly once. When no message is given the error shows the
+/// comparison and both values, like the message of a failed [`assert!`],
+/// which requires the values to implement `Debug`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`PartialOrd`]: https://doc.rust-lang.org/std/cmp/trait.PartialOrd.html
+/// [`assert!`]: https://doc.rust-lang.org/std/macro.assert.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (frame_len, max_frame_len) = (1024, 4096);
+/// ensure_le!(frame_len, max_frame_len, ErrorKind::InvalidData);
+/// ensure_le!(frame_len, max_frame_len, ErrorKind::InvalidData, "frame of {} bytes is too large", frame_len);
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_le {
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::__ensure_cmp!(<=, $left, $right, $($arg)+)
+    };
+}
+
+/// Compares two expressions with an operator, with the default message of the
+/// `ensure_eq!` and `ensure_gt!` families. The `@append` form adds a message
+/// after it.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_cmp {
+    (@append $op:tt, $left:expr, $right:expr, $kind:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left_val, right_val) => {
+                $crate::ensure!(
+                    *left_val $op *right_val,
+                    $kind,
+                    "check `{} {} {}` failed (left: {:?}, right: {:?}): {}",
+                    stringify!($left),
+                    stringify!($op),
+                    stringify!($right),
+                    left_val,
+                    right_val,
+                    format_args!($($arg)+)
+                );
+            }
+        }
+    };
+    ($op:tt, $left:expr, $right:expr, $kind:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left_val, right_val) => {
+                $crate::ensure!(
+                    *left_val $op *right_val,
+                    $kind,
+                    "check `{} {} {}` failed (left: {:?}, right: {:?})",
+                    stringify!($left),
+                    stringify!($op),
+                    stringify!($right),
+                    left_val,
+                    right_val
+                );
+            }
+        }
+    };
+    ($op:tt, $left:expr, $right:expr, $kind:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left_val, right_val) => {
+                $crate::ensure!(*left_val $op *right_val, $kind, $($arg)+);
+            }
+        }
     };
 }
@@ -0,0 +1,104 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Structured key-value metadata attached to an [`io::Error`].
+///
+/// Pairs are added with [`IoErrorMetadata::with`], and [`IoErrorMetadata::wrap`]
+/// turns the metadata into an `io::Error` of the same kind as the original. The
+/// metadata can be read back by downcasting the error's payload. Its
+/// [`Display`] implementation appends the pairs to the original message as
+/// `[key=value, ...]`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`Display`]: https://doc.rust-lang.org/std/fmt/trait.Display.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// let err = IoErrorMetadata::new(format_err!(ErrorKind::PermissionDenied, "token rejected"))
+///     .with("request_id", "abc-123")
+///     .with("component", "auth_service")
+///     .wrap();
+/// assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+/// assert_eq!(err.to_string(), "token rejected [request_id=abc-123, component=auth_service]");
+///
+/// let metadata = downcast_io_err!(err, IoErrorMetadata).unwrap();
+/// assert_eq!(metadata.get("request_id"), Some("abc-123"));
+/// ```
+#[derive(Debug)]
+pub struct IoErrorMetadata {
+    error: io::Error,
+    pairs: Vec<(String, String)>,
+}
+
+impl IoErrorMetadata {
+    /// Creates empty metadata for an error.
+    pub fn new(error: io::Error) -> Self {
+        Self {
+            error,
+            pairs: Vec::new(),
+        }
+    }
+
+    /// Adds a key-value pair.
+    pub fn with(mut self, key: impl Into<String>, value: impl fmt::Display) -> Self {
+        self.pairs.push((key.into(), value.to_string()));
+        self
+    }
+
+    /// Returns the value of the first pair with the given key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns all key-value pairs in the order they were added.
+    pub fn pairs(&self) -> &[(String, String)] {
+        &self.pairs
+    }
+
+    /// Returns the original error.
+    pub fn error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// Converts the metadata into an `io::Error` with the original kind.
+    pub fn wrap(self) -> io::Error {
+        io::Error::new(self.error.kind(), self)
+    }
+}
+
+impl fmt::Display for IoErrorMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        if self.pairs.is_empty() {
+            return Ok(());
+        }
+        f.write_str(" [")?;
+        for (index, (key, value)) in self.pairs.iter().enumerate() {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}={}", key, value)?;
+        }
+        f.write_str("]")
+    }
+}
+
+impl Error for IoErrorMetadata {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+impl From<IoErrorMetadata> for io::Error {
+    fn from(metadata: IoErrorMetadata) -> Self {
+        metadata.wrap()
+    }
+}
@@ -0,0 +1,78 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, ErrorKind};
+
+/// A collection of [`io::Error`]s reported together.
+///
+/// This is produced by [`batch_ensure!`], which runs every check before
+/// returning. It converts into a single `io::Error` with the kind of the first
+/// error, which can be downcast back into a `MultiError`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`batch_ensure!`]: crate::batch_ensure
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+///
+/// let multi = MultiError::new(vec![
+///     format_err!(ErrorKind::InvalidInput, "name is empty"),
+///     format_err!(ErrorKind::InvalidInput, "age is negative"),
+/// ]);
+/// assert_eq!(multi.to_string(), "2 checks failed: name is empty; age is negative");
+///
+/// let err: io::Error = multi.into();
+/// assert_eq!(err.kind(), ErrorKind::InvalidInput);
+/// assert_eq!(downcast_io_err!(err, MultiError).unwrap().errors().len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct MultiError {
+    errors: Vec<io::Error>,
+}
+
+impl MultiError {
+    /// Creates a `MultiError` from a list of errors.
+    pub fn new(errors: Vec<io::Error>) -> Self {
+        Self { errors }
+    }
+
+    /// Returns the collected errors.
+    pub fn errors(&self) -> &[io::Error] {
+        &self.errors
+    }
+
+    /// Returns the collected errors by value.
+    pub fn into_errors(self) -> Vec<io::Error> {
+        self.errors
+    }
+}
+
+impl fmt::Display for MultiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.errors.len() {
+            1 => write!(f, "1 check failed: ")?,
+            n => write!(f, "{} checks failed: ", n)?,
+        }
+        for (index, err) in self.errors.iter().enumerate() {
+            if index > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}", err)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for MultiError {}
+
+impl From<MultiError> for io::Error {
+    fn from(multi: MultiError) -> Self {
+        let kind = multi
+            .errors
+            .first()
+            .map_or(ErrorKind::Other, io::Error::kind);
+        io::Error::new(kind, multi)
+    }
+}
@@ -0,0 +1,179 @@
+//! Macros validating network addresses and ports.
+
+/// Parses a string as an [`IpAddr`], exiting a function early with an
+/// [`io::Error`] if it is not a valid IPv4 or IPv6 address.
+///
+/// The macro evaluates to the parsed address. The input may be anything
+/// implementing `AsRef<str>`, and the [`AddrParseError`] is attached as the
+/// error's source. When no message is given the error reports the input.
+///
+/// [`IpAddr`]: https://doc.rust-lang.org/std/net/enum.IpAddr.html
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`AddrParseError`]: https://doc.rust-lang.org/std/net/struct.AddrParseError.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let ip_str = "192.168.1.1";
+/// ensure_ip_valid!(ip_str, ErrorKind::InvalidInput);
+/// let addr = ensure_ip_valid!(ip_str, ErrorKind::InvalidInput, "invalid IP address: {}", ip_str);
+/// assert!(addr.is_ipv4());
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_ip_valid {
+    ($input:expr, $($arg:tt)+) => {
+        $crate::__ensure_parse!(::std::net::IpAddr, "IP", $input, $($arg)+)
+    };
+}
+
+/// Parses a string as an [`Ipv4Addr`], exiting a function early with an
+/// [`io::Error`] if it is not a valid IPv4 address.
+///
+/// Like [`ensure_ip_valid!`], but IPv6 addresses are rejected.
+///
+/// [`Ipv4Addr`]: https://doc.rust-lang.org/std/net/struct.Ipv4Addr.html
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_ip_valid!`]: crate::ensure_ip_valid
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let addr = ensure_ipv4_valid!("10.0.0.1", ErrorKind::InvalidInput);
+/// assert!(addr.is_private());
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_ipv4_valid {
+    ($input:expr, $($arg:tt)+) => {
+        $crate::__ensure_parse!(::std::net::Ipv4Addr, "IPv4", $input, $($arg)+)
+    };
+}
+
+/// Parses a string as an [`Ipv6Addr`], exiting a function early with an
+/// [`io::Error`] if it is not a valid IPv6 address.
+///
+/// Like [`ensure_ip_valid!`], but IPv4 addresses are rejected.
+///
+/// [`Ipv6Addr`]: https://doc.rust-lang.org/std/net/struct.Ipv6Addr.html
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_ip_valid!`]: crate::ensure_ip_valid
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let addr = ensure_ipv6_valid!("::1", ErrorKind::InvalidInput, "expected an IPv6 address");
+/// assert!(addr.is_loopback());
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_ipv6_valid {
+    ($input:expr, $($arg:tt)+) => {
+        $crate::__ensure_parse!(::std::net::Ipv6Addr, "IPv6", $input, $($arg)+)
+    };
+}
+
+/// Parses a string, attaching the parse error as the source on failure.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_parse {
+    ($ty:ty, $what:literal, $input:expr, $kind:expr $(,)?) => {
+        match ::std::convert::AsRef::<str>::as_ref(&$input) {
+            input => $crate::__ensure_parse!(
+                $ty, $what, input, $kind, "{:?} is not a valid {} address", input, $what
+            ),
+        }
+    };
+    ($ty:ty, $what:literal, $input:expr, $kind:expr, $($arg:tt)+) => {
+        match ::std::convert::AsRef::<str>::as_ref(&$input) {
+            input => $crate::__ensure_unwrap!($input, input.parse::<$ty>(), $kind, $($arg)+),
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a number is not a valid
+/// TCP or UDP port, in the range 1 to 65535.
+///
+/// Works with any integer type, so ports read as wider or signed integers can
+/// be checked before narrowing them to `u16`. When no message is given the
+/// error reports the port.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let port_num: u32 = 8080;
+/// ensure_port_valid!(port_num, ErrorKind::InvalidInput);
+/// ensure_port_valid!(port_num, ErrorKind::InvalidInput, "port {} is out of range 1-65535", port_num);
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_port_valid {
+    ($port:expr, $kind:expr $(,)?) => {
+        match $port {
+            port => $crate::ensure!(
+                $crate::__private::port_at_least(port, 1),
+                $kind,
+                "port {} is out of range 1-65535",
+                port
+            ),
+        }
+    };
+    ($port:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!($crate::__private::port_at_least($port, 1), $kind, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a number is not an
+/// unprivileged port, in the range 1024 to 65535.
+///
+/// Like [`ensure_port_valid!`], but also rejects the well-known ports below
+/// 1024, which usually need elevated privileges to bind.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_port_valid!`]: crate::ensure_port_valid
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let port_num: u16 = 8080;
+/// ensure_unprivileged_port!(port_num, ErrorKind::PermissionDenied);
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_unprivileged_port {
+    ($port:expr, $kind:expr $(,)?) => {
+        match $port {
+            port => $crate::ensure!(
+                $crate::__private::port_at_least(port, 1024),
+                $kind,
+                "port {} is out of range 1024-65535",
+                port
+            ),
+        }
+    };
+    ($port:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!($crate::__private::port_at_least($port, 1024), $kind, $($arg)+)
+    };
+}
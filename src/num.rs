@@ -0,0 +1,336 @@
+//! Macros validating numbers.
+
+/// Exits a function early with an [`io::Error`] if an address is not a
+/// multiple of an alignment.
+///
+/// Both the address and the alignment are `usize` values, so the check is
+/// plain arithmetic and needs no unsafe code. When no message is given the
+/// error reports the address in hex and the alignment.
+///
+/// An alignment of zero fails the check with `ErrorKind::InvalidInput` and the
+/// message "alignment must be non-zero", whatever kind and message are given.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let addr: usize = 0x1000;
+/// ensure_aligned!(addr, std::mem::align_of::<u32>(), ErrorKind::InvalidInput);
+/// ensure_aligned!(addr, std::mem::align_of::<u32>(), ErrorKind::InvalidInput, "address 0x{:x} is not u32-aligned", addr);
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_aligned {
+    ($addr:expr, $align:expr, $kind:expr $(,)?) => {
+        match ($addr, $align) {
+            (addr, align) => {
+                let (addr, align): (usize, usize) = (addr, align);
+                $crate::ensure!(
+                    align != 0,
+                    ::std::io::ErrorKind::InvalidInput,
+                    "alignment must be non-zero"
+                );
+                $crate::ensure!(
+                    addr % align == 0,
+                    $kind,
+                    "address {:#x} is not aligned to {} bytes",
+                    addr,
+                    align
+                );
+            }
+        }
+    };
+    ($addr:expr, $align:expr, $kind:expr, $($arg:tt)+) => {
+        match ($addr, $align) {
+            (addr, align) => {
+                let (addr, align): (usize, usize) = (addr, align);
+                $crate::ensure!(
+                    align != 0,
+                    ::std::io::ErrorKind::InvalidInput,
+                    "alignment must be non-zero"
+                );
+                $crate::ensure!(addr % align == 0, $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a value is outside an
+/// inclusive range.
+///
+/// This is a lower-bound and an upper-bound check in one call: the check
+/// passes when `lower <= value && value <= upper`. When no message is given
+/// the error says which bound was violated, naming the value by its
+/// expression, which requires the value and bounds to implement `Display`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let array = [0u8; 48];
+/// let index = 12;
+/// ensure_within_bounds!(index, 0, array.len(), ErrorKind::InvalidInput);
+/// ensure_within_bounds!(index, 0, array.len(), ErrorKind::InvalidInput, "index {} out of range", index);
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_within_bounds {
+    ($value:expr, $lower:expr, $upper:expr, $kind:expr $(,)?) => {
+        match (&$value, &$lower, &$upper) {
+            (value, lower, upper) => {
+                $crate::ensure!(
+                    *lower <= *value,
+                    $kind,
+                    "{} {} is below lower bound {}",
+                    stringify!($value),
+                    value,
+                    lower
+                );
+                $crate::ensure!(
+                    *value <= *upper,
+                    $kind,
+                    "{} {} exceeds upper bound {}",
+                    stringify!($value),
+                    value,
+                    upper
+                );
+            }
+        }
+    };
+    ($value:expr, $lower:expr, $upper:expr, $kind:expr, $($arg:tt)+) => {
+        match (&$value, &$lower, &$upper) {
+            (value, lower, upper) => {
+                $crate::ensure!(*lower <= *value && *value <= *upper, $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a value is not strictly
+/// between two bounds.
+///
+/// The check passes when `low < value && value < high`, so a value equal to
+/// either bound fails. Use [`ensure_within_bounds!`] when the bounds themselves
+/// are allowed. When no message is given the error names the value by its
+/// expression and reports it with both bounds, which requires them to
+/// implement `Display`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_within_bounds!`]: crate::ensure_within_bounds
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let value = 42;
+/// ensure_exclusive_range!(value, 0, 100, ErrorKind::InvalidInput);
+/// ensure_exclusive_range!(value, 0, 100, ErrorKind::InvalidInput, "value must be strictly between 0 and 100");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_exclusive_range {
+    ($value:expr, $low:expr, $high:expr, $kind:expr $(,)?) => {
+        match (&$value, &$low, &$high) {
+            (value, low, high) => {
+                $crate::ensure!(
+                    *low < *value && *value < *high,
+                    $kind,
+                    "{} {} is not strictly between {} and {}",
+                    stringify!($value),
+                    value,
+                    low,
+                    high
+                );
+            }
+        }
+    };
+    ($value:expr, $low:expr, $high:expr, $kind:expr, $($arg:tt)+) => {
+        match (&$value, &$low, &$high) {
+            (value, low, high) => {
+                $crate::ensure!(*low < *value && *value < *high, $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a value is not divisible
+/// by a divisor.
+///
+/// The check passes when `value % divisor == 0`. [`ensure_multiple_of!`]
+/// performs the same check under the other common wording. When no message is
+/// given the error names the value by its expression and reports the value
+/// and the divisor.
+///
+/// A divisor of zero fails the check with `ErrorKind::InvalidInput` and the
+/// message "divisor must be non-zero", whatever kind and message are given.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_multiple_of!`]: crate::ensure_multiple_of
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let block_len = 4096;
+/// ensure_divisible_by!(block_len, 512, ErrorKind::InvalidInput);
+/// ensure_divisible_by!(block_len, 512, ErrorKind::InvalidInput, "blocks must fill whole sectors");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_divisible_by {
+    ($value:expr, $divisor:expr, $($arg:tt)+) => {
+        $crate::__ensure_divisible!("is not divisible by", $value, $divisor, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a value is not a multiple
+/// of another.
+///
+/// This is the same check as [`ensure_divisible_by!`], `value % n == 0`, for
+/// code that reads better as "a multiple of 16". When no message is given the
+/// error says the value must be a multiple of `n`.
+///
+/// An `n` of zero fails the check with `ErrorKind::InvalidInput` and the
+/// message "divisor must be non-zero", whatever kind and message are given.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_divisible_by!`]: crate::ensure_divisible_by
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let stride = 32;
+/// ensure_multiple_of!(stride, 16, ErrorKind::InvalidInput);
+/// ensure_multiple_of!(stride, 16, ErrorKind::InvalidInput, "stride must be 16-byte aligned");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_multiple_of {
+    ($value:expr, $n:expr, $($arg:tt)+) => {
+        $crate::__ensure_divisible!("must be a multiple of", $value, $n, $($arg)+)
+    };
+}
+
+/// Checks `value % divisor == 0`, with the default message's wording.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_divisible {
+    ($wording:literal, $value:expr, $divisor:expr, $kind:expr $(,)?) => {
+        match (&$value, &$divisor) {
+            (value, divisor) => {
+                $crate::ensure!(
+                    !$crate::__private::is_zero(divisor),
+                    ::std::io::ErrorKind::InvalidInput,
+                    "divisor must be non-zero"
+                );
+                $crate::ensure!(
+                    *value % *divisor == 0,
+                    $kind,
+                    concat!("{} {} ", $wording, " {}"),
+                    stringify!($value),
+                    value,
+                    divisor
+                );
+            }
+        }
+    };
+    ($wording:literal, $value:expr, $divisor:expr, $kind:expr, $($arg:tt)+) => {
+        match (&$value, &$divisor) {
+            (value, divisor) => {
+                $crate::ensure!(
+                    !$crate::__private::is_zero(divisor),
+                    ::std::io::ErrorKind::InvalidInput,
+                    "divisor must be non-zero"
+                );
+                $crate::ensure!(*value % *divisor == 0, $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a number is zero.
+///
+/// Works with any integer type. When no message is given the error names the
+/// value by its expression.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let chunk_size: u32 = 4096;
+/// ensure_non_zero!(chunk_size, ErrorKind::InvalidInput);
+/// ensure_non_zero!(chunk_size, ErrorKind::InvalidInput, "chunk size must be positive");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_non_zero {
+    ($value:expr, $kind:expr $(,)?) => {
+        $crate::ensure!($value != 0, $kind, concat!(stringify!($value), " must not be zero"))
+    };
+    ($value:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!($value != 0, $kind, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a pointer-sized address is
+/// null.
+///
+/// This is [`ensure_non_zero!`] for `usize` values holding addresses, such as
+/// pointers returned over FFI and cast with `as usize`. It only compares the
+/// number to zero, so it needs no unsafe code. When no message is given the
+/// error names the address by its expression.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_non_zero!`]: crate::ensure_non_zero
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let value = 7;
+/// let ffi_result: *const i32 = &value;
+/// ensure_non_null!(ffi_result as usize, ErrorKind::InvalidInput);
+/// ensure_non_null!(ffi_result as usize, ErrorKind::InvalidInput, "FFI returned null pointer");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_non_null {
+    ($addr:expr, $kind:expr $(,)?) => {
+        $crate::ensure!(
+            $crate::__private::is_non_null($addr),
+            $kind,
+            concat!(stringify!($addr), " is a null pointer")
+        )
+    };
+    ($addr:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!($crate::__private::is_non_null($addr), $kind, $($arg)+)
+    };
+}
@@ -0,0 +1,402 @@
+//! Macros validating structured text formats.
+
+/// Parses a byte payload as JSON, exiting a function early with an
+/// [`io::Error`] if it is not valid JSON.
+///
+/// The macro evaluates to the parsed [`serde_json::Value`]. The payload may be
+/// anything implementing `AsRef<[u8]>`, and the [`serde_json::Error`] is
+/// attached as the error's source. Empty input is not valid JSON. Use
+/// [`ensure_json_valid_as!`] to check the payload against a specific type.
+///
+/// This macro is only available with the `serde_json` feature enabled.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`serde_json::Value`]: https://docs.rs/serde_json/1/serde_json/enum.Value.html
+/// [`serde_json::Error`]: https://docs.rs/serde_json/1/serde_json/struct.Error.html
+/// [`ensure_json_valid_as!`]: crate::ensure_json_valid_as
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let payload = br#"{"user": "ferris"}"#;
+/// ensure_json_valid!(payload, ErrorKind::InvalidData);
+/// let value = ensure_json_valid!(payload, ErrorKind::InvalidData, "request body is not valid JSON");
+/// assert_eq!(value["user"], "ferris");
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "serde_json")]
+#[macro_export]
+macro_rules! ensure_json_valid {
+    ($payload:expr, $kind:expr $(,)?) => {
+        $crate::ensure_json_valid!($payload, $kind, "payload is not valid JSON")
+    };
+    ($payload:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::ensure_json_valid_as!($payload, $crate::__private::serde_json::Value, $kind, $($arg)+)
+    };
+}
+
+/// Deserializes a byte payload from JSON into a type, exiting a function early
+/// with an [`io::Error`] if it is not valid JSON for that type.
+///
+/// The macro evaluates to the deserialized value, so the type must implement
+/// [`DeserializeOwned`]. The [`serde_json::Error`] is attached as the error's
+/// source. When no message is given the error names the expected type.
+///
+/// This macro is only available with the `serde_json` feature enabled.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`DeserializeOwned`]: https://docs.rs/serde/1/serde/de/trait.DeserializeOwned.html
+/// [`serde_json::Error`]: https://docs.rs/serde_json/1/serde_json/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::collections::HashMap;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let payload = br#"{"retries": 3}"#;
+/// let config = ensure_json_valid_as!(payload, HashMap<String, u32>, ErrorKind::InvalidData);
+/// assert_eq!(config["retries"], 3);
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "serde_json")]
+#[macro_export]
+macro_rules! ensure_json_valid_as {
+    ($payload:expr, $ty:ty, $kind:expr $(,)?) => {
+        $crate::ensure_json_valid_as!(
+            $payload,
+            $ty,
+            $kind,
+            "payload is not valid JSON for {}",
+            ::std::any::type_name::<$ty>()
+        )
+    };
+    ($payload:expr, $ty:ty, $kind:expr, $($arg:tt)+) => {
+        $crate::__ensure_unwrap!(
+            $payload,
+            $crate::__private::serde_json::from_slice::<$ty>(
+                ::std::convert::AsRef::<[u8]>::as_ref(&$payload)
+            ),
+            $kind,
+            $($arg)+
+        )
+    };
+}
+
+/// Parses a string as an absolute [`Url`], exiting a function early with an
+/// [`io::Error`] if it is not a valid URL.
+///
+/// The macro evaluates to the parsed URL. The input may be anything
+/// implementing `AsRef<str>`, and the [`url::ParseError`] is attached as the
+/// error's source. Relative URLs have no base to resolve against and fail the
+/// check. When no message is given the error reports the input.
+///
+/// This macro is only available with the `url` feature enabled.
+///
+/// [`Url`]: https://docs.rs/url/2/url/struct.Url.html
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`url::ParseError`]: https://docs.rs/url/2/url/enum.ParseError.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let url_str = "ftp://ftp.example.com/pub";
+/// ensure_url_valid!(url_str, ErrorKind::InvalidInput);
+/// let url = ensure_url_valid!(url_str, ErrorKind::InvalidInput, "invalid URL: {}", url_str);
+/// assert_eq!(url.scheme(), "ftp");
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "url")]
+#[macro_export]
+macro_rules! ensure_url_valid {
+    ($input:expr, $kind:expr $(,)?) => {
+        match ::std::convert::AsRef::<str>::as_ref(&$input) {
+            input => $crate::ensure_url_valid!(input, $kind, "{:?} is not a valid URL", input),
+        }
+    };
+    ($input:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::__ensure_unwrap!(
+            $input,
+            $crate::__private::url::Url::parse(::std::convert::AsRef::<str>::as_ref(&$input)),
+            $kind,
+            $($arg)+
+        )
+    };
+}
+
+/// Parses a string as an `https` [`Url`], exiting a function early with an
+/// [`io::Error`] if it is not a valid URL or uses another scheme.
+///
+/// Like [`ensure_url_valid!`], but the scheme must also be `https`.
+///
+/// This macro is only available with the `url` feature enabled.
+///
+/// [`Url`]: https://docs.rs/url/2/url/struct.Url.html
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_url_valid!`]: crate::ensure_url_valid
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let url = ensure_https_url!("https://example.com/login", ErrorKind::InvalidInput);
+/// assert_eq!(url.host_str(), Some("example.com"));
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "url")]
+#[macro_export]
+macro_rules! ensure_https_url {
+    ($input:expr, $($arg:tt)+) => {
+        $crate::__ensure_url_scheme!("an https", ["https"], $input, $($arg)+)
+    };
+}
+
+/// Parses a string as an `http` or `https` [`Url`], exiting a function early
+/// with an [`io::Error`] if it is not a valid URL or uses another scheme.
+///
+/// Like [`ensure_url_valid!`], but the scheme must also be `http` or `https`.
+///
+/// This macro is only available with the `url` feature enabled.
+///
+/// [`Url`]: https://docs.rs/url/2/url/struct.Url.html
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_url_valid!`]: crate::ensure_url_valid
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let endpoint = "http://localhost:8080/health";
+/// let url = ensure_http_url!(endpoint, ErrorKind::InvalidInput, "{} is not a web address", endpoint);
+/// assert_eq!(url.port(), Some(8080));
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "url")]
+#[macro_export]
+macro_rules! ensure_http_url {
+    ($input:expr, $($arg:tt)+) => {
+        $crate::__ensure_url_scheme!("an http or https", ["http", "https"], $input, $($arg)+)
+    };
+}
+
+/// Parses a URL and checks that its scheme matches a pattern.
+#[cfg(feature = "url")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_url_scheme {
+    ($what:literal, [$($scheme:literal),+], $input:expr, $kind:expr $(,)?) => {
+        match ::std::convert::AsRef::<str>::as_ref(&$input) {
+            input => {
+                let url = $crate::ensure_url_valid!(input, $kind);
+                $crate::ensure!(
+                    matches!(url.scheme(), $($scheme)|+),
+                    $kind,
+                    "{:?} is not {} URL",
+                    input,
+                    $what
+                );
+                url
+            }
+        }
+    };
+    ($what:literal, [$($scheme:literal),+], $input:expr, $kind:expr, $($arg:tt)+) => {
+        match ::std::convert::AsRef::<str>::as_ref(&$input) {
+            input => {
+                let url = $crate::ensure_url_valid!(input, $kind, $($arg)+);
+                $crate::ensure!(matches!(url.scheme(), $($scheme)|+), $kind, $($arg)+);
+                url
+            }
+        }
+    };
+}
+
+/// Parses a string as a [`Uuid`], exiting a function early with an
+/// [`io::Error`] if it is not a valid UUID.
+///
+/// The macro evaluates to the parsed UUID. Hyphenated, simple, braced, and
+/// `urn:uuid:` prefixed forms are all accepted, including the nil UUID. The
+/// input may be anything implementing `AsRef<str>`, and the [`uuid::Error`] is
+/// attached as the error's source. When no message is given the error reports
+/// the input.
+///
+/// This macro is only available with the `uuid` feature enabled.
+///
+/// [`Uuid`]: https://docs.rs/uuid/1/uuid/struct.Uuid.html
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`uuid::Error`]: https://docs.rs/uuid/1/uuid/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let id_str = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+/// ensure_uuid_valid!(id_str, ErrorKind::InvalidInput);
+/// let id = ensure_uuid_valid!(id_str, ErrorKind::InvalidInput, "invalid UUID: {}", id_str);
+/// assert_eq!(id.get_version_num(), 4);
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "uuid")]
+#[macro_export]
+macro_rules! ensure_uuid_valid {
+    ($input:expr, $kind:expr $(,)?) => {
+        match ::std::convert::AsRef::<str>::as_ref(&$input) {
+            input => $crate::ensure_uuid_valid!(input, $kind, "{:?} is not a valid UUID", input),
+        }
+    };
+    ($input:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::__ensure_unwrap!(
+            $input,
+            $crate::__private::uuid::Uuid::parse_str(::std::convert::AsRef::<str>::as_ref(&$input)),
+            $kind,
+            $($arg)+
+        )
+    };
+}
+
+/// Parses a string as a semantic [`Version`], exiting a function early with an
+/// [`io::Error`] if it is not a valid version or does not satisfy a
+/// requirement.
+///
+/// The macro evaluates to the parsed version. The requirement must be a string
+/// literal using the syntax of [`VersionReq`], such as `">= 1.2, < 2.0"`, and
+/// an invalid requirement is a compile error. It is parsed on first use and
+/// cached for all later calls made from the same call site; should `semver`
+/// still reject it, the check fails with `ErrorKind::InvalidInput` instead of
+/// panicking. A version that fails to parse has the [`semver::Error`] attached
+/// as the source. As with `semver`, pre-release versions only satisfy
+/// requirements naming a pre-release of the same `major.minor.patch`. When no
+/// message is given the error reports the version and the requirement.
+///
+/// This macro is only available with the `semver` feature enabled.
+///
+/// [`Version`]: https://docs.rs/semver/1/semver/struct.Version.html
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`VersionReq`]: https://docs.rs/semver/1/semver/struct.VersionReq.html
+/// [`semver::Error`]: https://docs.rs/semver/1/semver/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (plugin_name, plugin_version) = ("fmt", "1.4.2");
+/// ensure_version_compat!(plugin_version, ">= 1.2, < 2.0", ErrorKind::Other);
+/// let version = ensure_version_compat!(
+///     plugin_version,
+///     ">= 1.2, < 2.0",
+///     ErrorKind::Other,
+///     "plugin {} requires version >= 1.2, < 2.0, found {}",
+///     plugin_name,
+///     plugin_version
+/// );
+/// assert_eq!(version.minor, 4);
+/// # Ok(()) }
+/// ```
+///
+/// An invalid requirement does not compile:
+///
+/// ```compile_fail
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// ensure_version_compat!("1.4.2", ">= one", ErrorKind::Other);
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "semver")]
+#[macro_export]
+macro_rules! ensure_version_compat {
+    ($version:expr, $req:literal, $kind:expr $(,)?) => {
+        match ::std::convert::AsRef::<str>::as_ref(&$version) {
+            input => {
+                let version = $crate::__ensure_unwrap!(
+                    $version,
+                    $crate::__private::semver::Version::parse(input),
+                    $kind,
+                    "{:?} is not a valid version",
+                    input
+                );
+                let req = $crate::__ensure_unwrap!(
+                    $req,
+                    $crate::__version_req!($req),
+                    ::std::io::ErrorKind::InvalidInput,
+                    "invalid version requirement {:?}",
+                    $req
+                );
+                $crate::ensure!(
+                    req.matches(&version),
+                    $kind,
+                    "version {} does not satisfy {:?}",
+                    version,
+                    $req
+                );
+                version
+            }
+        }
+    };
+    ($version:expr, $req:literal, $kind:expr, $($arg:tt)+) => {
+        match ::std::convert::AsRef::<str>::as_ref(&$version) {
+            input => {
+                let version = $crate::__ensure_unwrap!(
+                    $version,
+                    $crate::__private::semver::Version::parse(input),
+                    $kind,
+                    $($arg)+
+                );
+                let req = $crate::__ensure_unwrap!(
+                    $req,
+                    $crate::__version_req!($req),
+                    ::std::io::ErrorKind::InvalidInput,
+                    "invalid version requirement {:?}",
+                    $req
+                );
+                $crate::ensure!(req.matches(&version), $kind, $($arg)+);
+                version
+            }
+        }
+    };
+}
+
+/// Parses a version requirement once per call site, after checking at compile
+/// time that it is valid.
+#[cfg(feature = "semver")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __version_req {
+    ($req:literal) => {{
+        const _: () = ::std::assert!(
+            $crate::__private::is_valid_version_req($req),
+            "invalid version requirement"
+        );
+        static REQ: ::std::sync::OnceLock<
+            ::std::result::Result<
+                $crate::__private::semver::VersionReq,
+                $crate::__private::semver::Error,
+            >,
+        > = ::std::sync::OnceLock::new();
+        REQ.get_or_init(|| $crate::__private::semver::VersionReq::parse($req))
+            .as_ref()
+            .map_err(::std::string::ToString::to_string)
+    }};
+}
@@ -0,0 +1,511 @@
+//! Macros validating paths.
+
+/// Exits a function early with an [`io::Error`] if a path has more than `max`
+/// components.
+///
+/// Components are counted with [`Path::components`], so root and `..`
+/// components count towards the limit while `.` components in the middle of
+/// the path are skipped. The path may be anything implementing
+/// `AsRef<Path>`. When no message is given the error reports the path, its
+/// component count, and the limit.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`Path::components`]: https://doc.rust-lang.org/std/path/struct.Path.html#method.components
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let user_path = Path::new("uploads/2024/avatar.png");
+/// ensure_path_component_count!(user_path, 10, ErrorKind::InvalidInput);
+/// ensure_path_component_count!(user_path, 10, ErrorKind::InvalidInput, "path {} has too many components", user_path.display());
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_path_component_count {
+    ($path:expr, $max:expr, $kind:expr $(,)?) => {
+        match (::std::convert::AsRef::<::std::path::Path>::as_ref(&$path), $max) {
+            (path, max) => {
+                let count = path.components().count();
+                $crate::ensure!(
+                    count <= max,
+                    $kind,
+                    "path {} has {} components, maximum is {}",
+                    path.display(),
+                    count,
+                    max
+                );
+            }
+        }
+    };
+    ($path:expr, $max:expr, $kind:expr, $($arg:tt)+) => {
+        match (::std::convert::AsRef::<::std::path::Path>::as_ref(&$path), $max) {
+            (path, max) => {
+                $crate::ensure!(path.components().count() <= max, $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a file's Unix permission
+/// bits are not exactly `mode`.
+///
+/// The file's mode is read with [`MetadataExt::mode`] and masked to its
+/// permission bits (`0o7777`), so the file-type bits are ignored. If the
+/// metadata cannot be read that error is returned instead. When no message is
+/// given the error reports the expected and the actual mode in octal.
+///
+/// On platforms other than Unix the check is skipped.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`MetadataExt::mode`]: https://doc.rust-lang.org/std/os/unix/fs/trait.MetadataExt.html#tymethod.mode
+///
+/// # Examples
+///
+/// ```no_run
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// ensure_unix_mode!("id_ed25519", 0o600, ErrorKind::PermissionDenied);
+/// ensure_unix_mode!("id_ed25519", 0o600, ErrorKind::PermissionDenied, "private key file has wrong permissions");
+/// # Ok(()) }
+/// ```
+#[cfg(unix)]
+#[macro_export]
+macro_rules! ensure_unix_mode {
+    ($path:expr, $mode:expr, $kind:expr $(,)?) => {
+        match (
+            $crate::__private::unix_mode(::std::convert::AsRef::<::std::path::Path>::as_ref(&$path)),
+            $mode,
+        ) {
+            (::std::result::Result::Err(err), _) => return ::std::result::Result::Err(::std::convert::From::from(err)),
+            (::std::result::Result::Ok(actual), expected) => {
+                $crate::ensure!(
+                    actual == expected,
+                    $kind,
+                    "expected mode 0o{:o}, got 0o{:o}",
+                    expected,
+                    actual
+                );
+            }
+        }
+    };
+    ($path:expr, $mode:expr, $kind:expr, $($arg:tt)+) => {
+        match (
+            $crate::__private::unix_mode(::std::convert::AsRef::<::std::path::Path>::as_ref(&$path)),
+            $mode,
+        ) {
+            (::std::result::Result::Err(err), _) => return ::std::result::Result::Err(::std::convert::From::from(err)),
+            (::std::result::Result::Ok(actual), expected) => {
+                $crate::ensure!(actual == expected, $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a file's Unix permission
+/// bits are not exactly `mode`.
+///
+/// On platforms other than Unix the check is skipped.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+#[cfg(not(unix))]
+#[macro_export]
+macro_rules! ensure_unix_mode {
+    ($path:expr, $mode:expr, $($arg:tt)+) => {
+        let _ = (&$path, &$mode);
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if the current process cannot
+/// read a path.
+///
+/// Regular files are checked by opening them for reading and directories by
+/// listing them, so the check reflects the process's actual access rather
+/// than just the permission bits. Nothing is read and the handle is closed
+/// right away. Other files, such as FIFOs and devices, are never opened, as
+/// that could block or have side effects. Their owner, group or other read bit
+/// is checked against the process's effective user and groups instead, or,
+/// where those cannot be read from `/proc/self/status`, any read bit is
+/// accepted. The error from the attempt, such as "permission denied" or "not found", is
+/// attached as the source. When no message is given the error reports the
+/// path and that error.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let path = std::env::temp_dir();
+/// ensure_path_readable!(path, ErrorKind::PermissionDenied);
+/// ensure_path_readable!(path, ErrorKind::PermissionDenied, "cannot read {}", path.display());
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_path_readable {
+    ($path:expr, $($arg:tt)+) => {
+        $crate::__ensure_path_access!($crate::__private::path_readable, "cannot read", $path, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if the current process cannot
+/// write to a path.
+///
+/// Regular files are checked by opening them for writing, without creating or
+/// truncating them, so their contents are left untouched. This accounts for
+/// ownership, access control lists, and read-only mounts. Directories and
+/// other files, such as FIFOs and devices, are never opened or modified. As
+/// with [`ensure_path_readable!`], their owner, group or other write bit is
+/// checked against the process's effective user and groups instead, so
+/// access control lists and read-only mounts are not taken into account for
+/// them. The error from the check is attached as the source and, when no
+/// message is given, reported alongside the path.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_path_readable!`]: crate::ensure_path_readable
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let path = std::env::temp_dir();
+/// ensure_path_writable!(path, ErrorKind::PermissionDenied);
+/// ensure_path_writable!(path, ErrorKind::PermissionDenied, "cannot write to {}", path.display());
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_path_writable {
+    ($path:expr, $($arg:tt)+) => {
+        $crate::__ensure_path_access!($crate::__private::path_writable, "cannot write to", $path, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a path is not executable.
+///
+/// The check passes when the execute bit that applies to the current process
+/// is set: the owner's bit if the process's effective user owns the path, else
+/// the group's bit if it is a member of the path's group, else the bit for
+/// others. Root may execute a path with any execute bit set. Directories are
+/// rejected, as their execute bit means permission to search them, and so are
+/// paths on a filesystem that `/proc/self/mounts` lists as mounted `noexec`.
+/// Where the process's user and groups cannot be read from
+/// `/proc/self/status`, any execute bit is accepted. As with
+/// [`ensure_path_readable!`], a failure is attached as the source and, when no
+/// message is given, reported alongside the path.
+///
+/// Only available on Unix; using it on other platforms is a compile error,
+/// since they have no execute permission bits.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_path_readable!`]: crate::ensure_path_readable
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let path = Path::new("/bin/sh");
+/// ensure_path_executable!(path, ErrorKind::PermissionDenied);
+/// ensure_path_executable!(path, ErrorKind::PermissionDenied, "{} is not executable", path.display());
+/// # Ok(()) }
+/// ```
+#[cfg(unix)]
+#[macro_export]
+macro_rules! ensure_path_executable {
+    ($path:expr, $($arg:tt)+) => {
+        $crate::__ensure_path_access!($crate::__private::path_executable, "cannot execute", $path, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a path is not executable.
+///
+/// Only available on Unix.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+#[cfg(not(unix))]
+#[macro_export]
+macro_rules! ensure_path_executable {
+    ($($arg:tt)*) => {
+        compile_error!(
+            "`ensure_path_executable!` is only available on Unix, other platforms have no execute permission bits"
+        )
+    };
+}
+
+/// Runs a path access check, attaching its error as the source on failure.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_path_access {
+    ($check:path, $what:literal, $path:expr, $kind:expr $(,)?) => {
+        match ::std::convert::AsRef::<::std::path::Path>::as_ref(&$path) {
+            path => match $check(path) {
+                ::std::result::Result::Ok(()) => {}
+                ::std::result::Result::Err(source) => {
+                    let err = $crate::__private::with_source(
+                        $crate::format_err!($kind, "{} {}: {}", $what, path.display(), source),
+                        source,
+                    );
+                    $crate::__ensure_failed!($path, err);
+                    return ::std::result::Result::Err(::std::convert::From::from(err));
+                }
+            },
+        }
+    };
+    ($check:path, $what:literal, $path:expr, $kind:expr, $($arg:tt)+) => {
+        match ::std::convert::AsRef::<::std::path::Path>::as_ref(&$path) {
+            path => $crate::__ensure_unwrap!($path, $check(path), $kind, $($arg)+),
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a path is not absolute.
+///
+/// The check is [`Path::is_absolute`], which is purely lexical: a leading `~`
+/// is an ordinary relative component, since tilde expansion is done by shells
+/// and not by the file system. When no message is given the error reports the
+/// path.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`Path::is_absolute`]: https://doc.rust-lang.org/std/path/struct.Path.html#method.is_absolute
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let config_path = std::env::temp_dir().join("app.toml");
+/// ensure_path_absolute!(config_path, ErrorKind::InvalidInput);
+/// ensure_path_absolute!(config_path, ErrorKind::InvalidInput, "config path must be absolute: {}", config_path.display());
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_path_absolute {
+    ($path:expr, $kind:expr $(,)?) => {
+        match ::std::convert::AsRef::<::std::path::Path>::as_ref(&$path) {
+            path => $crate::ensure!(
+                path.is_absolute(),
+                $kind,
+                "path {} is not absolute",
+                path.display()
+            ),
+        }
+    };
+    ($path:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!(
+            ::std::convert::AsRef::<::std::path::Path>::as_ref(&$path).is_absolute(),
+            $kind,
+            $($arg)+
+        )
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a path is not relative.
+///
+/// The complement of [`ensure_path_absolute!`], using [`Path::is_relative`].
+/// A relative path may still contain `..` components; use
+/// [`ensure_path_no_traversal!`] to keep it inside a base directory. When no
+/// message is given the error reports the path.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_path_absolute!`]: crate::ensure_path_absolute
+/// [`Path::is_relative`]: https://doc.rust-lang.org/std/path/struct.Path.html#method.is_relative
+/// [`ensure_path_no_traversal!`]: crate::ensure_path_no_traversal
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let template_path = Path::new("emails/welcome.html");
+/// ensure_path_relative!(template_path, ErrorKind::InvalidInput);
+/// ensure_path_relative!(template_path, ErrorKind::InvalidInput, "template path must be relative: {}", template_path.display());
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_path_relative {
+    ($path:expr, $kind:expr $(,)?) => {
+        match ::std::convert::AsRef::<::std::path::Path>::as_ref(&$path) {
+            path => $crate::ensure!(
+                path.is_relative(),
+                $kind,
+                "path {} is not relative",
+                path.display()
+            ),
+        }
+    };
+    ($path:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!(
+            ::std::convert::AsRef::<::std::path::Path>::as_ref(&$path).is_relative(),
+            $kind,
+            $($arg)+
+        )
+    };
+}
+
+/// Resolves a path inside a base directory, exiting a function early with an
+/// [`io::Error`] if it escapes that directory.
+///
+/// The macro evaluates to the resolved path. The base directory is
+/// canonicalized, then each component of the path is applied to it, resolving
+/// symlinks with [`Path::canonicalize`]. This catches both `..` components and
+/// symlinks pointing outside the base, which comparing the raw joined path
+/// with [`Path::starts_with`] would miss. Components after the first one that
+/// doesn't exist are applied lexically, so paths of files that are yet to be
+/// created can be checked too. Absolute paths replace the base, as with
+/// [`Path::join`], and fail unless they point inside it.
+///
+/// If the base directory doesn't exist, or resolving fails for another reason,
+/// that error is attached as the source. When no message is given the error
+/// reports the path and the base directory.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`Path::canonicalize`]: https://doc.rust-lang.org/std/path/struct.Path.html#method.canonicalize
+/// [`Path::starts_with`]: https://doc.rust-lang.org/std/path/struct.Path.html#method.starts_with
+/// [`Path::join`]: https://doc.rust-lang.org/std/path/struct.Path.html#method.join
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let base_directory = std::env::temp_dir();
+/// let user_input_path = "uploads/avatar.png";
+/// let path = ensure_path_no_traversal!(user_input_path, base_directory, ErrorKind::PermissionDenied);
+/// ensure_path_no_traversal!(user_input_path, base_directory, ErrorKind::PermissionDenied, "path traversal detected");
+/// assert!(path.ends_with("uploads/avatar.png"));
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_path_no_traversal {
+    ($path:expr, $base:expr, $kind:expr $(,)?) => {
+        match (
+            ::std::convert::AsRef::<::std::path::Path>::as_ref(&$path),
+            ::std::convert::AsRef::<::std::path::Path>::as_ref(&$base),
+        ) {
+            (path, base) => {
+                let (base, resolved) = $crate::__ensure_unwrap!(
+                    $path,
+                    $crate::__private::resolve_within(base, path),
+                    $kind,
+                    "cannot resolve {} in {}",
+                    path.display(),
+                    base.display()
+                );
+                $crate::ensure!(
+                    resolved.starts_with(&base),
+                    $kind,
+                    "path {} escapes base directory {}",
+                    path.display(),
+                    base.display()
+                );
+                resolved
+            }
+        }
+    };
+    ($path:expr, $base:expr, $kind:expr, $($arg:tt)+) => {
+        match (
+            ::std::convert::AsRef::<::std::path::Path>::as_ref(&$path),
+            ::std::convert::AsRef::<::std::path::Path>::as_ref(&$base),
+        ) {
+            (path, base) => {
+                let (base, resolved) = $crate::__ensure_unwrap!(
+                    $path,
+                    $crate::__private::resolve_within(base, path),
+                    $kind,
+                    $($arg)+
+                );
+                $crate::ensure!(resolved.starts_with(&base), $kind, $($arg)+);
+                resolved
+            }
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a symlink does not point
+/// to the expected target.
+///
+/// The link is read with [`std::fs::read_link`], without following it further,
+/// and its target is compared with `expected` component by component. Trailing
+/// slashes and `.` components are ignored, but no other normalization is done,
+/// so a relative target only matches the same relative path. If the link
+/// cannot be read, for example because it is not a symlink, that error is
+/// attached as the source. When no message is given the error reports the
+/// link, its target, and the expected target.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`std::fs::read_link`]: https://doc.rust-lang.org/std/fs/fn.read_link.html
+///
+/// # Examples
+///
+/// ```no_run
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+/// use std::path::Path;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let link_path = Path::new("/etc/nginx/sites-enabled/default");
+/// let expected_target = "/etc/nginx/sites-available/default";
+/// ensure_symlink_target!(link_path, expected_target, ErrorKind::InvalidInput);
+/// ensure_symlink_target!(link_path, expected_target, ErrorKind::InvalidInput, "symlink {} points to wrong target", link_path.display());
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_symlink_target {
+    ($link:expr, $expected:expr, $kind:expr $(,)?) => {
+        match (
+            ::std::convert::AsRef::<::std::path::Path>::as_ref(&$link),
+            ::std::convert::AsRef::<::std::path::Path>::as_ref(&$expected),
+        ) {
+            (link, expected) => {
+                let target = $crate::__ensure_unwrap!(
+                    $link,
+                    ::std::fs::read_link(link),
+                    $kind,
+                    "cannot read symlink {}",
+                    link.display()
+                );
+                $crate::ensure!(
+                    $crate::__private::same_lexical_path(&target, expected),
+                    $kind,
+                    "symlink {} points to {}, expected {}",
+                    link.display(),
+                    target.display(),
+                    expected.display()
+                );
+            }
+        }
+    };
+    ($link:expr, $expected:expr, $kind:expr, $($arg:tt)+) => {
+        match (
+            ::std::convert::AsRef::<::std::path::Path>::as_ref(&$link),
+            ::std::convert::AsRef::<::std::path::Path>::as_ref(&$expected),
+        ) {
+            (link, expected) => {
+                let target = $crate::__ensure_unwrap!($link, ::std::fs::read_link(link), $kind, $($arg)+);
+                $crate::ensure!($crate::__private::same_lexical_path(&target, expected), $kind, $($arg)+);
+            }
+        }
+    };
+}
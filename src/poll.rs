@@ -0,0 +1,162 @@
+//! Macros for poll functions.
+
+/// Returns `Poll::Ready(Err(..))` with an [`io::Error`] if the condition is
+/// not satisfied.
+///
+/// This is [`ensure!`] for poll functions, such as hand-written `AsyncRead`
+/// and `AsyncWrite` implementations, which return `Poll<io::Result<T>>` rather
+/// than `io::Result<T>`. It takes the same arguments as `ensure!`, and the
+/// error is converted with `From` in the same way.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure!`]: crate::ensure
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+/// use std::task::Poll;
+///
+/// fn poll_write(closed: bool, buf: &[u8]) -> Poll<io::Result<usize>> {
+///     poll_ensure!(!closed, ErrorKind::BrokenPipe);
+///     poll_ensure!(buf.len() <= 16, ErrorKind::InvalidInput, "frame of {} bytes is too large", buf.len());
+///     Poll::Ready(Ok(buf.len()))
+/// }
+///
+/// assert!(matches!(poll_write(false, b"hello"), Poll::Ready(Ok(5))));
+/// assert!(matches!(poll_write(true, b"hello"), Poll::Ready(Err(_))));
+/// ```
+#[macro_export]
+macro_rules! poll_ensure {
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            let err = $crate::format_err!($($arg)+);
+            $crate::__ensure_failed!($cond, err);
+            return ::std::task::Poll::Ready(::std::result::Result::Err(
+                ::std::convert::From::from(err),
+            ));
+        }
+    };
+}
+
+/// Extracts the value of a `Poll<io::Result<T>>`, returning early if it is
+/// pending or failed.
+///
+/// `Poll::Ready(Ok(value))` evaluates to `value`. `Poll::Pending` is returned
+/// as is, and `Poll::Ready(Err(err))` is returned with the error converted
+/// using `From`. This combines the `ready!` macro of `std::task` with the `?`
+/// operator, for poll functions that call other poll functions.
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+/// use std::task::Poll;
+///
+/// fn poll_header(inner: Poll<io::Result<usize>>) -> Poll<io::Result<usize>> {
+///     let n = ensure_ready!(inner);
+///     poll_ensure!(n >= 4, ErrorKind::UnexpectedEof, "short header");
+///     Poll::Ready(Ok(n))
+/// }
+///
+/// assert!(poll_header(Poll::Pending).is_pending());
+/// assert!(matches!(poll_header(Poll::Ready(Ok(8))), Poll::Ready(Ok(8))));
+/// assert!(matches!(poll_header(Poll::Ready(Ok(2))), Poll::Ready(Err(_))));
+/// ```
+#[macro_export]
+macro_rules! ensure_ready {
+    ($poll:expr $(,)?) => {
+        match $poll {
+            ::std::task::Poll::Ready(::std::result::Result::Ok(value)) => value,
+            ::std::task::Poll::Ready(::std::result::Result::Err(err)) => {
+                return ::std::task::Poll::Ready(::std::result::Result::Err(
+                    ::std::convert::From::from(err),
+                ));
+            }
+            ::std::task::Poll::Pending => return ::std::task::Poll::Pending,
+        }
+    };
+}
+
+/// Returns `Poll::Ready(Some(Err(..)))` with an [`io::Error`] if the condition
+/// is not satisfied.
+///
+/// Like [`poll_ensure!`], but for `poll_next` functions of streams, which
+/// return `Poll<Option<io::Result<T>>>`. It takes the same arguments as
+/// [`ensure!`].
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`poll_ensure!`]: crate::poll_ensure
+/// [`ensure!`]: crate::ensure
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+/// use std::task::Poll;
+///
+/// fn poll_next(frames: &mut Vec<Vec<u8>>) -> Poll<Option<io::Result<Vec<u8>>>> {
+///     let frame = match frames.pop() {
+///         Some(frame) => frame,
+///         None => return Poll::Ready(None),
+///     };
+///     ensure_next!(!frame.is_empty(), ErrorKind::InvalidData, "empty frame");
+///     Poll::Ready(Some(Ok(frame)))
+/// }
+///
+/// let mut frames = vec![vec![], vec![1, 2]];
+/// assert!(matches!(poll_next(&mut frames), Poll::Ready(Some(Ok(_)))));
+/// assert!(matches!(poll_next(&mut frames), Poll::Ready(Some(Err(_)))));
+/// assert!(matches!(poll_next(&mut frames), Poll::Ready(None)));
+/// ```
+#[macro_export]
+macro_rules! ensure_next {
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            let err = $crate::format_err!($($arg)+);
+            $crate::__ensure_failed!($cond, err);
+            return ::std::task::Poll::Ready(::std::option::Option::Some(
+                ::std::result::Result::Err(::std::convert::From::from(err)),
+            ));
+        }
+    };
+}
+
+/// Returns `Poll::Ready(Some(Err(..)))` with an [`io::Error`].
+///
+/// Takes the same arguments as [`format_err!`]. This is an unconditional
+/// [`ensure_next!`], like [`bail!`] is for `ensure!`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`format_err!`]: crate::format_err
+/// [`ensure_next!`]: crate::ensure_next
+/// [`bail!`]: crate::bail
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{self, ErrorKind};
+/// use std::task::Poll;
+///
+/// fn poll_next(tag: u8) -> Poll<Option<io::Result<&'static str>>> {
+///     match tag {
+///         0 => Poll::Ready(None),
+///         1 => Poll::Ready(Some(Ok("data"))),
+///         tag => bail_next!(ErrorKind::InvalidData, "unknown tag {}", tag),
+///     }
+/// }
+///
+/// assert!(matches!(poll_next(7), Poll::Ready(Some(Err(_)))));
+/// ```
+#[macro_export]
+macro_rules! bail_next {
+    ($($arg:tt)+) => {
+        return ::std::task::Poll::Ready(::std::option::Option::Some(::std::result::Result::Err(
+            ::std::convert::From::from($crate::format_err!($($arg)+)),
+        )))
+    };
+}
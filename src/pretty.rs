@@ -0,0 +1,83 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::ops::Deref;
+
+/// An [`io::Error`] wrapper with a multi-line [`Display`] implementation.
+///
+/// The error kind is printed on the first line, the message on the second
+/// line, and each error in the [`source`] chain is listed below it.
+///
+/// This is created by [`PrettyIoError::new`] or [`IoErrorExt::pretty`].
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`Display`]: https://doc.rust-lang.org/std/fmt/trait.Display.html
+/// [`source`]: https://doc.rust-lang.org/std/error/trait.Error.html#method.source
+/// [`IoErrorExt::pretty`]: crate::IoErrorExt::pretty
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// let err = format_err!(ErrorKind::NotFound, "config file missing").pretty();
+/// assert_eq!(err.to_string(), "kind: NotFound\nmessage: config file missing");
+/// ```
+#[derive(Debug)]
+pub struct PrettyIoError(io::Error);
+
+impl PrettyIoError {
+    /// Wraps an `io::Error`.
+    pub fn new(error: io::Error) -> Self {
+        Self(error)
+    }
+
+    /// Returns the wrapped `io::Error`.
+    pub fn into_inner(self) -> io::Error {
+        self.0
+    }
+}
+
+impl fmt::Display for PrettyIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "kind: {:?}\nmessage: {}", self.0.kind(), self.0)?;
+        let mut source = self.0.source();
+        if source.is_some() {
+            write!(f, "\ncaused by:")?;
+        }
+        let mut index = 0;
+        while let Some(err) = source {
+            write!(f, "\n    {}: {}", index, err)?;
+            source = err.source();
+            index += 1;
+        }
+        Ok(())
+    }
+}
+
+impl Error for PrettyIoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl Deref for PrettyIoError {
+    type Target = io::Error;
+
+    fn deref(&self) -> &io::Error {
+        &self.0
+    }
+}
+
+impl From<io::Error> for PrettyIoError {
+    fn from(error: io::Error) -> Self {
+        Self(error)
+    }
+}
+
+impl From<PrettyIoError> for io::Error {
+    fn from(error: PrettyIoError) -> Self {
+        error.0
+    }
+}
@@ -0,0 +1,1124 @@
+//! Implementation details of the macros. Not public API.
+
+use std::error::Error;
+use std::fmt;
+
+pub use crate::hook::ensure_failed;
+
+const CAPACITY: usize = 256;
+const ELLIPSIS: &str = "...";
+
+/// A fixed-size message buffer used by `format_err_stack!`.
+pub struct StackMessage {
+    buf: [u8; CAPACITY],
+    len: usize,
+    truncated: bool,
+}
+
+impl Default for StackMessage {
+    fn default() -> Self {
+        Self {
+            buf: [0; CAPACITY],
+            len: 0,
+            truncated: false,
+        }
+    }
+}
+
+impl StackMessage {
+    fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.buf[..self.len]).expect("message is valid UTF-8")
+    }
+
+    fn push(&mut self, s: &str) {
+        self.buf[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+        self.len += s.len();
+    }
+}
+
+impl fmt::Write for StackMessage {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.truncated {
+            return Ok(());
+        }
+        if s.len() <= CAPACITY - self.len {
+            self.push(s);
+            return Ok(());
+        }
+
+        // Cut the message at a char boundary so the ellipsis fits.
+        let limit = CAPACITY - ELLIPSIS.len();
+        let written = self.as_str();
+        let mut len = self.len.min(limit);
+        while !written.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.len = len;
+        let mut end = (limit - self.len).min(s.len());
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.push(&s[..end]);
+        self.push(ELLIPSIS);
+        self.truncated = true;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for StackMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for StackMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Error for StackMessage {}
+
+#[cfg(feature = "tracing-warn")]
+pub use tracing;
+
+/// Builds the error for a failed check and returns it from the function.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_bail {
+    ($cond:expr, $($arg:tt)+) => {{
+        let err = $crate::format_err!($($arg)+);
+        $crate::__ensure_failed!($cond, err);
+        return ::std::result::Result::Err(::std::convert::From::from(err));
+    }};
+}
+
+/// Unwraps a result, or returns an error with the original error attached as
+/// its source.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_unwrap {
+    ($input:expr, $result:expr, $($arg:tt)+) => {
+        match $result {
+            ::std::result::Result::Ok(value) => value,
+            ::std::result::Result::Err(source) => {
+                let err = $crate::__private::with_source($crate::format_err!($($arg)+), source);
+                $crate::__ensure_failed!($input, err);
+                return ::std::result::Result::Err(::std::convert::From::from(err));
+            }
+        }
+    };
+}
+
+/// Called by `ensure!` on the failure path, before returning the error.
+#[cfg(feature = "tracing-warn")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_failed {
+    ($cond:expr, $err:expr) => {
+        $crate::__private::tracing::warn!(
+            condition = %stringify!($cond),
+            kind = ?$err.kind(),
+            "{}", $err
+        );
+        $crate::__private::ensure_failed(&$err)
+    };
+}
+
+/// Called by `ensure!` on the failure path, before returning the error.
+#[cfg(not(feature = "tracing-warn"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_failed {
+    ($cond:expr, $err:expr) => {
+        $crate::__private::ensure_failed(&$err)
+    };
+}
+
+/// Compares two byte slices without exiting early on the first difference.
+#[cfg(feature = "subtle")]
+pub fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    subtle::ConstantTimeEq::ct_eq(left, right).into()
+}
+
+/// Compares two byte slices without exiting early on the first difference.
+#[cfg(not(feature = "subtle"))]
+pub fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+    let diff = left
+        .iter()
+        .zip(right)
+        .fold(0u8, |acc, (l, r)| acc | std::hint::black_box(l ^ r));
+    diff == 0
+}
+
+/// The values a `bail_with!` closure may return.
+pub trait BailWith {
+    fn into_bail(self) -> std::io::Error;
+}
+
+impl<E: crate::IntoIoError> BailWith for E {
+    fn into_bail(self) -> std::io::Error {
+        self.into_io_error()
+    }
+}
+
+impl<E: crate::IntoIoError> BailWith for std::io::Result<E> {
+    fn into_bail(self) -> std::io::Error {
+        match self {
+            Ok(err) => err.into_io_error(),
+            Err(err) => err,
+        }
+    }
+}
+
+/// The closures `ensure_then!` accepts, with or without the error argument.
+pub trait EnsureThen<Args> {
+    fn call(self, err: &mut std::io::Error);
+}
+
+impl<F: FnOnce()> EnsureThen<()> for F {
+    fn call(self, _: &mut std::io::Error) {
+        self()
+    }
+}
+
+impl<F: FnOnce(&mut std::io::Error)> EnsureThen<(&mut std::io::Error,)> for F {
+    fn call(self, err: &mut std::io::Error) {
+        self(err)
+    }
+}
+
+/// Rebuilds an `io::Error` with a different kind, keeping its message. An
+/// `IoEnsureError` payload is given the new kind too.
+pub fn with_kind(err: std::io::Error, kind: std::io::ErrorKind) -> std::io::Error {
+    if err.raw_os_error().is_some() {
+        return std::io::Error::new(kind, err.to_string());
+    }
+    match err.into_inner() {
+        Some(mut payload) => {
+            if let Some(payload) = payload.downcast_mut::<crate::IoEnsureError>() {
+                payload.set_kind(kind);
+            }
+            std::io::Error::new(kind, payload)
+        }
+        None => std::io::Error::from(kind),
+    }
+}
+
+/// The types `categorize_io_err!` accepts.
+pub trait Categorize {
+    fn io_category(&self) -> crate::IoErrorCategory;
+}
+
+impl Categorize for std::io::Error {
+    fn io_category(&self) -> crate::IoErrorCategory {
+        self.kind().into()
+    }
+}
+
+impl Categorize for std::io::ErrorKind {
+    fn io_category(&self) -> crate::IoErrorCategory {
+        (*self).into()
+    }
+}
+
+impl<T: Categorize + ?Sized> Categorize for &T {
+    fn io_category(&self) -> crate::IoErrorCategory {
+        (**self).io_category()
+    }
+}
+
+#[cfg(feature = "regex")]
+pub use regex;
+
+#[cfg(feature = "glob")]
+pub use glob;
+
+#[cfg(feature = "log")]
+pub use log;
+
+#[cfg(feature = "semver")]
+pub use semver;
+
+/// Returns whether `req` is a valid `semver::VersionReq`, so that
+/// `ensure_version_compat!` can reject an invalid requirement at compile time.
+///
+/// This follows the grammar accepted by `VersionReq::parse` in `semver` 1.
+#[cfg(feature = "semver")]
+pub const fn is_valid_version_req(req: &str) -> bool {
+    const MAX_COMPARATORS: usize = 32;
+
+    let bytes = req.as_bytes();
+    let mut i = skip_spaces(bytes, 0);
+    if is_wildcard(bytes, i) {
+        return skip_spaces(bytes, i + 1) == bytes.len();
+    }
+    let mut count = 1;
+    loop {
+        i = match version_comparator(bytes, i) {
+            Some(end) => end,
+            None => return false,
+        };
+        if i == bytes.len() {
+            return true;
+        }
+        if bytes[i] != b',' || count == MAX_COMPARATORS {
+            return false;
+        }
+        i = skip_spaces(bytes, i + 1);
+        count += 1;
+    }
+}
+
+/// Parses one comparator of a version requirement starting at `i`, returning
+/// the index after it and any trailing spaces.
+#[cfg(feature = "semver")]
+const fn version_comparator(bytes: &[u8], mut i: usize) -> Option<usize> {
+    if i < bytes.len() {
+        match bytes[i] {
+            b'=' | b'~' | b'^' => i += 1,
+            b'>' | b'<' => {
+                i += 1;
+                if i < bytes.len() && bytes[i] == b'=' {
+                    i += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    i = skip_spaces(bytes, i);
+    i = match version_number(bytes, i) {
+        Some(end) => end,
+        None => return None,
+    };
+    let mut wildcard = false;
+    let mut patch = false;
+    if i < bytes.len() && bytes[i] == b'.' {
+        i += 1;
+        if is_wildcard(bytes, i) {
+            wildcard = true;
+            i += 1;
+        } else {
+            i = match version_number(bytes, i) {
+                Some(end) => end,
+                None => return None,
+            };
+        }
+    }
+    if i < bytes.len() && bytes[i] == b'.' {
+        i += 1;
+        if is_wildcard(bytes, i) {
+            i += 1;
+        } else if wildcard {
+            return None;
+        } else {
+            i = match version_number(bytes, i) {
+                Some(end) => end,
+                None => return None,
+            };
+            patch = true;
+        }
+    }
+    if patch && i < bytes.len() && bytes[i] == b'-' {
+        i = match version_identifier(bytes, i + 1, true) {
+            Some(end) => end,
+            None => return None,
+        };
+    }
+    if patch && i < bytes.len() && bytes[i] == b'+' {
+        i = match version_identifier(bytes, i + 1, false) {
+            Some(end) => end,
+            None => return None,
+        };
+    }
+    Some(skip_spaces(bytes, i))
+}
+
+/// Parses a number without leading zeros that fits in a `u64`.
+#[cfg(feature = "semver")]
+const fn version_number(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start;
+    let mut value: u64 = 0;
+    while i < bytes.len() && bytes[i].is_ascii_digit() {
+        if value == 0 && i > start {
+            return None;
+        }
+        value = match value.checked_mul(10) {
+            Some(value) => match value.checked_add((bytes[i] - b'0') as u64) {
+                Some(value) => value,
+                None => return None,
+            },
+            None => return None,
+        };
+        i += 1;
+    }
+    if i == start {
+        None
+    } else {
+        Some(i)
+    }
+}
+
+/// Parses a non-empty, dot-separated pre-release or build identifier.
+#[cfg(feature = "semver")]
+const fn version_identifier(bytes: &[u8], mut i: usize, pre: bool) -> Option<usize> {
+    loop {
+        let start = i;
+        let mut numeric = true;
+        while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'-') {
+            numeric &= bytes[i].is_ascii_digit();
+            i += 1;
+        }
+        if i == start || (pre && numeric && i - start > 1 && bytes[start] == b'0') {
+            return None;
+        }
+        if i < bytes.len() && bytes[i] == b'.' {
+            i += 1;
+        } else {
+            return Some(i);
+        }
+    }
+}
+
+#[cfg(feature = "semver")]
+const fn is_wildcard(bytes: &[u8], i: usize) -> bool {
+    i < bytes.len() && matches!(bytes[i], b'*' | b'x' | b'X')
+}
+
+#[cfg(feature = "semver")]
+const fn skip_spaces(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && bytes[i] == b' ' {
+        i += 1;
+    }
+    i
+}
+
+#[cfg(feature = "serde_json")]
+pub use serde_json;
+
+#[cfg(feature = "url")]
+pub use url;
+
+#[cfg(feature = "uuid")]
+pub use uuid;
+
+/// Reports an error as a warning for `io_warn!` and `ensure_or_warn!`.
+#[cfg(feature = "tracing")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __io_warn {
+    ($err:expr) => {
+        $crate::__private::tracing::warn!(kind = ?$err.kind(), "{}", $err)
+    };
+    ($err:expr, $cond:expr) => {
+        $crate::__private::tracing::warn!(
+            condition = %stringify!($cond),
+            kind = ?$err.kind(),
+            "{}", $err
+        )
+    };
+}
+
+/// Reports an error as a warning for `io_warn!` and `ensure_or_warn!`.
+#[cfg(all(not(feature = "tracing"), feature = "log"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __io_warn {
+    ($err:expr $(, $cond:expr)?) => {
+        $crate::__private::log::warn!("{}", $err)
+    };
+}
+
+/// Reports an error as a warning for `io_warn!` and `ensure_or_warn!`.
+#[cfg(all(
+    not(feature = "tracing"),
+    not(feature = "log"),
+    feature = "warn-stderr"
+))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __io_warn {
+    ($err:expr $(, $cond:expr)?) => {
+        eprintln!("warning: {}", $err)
+    };
+}
+
+/// Reports an error as a warning for `io_warn!` and `ensure_or_warn!`.
+#[cfg(not(any(feature = "tracing", feature = "log", feature = "warn-stderr")))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __io_warn {
+    ($err:expr $(, $cond:expr)?) => {
+        let _ = &$err;
+    };
+}
+
+/// Turns the failures collected by `batch_ensure!` into its result.
+pub fn batch_result(errors: Vec<std::io::Error>) -> std::io::Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(crate::MultiError::new(errors).into())
+}
+
+/// The first adjacent pair of elements found out of order.
+#[derive(Debug)]
+pub struct OutOfOrder<T> {
+    index: usize,
+    prev: T,
+    next: T,
+}
+
+impl<T: fmt::Debug> fmt::Display for OutOfOrder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "elements {} and {} are out of order: {:?}, {:?}",
+            self.index - 1,
+            self.index,
+            self.prev,
+            self.next
+        )
+    }
+}
+
+/// Checks that `in_order(prev, next)` holds for every adjacent pair.
+pub fn check_order<I, F>(iter: I, in_order: F) -> Result<(), OutOfOrder<I::Item>>
+where
+    I: IntoIterator,
+    F: Fn(&I::Item, &I::Item) -> bool,
+{
+    let mut iter = iter.into_iter();
+    let mut prev = match iter.next() {
+        Some(first) => first,
+        None => return Ok(()),
+    };
+    for (index, next) in iter.enumerate() {
+        if !in_order(&prev, &next) {
+            let index = index + 1;
+            return Err(OutOfOrder { index, prev, next });
+        }
+        prev = next;
+    }
+    Ok(())
+}
+
+/// Checks `predicate` against every window of `size` adjacent elements,
+/// returning the start index of the first window that fails. `ensure_window!`
+/// rejects a size of zero before calling this.
+pub fn check_windows<I, F>(iter: I, size: usize, mut predicate: F) -> Result<(), usize>
+where
+    I: IntoIterator,
+    F: FnMut(&[I::Item]) -> bool,
+{
+    if size == 0 {
+        return Ok(());
+    }
+    let mut window = std::collections::VecDeque::with_capacity(size);
+    for (index, item) in iter.into_iter().enumerate() {
+        if window.len() == size {
+            window.pop_front();
+        }
+        window.push_back(item);
+        if window.len() == size && !predicate(window.make_contiguous()) {
+            return Err(index + 1 - size);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the permission bits of a file's mode.
+#[cfg(unix)]
+pub fn unix_mode(path: &std::path::Path) -> std::io::Result<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(std::fs::metadata(path)?.mode() & 0o7777)
+}
+
+/// Displays bytes as space-separated lowercase hex pairs.
+#[derive(Debug)]
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, byte) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds the `io::Error` returned by `format_err!`, recording the location of
+/// the macro call.
+///
+/// This and the other constructors are only called on the failure path, so
+/// they are kept out of line to leave the checks themselves small.
+#[cold]
+#[inline(never)]
+#[track_caller]
+pub fn new_error(
+    kind: std::io::ErrorKind,
+    message: impl Into<std::borrow::Cow<'static, str>>,
+) -> std::io::Error {
+    crate::IoEnsureError::new(kind, message).into()
+}
+
+/// Builds the `io::Error` returned by `format_err!` from format arguments.
+#[cold]
+#[inline(never)]
+#[track_caller]
+pub fn new_error_fmt(kind: std::io::ErrorKind, args: fmt::Arguments<'_>) -> std::io::Error {
+    match args.as_str() {
+        Some(message) => new_error(kind, message),
+        None => new_error(kind, args.to_string()),
+    }
+}
+
+/// Builds the `io::Error` returned by `format_err!` with a field block.
+#[cold]
+#[inline(never)]
+#[track_caller]
+pub fn new_error_with_fields(
+    kind: std::io::ErrorKind,
+    args: fmt::Arguments<'_>,
+    fields: crate::Fields,
+) -> std::io::Error {
+    crate::IoEnsureError::new(kind, args.to_string())
+        .with_fields(fields)
+        .into()
+}
+
+/// Rebuilds an `io::Error` with `source` attached, keeping its kind and message.
+///
+/// An `IoEnsureError` payload is kept, along with its location and fields.
+/// Any other error is replaced by a new payload holding its message.
+#[track_caller]
+pub fn with_source(
+    err: std::io::Error,
+    source: impl Into<Box<dyn Error + Send + Sync>>,
+) -> std::io::Error {
+    let kind = err.kind();
+    let message = err.to_string();
+    let payload = match err
+        .into_inner()
+        .map(|inner| inner.downcast::<crate::IoEnsureError>())
+    {
+        Some(Ok(payload)) => *payload,
+        _ => crate::IoEnsureError::new(kind, message),
+    };
+    std::io::Error::new(kind, payload.with_source(source))
+}
+
+/// Picks how `format_err!(kind, payload)` builds its error from the type of
+/// the payload.
+///
+/// The macro calls `(&&&Payload(&payload)).payload_kind()`, so method
+/// resolution tries the traits below in order, each one level of auto-deref
+/// further down: strings become the message of an `IoEnsureError`, errors are
+/// kept as the payload of the `io::Error`, and other `IntoIoError` types are
+/// converted and attached as the source of an `IoEnsureError`. Matching on `Payload` rather
+/// than the payload itself keeps a reference to an error, which is an error
+/// too, from being picked at the wrong level.
+pub mod payload {
+    use std::borrow::Cow;
+    use std::error::Error;
+    use std::io;
+
+    #[derive(Debug)]
+    pub struct Payload<'a, T>(pub &'a T);
+
+    /// The string types used as a message.
+    pub trait Message {
+        fn into_message(self) -> Cow<'static, str>;
+    }
+
+    impl Message for &str {
+        fn into_message(self) -> Cow<'static, str> {
+            Cow::Owned(self.to_owned())
+        }
+    }
+
+    impl Message for &String {
+        fn into_message(self) -> Cow<'static, str> {
+            Cow::Owned(self.clone())
+        }
+    }
+
+    impl Message for String {
+        fn into_message(self) -> Cow<'static, str> {
+            Cow::Owned(self)
+        }
+    }
+
+    impl Message for Box<str> {
+        fn into_message(self) -> Cow<'static, str> {
+            Cow::Owned(self.into())
+        }
+    }
+
+    impl Message for Cow<'_, str> {
+        fn into_message(self) -> Cow<'static, str> {
+            Cow::Owned(self.into_owned())
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct MessageTag;
+
+    impl MessageTag {
+        #[track_caller]
+        pub fn new_error(self, kind: io::ErrorKind, message: impl Message) -> io::Error {
+            super::new_error(kind, message.into_message())
+        }
+    }
+
+    pub trait MessageKind {
+        fn payload_kind(&self) -> MessageTag {
+            MessageTag
+        }
+    }
+
+    impl<M: Message> MessageKind for &&Payload<'_, M> {}
+
+    #[derive(Debug)]
+    pub struct WrapTag;
+
+    impl WrapTag {
+        pub fn new_error(
+            self,
+            kind: io::ErrorKind,
+            error: impl Into<Box<dyn Error + Send + Sync>>,
+        ) -> io::Error {
+            io::Error::new(kind, error)
+        }
+    }
+
+    pub trait WrapKind {
+        fn payload_kind(&self) -> WrapTag {
+            WrapTag
+        }
+    }
+
+    impl<E: Into<Box<dyn Error + Send + Sync>>> WrapKind for &Payload<'_, E> {}
+
+    #[derive(Debug)]
+    pub struct ConvertTag;
+
+    impl ConvertTag {
+        #[track_caller]
+        pub fn new_error(self, kind: io::ErrorKind, value: impl crate::IntoIoError) -> io::Error {
+            let err = value.into_io_error();
+            super::with_source(super::new_error(kind, err.to_string()), err)
+        }
+    }
+
+    pub trait ConvertKind {
+        fn payload_kind(&self) -> ConvertTag {
+            ConvertTag
+        }
+    }
+
+    impl<T: crate::IntoIoError> ConvertKind for Payload<'_, T> {}
+}
+
+/// Builds the error of a check whose message is only known to convert into a
+/// boxed error, as in `IoEnsureChain::add`.
+///
+/// Strings become the message of an `IoEnsureError`; other errors are kept as
+/// the payload, as `format_err!(kind, payload)` does.
+#[cold]
+#[inline(never)]
+#[track_caller]
+pub fn new_payload_error<M>(kind: std::io::ErrorKind, payload: M) -> std::io::Error
+where
+    M: Into<Box<dyn Error + Send + Sync>> + 'static,
+{
+    use std::any::Any;
+    use std::borrow::Cow;
+
+    let mut payload = Some(payload);
+    let any = &mut payload as &mut dyn Any;
+    if let Some(message) = any.downcast_ref::<Option<&'static str>>() {
+        return new_error(kind, message.unwrap());
+    }
+    if let Some(message) = any.downcast_mut::<Option<String>>() {
+        return new_error(kind, message.take().unwrap());
+    }
+    if let Some(message) = any.downcast_mut::<Option<Cow<'static, str>>>() {
+        return new_error(kind, message.take().unwrap());
+    }
+    std::io::Error::new(kind, payload.unwrap())
+}
+
+/// Returns `true` if a divisor is zero, for the divisibility checks.
+pub fn is_zero<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// Sets the location reported by an error built by this crate, if it has one.
+pub fn set_location(err: &mut std::io::Error, location: &'static std::panic::Location<'static>) {
+    if let Some(payload) = err
+        .get_mut()
+        .and_then(|payload| payload.downcast_mut::<crate::IoEnsureError>())
+    {
+        payload.set_location(location);
+    }
+}
+
+/// The tables of allowed transitions `ensure_state_transition!` accepts.
+pub trait TransitionTable<S> {
+    fn allows_transition(&self, from: &S, to: &S) -> bool;
+}
+
+impl<S: PartialEq> TransitionTable<S> for [(S, S)] {
+    fn allows_transition(&self, from: &S, to: &S) -> bool {
+        self.iter().any(|(f, t)| f == from && t == to)
+    }
+}
+
+impl<S: PartialEq, const N: usize> TransitionTable<S> for [(S, S); N] {
+    fn allows_transition(&self, from: &S, to: &S) -> bool {
+        self[..].allows_transition(from, to)
+    }
+}
+
+impl<S: PartialEq> TransitionTable<S> for Vec<(S, S)> {
+    fn allows_transition(&self, from: &S, to: &S) -> bool {
+        self[..].allows_transition(from, to)
+    }
+}
+
+impl<S, H> TransitionTable<S> for std::collections::HashSet<(S, S), H>
+where
+    S: std::hash::Hash + Eq + Clone,
+    H: std::hash::BuildHasher,
+{
+    fn allows_transition(&self, from: &S, to: &S) -> bool {
+        self.contains(&(from.clone(), to.clone()))
+    }
+}
+
+impl<S, F: Fn((&S, &S)) -> bool> TransitionTable<S> for F {
+    fn allows_transition(&self, from: &S, to: &S) -> bool {
+        self((from, to))
+    }
+}
+
+/// Calls a closure literal passed to `ensure_state_transition!`, so its
+/// argument types are inferred.
+pub fn transition_allowed_by<S, F: Fn((&S, &S)) -> bool>(allowed: F, from: &S, to: &S) -> bool {
+    allowed((from, to))
+}
+
+/// Computes the CRC-32/ISO-HDLC checksum used by `ensure_crc32!`.
+#[cfg(feature = "crc")]
+pub fn crc32(data: &[u8]) -> u32 {
+    const CRC32: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+    CRC32.checksum(data)
+}
+
+/// Returns `true` if `port` fits in a `u16` and is at least `min`.
+pub fn port_at_least<T: std::convert::TryInto<u16>>(port: T, min: u16) -> bool {
+    port.try_into().is_ok_and(|port| port >= min)
+}
+
+/// Checks that a path can be opened for reading: regular files are opened and
+/// directories are listed. Other files, such as FIFOs and devices, are only
+/// checked against their permission bits, as opening them can block or have
+/// side effects.
+pub fn path_readable(path: &std::path::Path) -> std::io::Result<()> {
+    let metadata = std::fs::metadata(path)?;
+    if metadata.is_dir() {
+        std::fs::read_dir(path)?;
+    } else if metadata.is_file() {
+        std::fs::File::open(path)?;
+    } else {
+        check_mode(&metadata, 0o4)?;
+    }
+    Ok(())
+}
+
+/// The effective user and groups of the current process, used to check
+/// permission bits.
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct Credentials {
+    pub uid: u32,
+    pub gid: u32,
+    pub groups: Vec<u32>,
+}
+
+#[cfg(unix)]
+impl Credentials {
+    /// Reads the credentials of the current process from `/proc/self/status`,
+    /// returning `None` where it is not available.
+    pub fn current() -> Option<Self> {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let field = |name: &str| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .map(str::split_whitespace)
+        };
+        // The second id is the effective one.
+        Some(Self {
+            uid: field("Uid:")?.nth(1)?.parse().ok()?,
+            gid: field("Gid:")?.nth(1)?.parse().ok()?,
+            groups: field("Groups:")
+                .map(|ids| ids.filter_map(|id| id.parse().ok()).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Returns `true` if `mode` grants `bit` (`0o4` to read, `0o2` to write,
+    /// `0o1` to execute) to these credentials, for a file with the given owner
+    /// and group.
+    ///
+    /// Only the owner's bits apply to the owner and only the group's bits to
+    /// members of the group, as with `access`. Root may read and write
+    /// anything, and execute anything with an execute bit set.
+    pub fn permits(&self, mode: u32, owner: u32, group: u32, bit: u32) -> bool {
+        if self.uid == 0 {
+            return bit != 0o1 || mode & 0o111 != 0;
+        }
+        let shift = if owner == self.uid {
+            6
+        } else if group == self.gid || self.groups.contains(&group) {
+            3
+        } else {
+            0
+        };
+        (mode >> shift) & bit != 0
+    }
+}
+
+/// Checks a path's permission bits for the current process, without opening
+/// it. Where the process's credentials cannot be read, any of the owner, group
+/// or other bits is accepted.
+#[cfg(unix)]
+fn check_mode(metadata: &std::fs::Metadata, bit: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let permitted = match Credentials::current() {
+        Some(credentials) => {
+            credentials.permits(metadata.mode(), metadata.uid(), metadata.gid(), bit)
+        }
+        None => metadata.mode() & (bit * 0o111) != 0,
+    };
+    if !permitted {
+        return Err(std::io::ErrorKind::PermissionDenied.into());
+    }
+    Ok(())
+}
+
+/// Checks a path's read-only attribute for writing, without opening it.
+#[cfg(not(unix))]
+fn check_mode(metadata: &std::fs::Metadata, bit: u32) -> std::io::Result<()> {
+    if bit == 0o2 && metadata.permissions().readonly() {
+        return Err(std::io::ErrorKind::PermissionDenied.into());
+    }
+    Ok(())
+}
+
+/// Checks that a path can be opened for writing. Regular files are opened
+/// without creating or truncating them. Other files, including directories,
+/// are only checked against their permission bits, as opening them can block
+/// or have side effects.
+pub fn path_writable(path: &std::path::Path) -> std::io::Result<()> {
+    let metadata = std::fs::metadata(path)?;
+    if metadata.is_file() {
+        std::fs::OpenOptions::new().write(true).open(path)?;
+    } else {
+        check_mode(&metadata, 0o2)?;
+    }
+    Ok(())
+}
+
+/// Checks that a path is a file the current process may execute.
+#[cfg(unix)]
+pub fn path_executable(path: &std::path::Path) -> std::io::Result<()> {
+    let metadata = std::fs::metadata(path)?;
+    if metadata.is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "is a directory",
+        ));
+    }
+    check_mode(&metadata, 0o1)?;
+    if on_noexec_mount(path) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "is on a filesystem mounted noexec",
+        ));
+    }
+    Ok(())
+}
+
+/// Returns `true` if `/proc/self/mounts` lists the mount containing a path
+/// with the `noexec` option.
+#[cfg(unix)]
+fn on_noexec_mount(path: &std::path::Path) -> bool {
+    let (path, mounts) = match (
+        std::fs::canonicalize(path),
+        std::fs::read_to_string("/proc/self/mounts"),
+    ) {
+        (Ok(path), Ok(mounts)) => (path, mounts),
+        _ => return false,
+    };
+    let mut longest = 0;
+    let mut noexec = false;
+    for line in mounts.lines() {
+        let mut fields = line.split(' ');
+        let (point, options) = match (fields.nth(1), fields.nth(1)) {
+            (Some(point), Some(options)) => (unescape_mount_point(point), options),
+            _ => continue,
+        };
+        // Later mounts on the same point hide earlier ones.
+        if path.starts_with(&point) && point.len() >= longest {
+            longest = point.len();
+            noexec = options.split(',').any(|option| option == "noexec");
+        }
+    }
+    noexec
+}
+
+/// Decodes the octal escapes, such as `\040` for a space, in a mount point
+/// from `/proc/self/mounts`.
+#[cfg(unix)]
+fn unescape_mount_point(point: &str) -> String {
+    let mut unescaped = String::with_capacity(point.len());
+    let mut rest = point;
+    while let Some(index) = rest.find('\\') {
+        unescaped.push_str(&rest[..index]);
+        let escape = rest.get(index + 1..index + 4);
+        match escape.and_then(|digits| u8::from_str_radix(digits, 8).ok()) {
+            Some(byte) => {
+                unescaped.push(char::from(byte));
+                rest = &rest[index + 4..];
+            }
+            None => {
+                unescaped.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Returns `true` if an address is not null, for `ensure_non_null!`.
+pub fn is_non_null(addr: usize) -> bool {
+    addr != 0
+}
+
+/// Converts the value of an environment variable read by `ensure_env_var!`.
+pub fn env_string(name: &std::ffi::OsStr, value: std::ffi::OsString) -> std::io::Result<String> {
+    value.into_string().map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("environment variable {:?} is not valid UTF-8", name),
+        )
+    })
+}
+
+/// The time types `ensure_not_expired!` accepts.
+pub trait Expiry: Sized {
+    fn checked_expiry(&self, ttl: std::time::Duration) -> Option<Self>;
+    fn now(&self) -> Self;
+}
+
+impl Expiry for std::time::SystemTime {
+    fn checked_expiry(&self, ttl: std::time::Duration) -> Option<Self> {
+        self.checked_add(ttl)
+    }
+
+    fn now(&self) -> Self {
+        std::time::SystemTime::now()
+    }
+}
+
+impl Expiry for std::time::Instant {
+    fn checked_expiry(&self, ttl: std::time::Duration) -> Option<Self> {
+        self.checked_add(ttl)
+    }
+
+    fn now(&self) -> Self {
+        std::time::Instant::now()
+    }
+}
+
+/// Resolves `path` against the canonical form of `base`, following symlinks
+/// in every part of the result that exists. Returns the canonical base and the
+/// resolved path.
+pub fn resolve_within(
+    base: &std::path::Path,
+    path: &std::path::Path,
+) -> std::io::Result<(std::path::PathBuf, std::path::PathBuf)> {
+    use std::path::Component;
+
+    let base = base.canonicalize()?;
+    let mut resolved = base.clone();
+    // Below a missing component nothing can exist, so the components are
+    // applied lexically. A `..` may climb back into a part that exists, so the
+    // check is repeated after each one.
+    let mut exists = true;
+    for component in path.components() {
+        match component {
+            Component::CurDir => continue,
+            Component::ParentDir => {
+                resolved.pop();
+                exists = true;
+            }
+            component => resolved.push(component),
+        }
+        if exists {
+            match resolved.canonicalize() {
+                Ok(canonical) => resolved = canonical,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => exists = false,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+    Ok((base, resolved))
+}
+
+/// Compares two paths component by component, ignoring `.` components.
+pub fn same_lexical_path(left: &std::path::Path, right: &std::path::Path) -> bool {
+    use std::path::Component;
+
+    let left = left.components().filter(|c| *c != Component::CurDir);
+    let right = right.components().filter(|c| *c != Component::CurDir);
+    left.eq(right)
+}
+
+/// Returns `true` if two half-open ranges share at least one element, for
+/// `ensure_overlap!` and `ensure_disjoint!`.
+pub fn ranges_overlap<T: PartialOrd>(a: &std::ops::Range<T>, b: &std::ops::Range<T>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+/// Decodes UTF-16 code units stored as byte pairs, for `ensure_utf16_valid!`.
+pub fn decode_utf16(
+    bytes: &[u8],
+    from_bytes: fn([u8; 2]) -> u16,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(format!("odd number of bytes: {}", bytes.len()).into());
+    }
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+    Ok(std::char::decode_utf16(units).collect::<Result<_, _>>()?)
+}
+
+/// Returns `true` if a descriptor is open in this process, for
+/// `ensure_fd_valid!`.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn fd_is_open(fd: std::os::unix::io::RawFd) -> bool {
+    fd >= 0 && std::fs::symlink_metadata(format!("/dev/fd/{}", fd)).is_ok()
+}
@@ -0,0 +1,96 @@
+//! Macros validating ranges.
+
+/// Exits a function early with an [`io::Error`] if two ranges do not share at
+/// least one element.
+///
+/// Both arguments are [`Range`]s over any `PartialOrd` type, and the check
+/// passes when `a.start < b.end && b.start < a.end`. Ranges are half-open, so
+/// ranges that only touch, such as `0..4` and `4..8`, do not overlap. When no
+/// message is given the error reports both ranges, which requires the element
+/// type to implement `Debug`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`Range`]: https://doc.rust-lang.org/std/ops/struct.Range.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (a_start, a_end, b_start, b_end) = (0, 10, 5, 15);
+/// ensure_overlap!(a_start..a_end, b_start..b_end, ErrorKind::InvalidData);
+/// ensure_overlap!(a_start..a_end, b_start..b_end, ErrorKind::InvalidData, "ranges must overlap");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_overlap {
+    ($a:expr, $b:expr, $kind:expr $(,)?) => {
+        match (&$a, &$b) {
+            (a, b) => {
+                $crate::ensure!(
+                    $crate::__private::ranges_overlap(a, b),
+                    $kind,
+                    "range {:?} does not overlap {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    };
+    ($a:expr, $b:expr, $kind:expr, $($arg:tt)+) => {
+        match (&$a, &$b) {
+            (a, b) => {
+                $crate::ensure!($crate::__private::ranges_overlap(a, b), $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if two ranges share any
+/// element.
+///
+/// This is the complement of [`ensure_overlap!`]: the check passes when
+/// `!(a.start < b.end && b.start < a.end)`. Ranges that only touch, such as
+/// `0..4` and `4..8`, are disjoint. When no message is given the error reports
+/// both ranges, which requires the element type to implement `Debug`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_overlap!`]: crate::ensure_overlap
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (alloc1, alloc2) = (0x1000..0x2000, 0x2000..0x3000);
+/// ensure_disjoint!(alloc1, alloc2, ErrorKind::InvalidData);
+/// ensure_disjoint!(alloc1, alloc2, ErrorKind::InvalidData, "allocation regions must not overlap");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_disjoint {
+    ($a:expr, $b:expr, $kind:expr $(,)?) => {
+        match (&$a, &$b) {
+            (a, b) => {
+                $crate::ensure!(
+                    !$crate::__private::ranges_overlap(a, b),
+                    $kind,
+                    "range {:?} overlaps {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    };
+    ($a:expr, $b:expr, $kind:expr, $($arg:tt)+) => {
+        match (&$a, &$b) {
+            (a, b) => {
+                $crate::ensure!(!$crate::__private::ranges_overlap(a, b), $kind, $($arg)+);
+            }
+        }
+    };
+}
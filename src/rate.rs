@@ -0,0 +1,132 @@
+//! A token-bucket rate limiter and the macro checking it.
+
+use std::time::{Duration, Instant};
+
+/// A token-bucket rate limiter.
+///
+/// The bucket holds up to `max_rate` tokens and refills continuously at
+/// `max_rate` tokens per `window`, measured with [`Instant`]. Each successful
+/// [`try_acquire`] takes one token, so bursts of up to `max_rate` requests are
+/// allowed after a quiet period. This is what [`ensure_rate_within!`] checks.
+///
+/// [`Instant`]: https://doc.rust-lang.org/std/time/struct.Instant.html
+/// [`try_acquire`]: RateLimiter::try_acquire
+/// [`ensure_rate_within!`]: crate::ensure_rate_within
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::RateLimiter;
+/// use std::time::Duration;
+///
+/// let mut limiter = RateLimiter::new(2, Duration::from_secs(60));
+/// assert!(limiter.try_acquire());
+/// assert!(limiter.try_acquire());
+/// assert!(!limiter.try_acquire());
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    max_rate: u32,
+    window: Duration,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing `max_rate` requests per `window`, starting
+    /// with a full bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero.
+    pub fn new(max_rate: u32, window: Duration) -> Self {
+        assert!(
+            window > Duration::ZERO,
+            "rate limiter window must not be zero"
+        );
+        Self {
+            max_rate,
+            window,
+            tokens: f64::from(max_rate),
+            refilled_at: Instant::now(),
+        }
+    }
+
+    /// Takes a token if one is available, returning whether it did.
+    pub fn try_acquire(&mut self) -> bool {
+        self.refill();
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+
+    /// Returns the current request rate per second, estimated from the tokens
+    /// taken from the bucket and not yet refilled.
+    pub fn current_rate(&mut self) -> f64 {
+        self.refill();
+        (f64::from(self.max_rate) - self.tokens) / self.window.as_secs_f64()
+    }
+
+    /// Returns the number of requests allowed per window.
+    pub fn max_rate(&self) -> u32 {
+        self.max_rate
+    }
+
+    /// Returns the window `max_rate` applies to.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        let refill = elapsed * f64::from(self.max_rate) / self.window.as_secs_f64();
+        self.tokens = (self.tokens + refill).min(f64::from(self.max_rate));
+        self.refilled_at = now;
+    }
+}
+
+/// Exits a function early with an [`io::Error`] if a [`RateLimiter`] has no
+/// tokens left.
+///
+/// The macro calls [`RateLimiter::try_acquire`] on the limiter, which must be
+/// passed as a mutable reference, and bails if no token was available. When
+/// no message is given the error reports the configured rate.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+/// use std::time::Duration;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut limiter = RateLimiter::new(100, Duration::from_secs(1));
+/// ensure_rate_within!(&mut limiter, ErrorKind::WouldBlock);
+/// ensure_rate_within!(&mut limiter, ErrorKind::WouldBlock, "rate limit exceeded: {} req/s", limiter.current_rate());
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_rate_within {
+    ($limiter:expr, $kind:expr $(,)?) => {
+        match $limiter {
+            limiter => {
+                let limiter: &mut $crate::RateLimiter = limiter;
+                $crate::ensure!(
+                    limiter.try_acquire(),
+                    $kind,
+                    "rate limit of {} per {:?} exceeded",
+                    limiter.max_rate(),
+                    limiter.window()
+                );
+            }
+        }
+    };
+    ($limiter:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!($crate::RateLimiter::try_acquire($limiter), $kind, $($arg)+)
+    };
+}
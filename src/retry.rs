@@ -0,0 +1,182 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, ErrorKind, Read, Write};
+use std::ops::Deref;
+
+use crate::IoErrorCategory;
+
+/// An [`io::Error`] marked as safe to retry.
+///
+/// Retry policies can accept this type to signal that the failed operation may
+/// succeed if it is attempted again. It has the same layout as `io::Error`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// let err = format_err!(ErrorKind::TimedOut, "dial timed out");
+/// let err = RetryableError::if_transient(err).unwrap();
+/// assert_eq!(err.kind(), ErrorKind::TimedOut);
+///
+/// let err = format_err!(ErrorKind::NotFound, "no such host");
+/// assert!(RetryableError::if_transient(err).is_none());
+/// ```
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct RetryableError(io::Error);
+
+impl RetryableError {
+    /// Wraps the error if its kind is [`IoErrorCategory::Transient`], and
+    /// returns `None` otherwise.
+    pub fn if_transient(error: io::Error) -> Option<Self> {
+        match IoErrorCategory::from(&error) {
+            IoErrorCategory::Transient => Some(Self(error)),
+            _ => None,
+        }
+    }
+
+    /// Returns the wrapped `io::Error`.
+    pub fn into_inner(self) -> io::Error {
+        self.0
+    }
+}
+
+impl fmt::Display for RetryableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Error for RetryableError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl Deref for RetryableError {
+    type Target = io::Error;
+
+    fn deref(&self) -> &io::Error {
+        &self.0
+    }
+}
+
+impl From<io::Error> for RetryableError {
+    fn from(error: io::Error) -> Self {
+        Self(error)
+    }
+}
+
+impl From<RetryableError> for io::Error {
+    fn from(error: RetryableError) -> Self {
+        error.0
+    }
+}
+
+/// A reader or writer that retries operations interrupted by a signal.
+///
+/// System calls may fail with [`ErrorKind::Interrupted`] when a signal arrives
+/// before they complete, and the operation should almost always be retried
+/// immediately. `RetryIo` wraps any [`Read`] or [`Write`] implementor and does
+/// so, up to an optional limit. Once the limit is reached the `Interrupted`
+/// error is returned. Other errors are returned right away.
+///
+/// [`ErrorKind::Interrupted`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.Interrupted
+/// [`Read`]: https://doc.rust-lang.org/std/io/trait.Read.html
+/// [`Write`]: https://doc.rust-lang.org/std/io/trait.Write.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::RetryIo;
+/// use std::io::Read;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = RetryIo::new(&b"hello"[..]);
+/// let mut buf = [0; 5];
+/// reader.read(&mut buf)?;
+/// assert_eq!(&buf, b"hello");
+///
+/// let writer = RetryIo::with_max_retries(Vec::<u8>::new(), 3);
+/// assert_eq!(writer.max_retries(), Some(3));
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+pub struct RetryIo<T> {
+    inner: T,
+    max_retries: Option<usize>,
+}
+
+impl<T> RetryIo<T> {
+    /// Wraps `inner`, retrying interrupted operations without limit.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            max_retries: None,
+        }
+    }
+
+    /// Wraps `inner`, retrying each interrupted operation at most `max_retries`
+    /// times.
+    pub fn with_max_retries(inner: T, max_retries: usize) -> Self {
+        Self {
+            inner,
+            max_retries: Some(max_retries),
+        }
+    }
+
+    /// Returns the retry limit, or `None` if retries are unlimited.
+    pub fn max_retries(&self) -> Option<usize> {
+        self.max_retries
+    }
+
+    /// Returns a reference to the wrapped value.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped value.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn retry<R>(&mut self, mut op: impl FnMut(&mut T) -> io::Result<R>) -> io::Result<R> {
+        let mut retries = 0;
+        loop {
+            match op(&mut self.inner) {
+                Err(err)
+                    if err.kind() == ErrorKind::Interrupted
+                        && self.max_retries.is_none_or(|max| retries < max) =>
+                {
+                    retries += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+impl<T: Read> Read for RetryIo<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.retry(|inner| inner.read(buf))
+    }
+}
+
+impl<T: Write> Write for RetryIo<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.retry(|inner| inner.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.retry(|inner| inner.flush())
+    }
+}
@@ -0,0 +1,166 @@
+//! Macros validating reads, writes, and seeks.
+
+/// How the end of the data is treated by [`ensure_seek_valid!`].
+///
+/// [`ensure_seek_valid!`]: crate::ensure_seek_valid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SeekMode {
+    /// The position will be read from, so it must be before the end of the
+    /// data.
+    Read,
+    /// The position will be written to, so it may also be exactly at the end
+    /// of the data, appending to it.
+    Write,
+}
+
+impl SeekMode {
+    /// Returns `true` if `offset` is a valid position in data of length `len`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use io_ensure::SeekMode;
+    ///
+    /// assert!(!SeekMode::Read.is_valid(16, 16));
+    /// assert!(SeekMode::Write.is_valid(16, 16));
+    /// ```
+    pub fn is_valid(self, offset: u64, len: u64) -> bool {
+        match self {
+            SeekMode::Read => offset < len,
+            SeekMode::Write => offset <= len,
+        }
+    }
+}
+
+/// Exits a function early with an [`io::Error`] if a seek position is out of
+/// range for data of a known length.
+///
+/// The [`SeekMode`] decides whether seeking to exactly the end of the data is
+/// allowed: it is for [`SeekMode::Write`], which appends there, and is not for
+/// [`SeekMode::Read`], since there is nothing left to read. Both the offset
+/// and the length are `u64`. When no message is given the error reports both.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (seek_offset, file_len) = (512_u64, 4096_u64);
+/// ensure_seek_valid!(seek_offset, file_len, SeekMode::Read, ErrorKind::InvalidInput);
+/// ensure_seek_valid!(
+///     file_len,
+///     file_len,
+///     SeekMode::Write,
+///     ErrorKind::InvalidInput,
+///     "seek offset {} exceeds file length {}",
+///     file_len,
+///     file_len
+/// );
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_seek_valid {
+    ($offset:expr, $len:expr, $mode:expr, $kind:expr $(,)?) => {
+        match ($offset, $len) {
+            (offset, len) => $crate::ensure!(
+                $crate::SeekMode::is_valid($mode, offset, len),
+                $kind,
+                "seek offset {} is out of range for length {}",
+                offset,
+                len
+            ),
+        }
+    };
+    ($offset:expr, $len:expr, $mode:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!($crate::SeekMode::is_valid($mode, $offset, $len), $kind, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a read did not return
+/// exactly the expected number of bytes.
+///
+/// [`Read::read`] may return fewer bytes than requested; this turns the
+/// count check after such a call into one line. When no message is given the
+/// error reports the actual and the expected count.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`Read::read`]: https://doc.rust-lang.org/std/io/trait.Read.html#tymethod.read
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{ErrorKind, Read};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reader = &b"header"[..];
+/// let mut buf = [0; 6];
+/// let bytes_read = reader.read(&mut buf)?;
+/// ensure_read_exact!(bytes_read, buf.len(), ErrorKind::UnexpectedEof);
+/// ensure_read_exact!(bytes_read, 6, ErrorKind::UnexpectedEof, "short read: got {} of {} bytes", bytes_read, 6);
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_read_exact {
+    ($actual:expr, $expected:expr, $kind:expr $(,)?) => {
+        match ($actual, $expected) {
+            (actual, expected) => $crate::ensure!(
+                actual == expected,
+                $kind,
+                "short read: got {} of {} bytes",
+                actual,
+                expected
+            ),
+        }
+    };
+    ($actual:expr, $expected:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!($actual == $expected, $kind, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a write did not accept
+/// exactly the expected number of bytes.
+///
+/// The counterpart of [`ensure_read_exact!`] for the count returned by
+/// [`Write::write`]. When no message is given the error reports the actual
+/// and the expected count.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_read_exact!`]: crate::ensure_read_exact
+/// [`Write::write`]: https://doc.rust-lang.org/std/io/trait.Write.html#tymethod.write
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::{ErrorKind, Write};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut out = Vec::new();
+/// let buf = b"frame";
+/// let bytes_written = out.write(buf)?;
+/// ensure_write_exact!(bytes_written, buf.len(), ErrorKind::WriteZero);
+/// ensure_write_exact!(bytes_written, buf.len(), ErrorKind::WriteZero, "short write: {} of {} bytes written", bytes_written, buf.len());
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_write_exact {
+    ($actual:expr, $expected:expr, $kind:expr $(,)?) => {
+        match ($actual, $expected) {
+            (actual, expected) => $crate::ensure!(
+                actual == expected,
+                $kind,
+                "short write: {} of {} bytes written",
+                actual,
+                expected
+            ),
+        }
+    };
+    ($actual:expr, $expected:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!($actual == $expected, $kind, $($arg)+)
+    };
+}
@@ -0,0 +1,211 @@
+//! Macros validating sequences.
+
+/// Exits a function early with an [`io::Error`] if the elements of an iterator
+/// are not in non-decreasing order.
+///
+/// Each element must be greater than or equal to the one before it, as
+/// determined by [`PartialOrd`]. The iterator is consumed up to the first
+/// out-of-order pair. When no message is given the error reports that pair and
+/// their indices, which requires the elements to implement `Debug`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`PartialOrd`]: https://doc.rust-lang.org/std/cmp/trait.PartialOrd.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let timestamps = [1, 2, 2, 5];
+/// ensure_monotonic!(timestamps.iter().copied(), ErrorKind::InvalidData);
+/// ensure_monotonic!(timestamps.iter(), ErrorKind::InvalidData, "timestamps must be non-decreasing");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_monotonic {
+    ($iter:expr, $($arg:tt)+) => {
+        $crate::__ensure_order!($iter, |prev, next| next >= prev, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if the elements of an iterator
+/// are not in strictly increasing order.
+///
+/// Like [`ensure_monotonic!`], but each element must be strictly greater than
+/// the one before it, so equal adjacent elements fail.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_monotonic!`]: crate::ensure_monotonic
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let sequence_numbers = vec![1, 2, 3, 8];
+/// ensure_strictly_monotonic!(sequence_numbers.iter(), ErrorKind::InvalidData);
+/// ensure_strictly_monotonic!(sequence_numbers, ErrorKind::InvalidData, "duplicate sequence number");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_strictly_monotonic {
+    ($iter:expr, $($arg:tt)+) => {
+        $crate::__ensure_order!($iter, |prev, next| next > prev, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if the elements of an iterator
+/// are not in non-increasing order.
+///
+/// The reverse of [`ensure_monotonic!`]: each element must be less than or
+/// equal to the one before it, so runs of equal elements pass. When no message
+/// is given the error reports the first out-of-order pair and their indices.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_monotonic!`]: crate::ensure_monotonic
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut priorities = vec![9, 7, 7, 2];
+/// ensure_monotonic_decreasing!(priorities.iter(), ErrorKind::InvalidData);
+/// ensure_monotonic_decreasing!(priorities.drain(..), ErrorKind::InvalidData, "expected decreasing priorities");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_monotonic_decreasing {
+    ($iter:expr, $($arg:tt)+) => {
+        $crate::__ensure_order!($iter, |prev, next| next <= prev, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if the elements of an iterator
+/// are not in strictly decreasing order.
+///
+/// Like [`ensure_monotonic_decreasing!`], but each element must be strictly
+/// less than the one before it, so equal adjacent elements fail.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_monotonic_decreasing!`]: crate::ensure_monotonic_decreasing
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let countdown = [3, 2, 1, 0];
+/// ensure_strictly_monotonic_decreasing!(countdown.iter(), ErrorKind::InvalidData);
+/// ensure_strictly_monotonic_decreasing!(countdown, ErrorKind::InvalidData, "countdown repeated a value");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_strictly_monotonic_decreasing {
+    ($iter:expr, $($arg:tt)+) => {
+        $crate::__ensure_order!($iter, |prev, next| next < prev, $($arg)+)
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if any window of adjacent
+/// elements of an iterator does not satisfy a predicate.
+///
+/// Every run of `size` consecutive elements is passed to the predicate as a
+/// slice, in order, like [`slice::windows`] but for any iterator. The iterator
+/// is consumed up to the end of the first window that fails. An iterator with
+/// fewer than `size` elements has no windows and passes. When no message is
+/// given the error reports the position of the failing window.
+///
+/// A window size of zero fails the check with `ErrorKind::InvalidInput` and the
+/// message "window size must be non-zero", whatever kind and message are given.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`slice::windows`]: https://doc.rust-lang.org/std/primitive.slice.html#method.windows
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let data: Vec<i64> = vec![3, -1, 2, -4, 5, 1];
+/// ensure_window!(data.iter().copied(), 4, |window: &[i64]| window.iter().sum::<i64>() >= 0, ErrorKind::InvalidData);
+/// ensure_window!(
+///     data.iter().copied(),
+///     4,
+///     |window: &[i64]| window.iter().sum::<i64>() >= 0,
+///     ErrorKind::InvalidData,
+///     "sliding window sum went negative"
+/// );
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_window {
+    ($iter:expr, $size:expr, $predicate:expr, $kind:expr $(,)?) => {
+        match $size {
+            size => {
+                let size: usize = size;
+                $crate::ensure!(
+                    size != 0,
+                    ::std::io::ErrorKind::InvalidInput,
+                    "window size must be non-zero"
+                );
+                if let ::std::result::Result::Err(start) =
+                    $crate::__private::check_windows($iter, size, $predicate)
+                {
+                    $crate::__ensure_bail!(
+                        $iter,
+                        $kind,
+                        "window of {} elements at index {} failed the check",
+                        size,
+                        start
+                    );
+                }
+            }
+        }
+    };
+    ($iter:expr, $size:expr, $predicate:expr, $kind:expr, $($arg:tt)+) => {
+        match $size {
+            size => {
+                let size: usize = size;
+                $crate::ensure!(
+                    size != 0,
+                    ::std::io::ErrorKind::InvalidInput,
+                    "window size must be non-zero"
+                );
+                if let ::std::result::Result::Err(_) =
+                    $crate::__private::check_windows($iter, size, $predicate)
+                {
+                    $crate::__ensure_bail!($iter, $kind, $($arg)+);
+                }
+            }
+        }
+    };
+}
+
+/// Checks that every adjacent pair of elements satisfies a comparison.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_order {
+    ($iter:expr, $in_order:expr, $kind:expr $(,)?) => {
+        if let ::std::result::Result::Err(out_of_order) =
+            $crate::__private::check_order($iter, $in_order)
+        {
+            $crate::__ensure_bail!($iter, $kind, "{}", out_of_order);
+        }
+    };
+    ($iter:expr, $in_order:expr, $kind:expr, $($arg:tt)+) => {
+        if let ::std::result::Result::Err(_) = $crate::__private::check_order($iter, $in_order) {
+            $crate::__ensure_bail!($iter, $kind, $($arg)+);
+        }
+    };
+}
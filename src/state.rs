@@ -0,0 +1,95 @@
+//! Macros validating state machines.
+
+/// Exits a function early with an [`io::Error`] if moving from one state to
+/// another is not an allowed transition.
+///
+/// The allowed transitions can be given as a list of `(from, to)` pairs (an
+/// array, slice, or `Vec`), as a [`HashSet`] of pairs, or as a closure taking
+/// `(&from, &to)` and returning whether the transition is allowed. A closure
+/// stored in a variable needs its argument types spelled out. Lists need
+/// the state type to implement `PartialEq`, sets need `Hash`, `Eq`, and
+/// `Clone`. When no message is given the error reports both states, which
+/// requires the state type to implement `Debug`.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`HashSet`]: https://doc.rust-lang.org/std/collections/struct.HashSet.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum State {
+///     Idle,
+///     Handshake,
+///     Open,
+///     Closed,
+/// }
+///
+/// const VALID_TRANSITIONS: &[(State, State)] = &[
+///     (State::Idle, State::Handshake),
+///     (State::Handshake, State::Open),
+///     (State::Open, State::Closed),
+/// ];
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (from_state, to_state) = (State::Handshake, State::Open);
+/// ensure_state_transition!(from_state, to_state, VALID_TRANSITIONS, ErrorKind::Other);
+/// ensure_state_transition!(
+///     from_state,
+///     to_state,
+///     VALID_TRANSITIONS,
+///     ErrorKind::Other,
+///     "invalid transition from {:?} to {:?}",
+///     from_state,
+///     to_state
+/// );
+/// ensure_state_transition!(from_state, to_state, |(_, to)| *to != State::Idle, ErrorKind::Other);
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_state_transition {
+    ($from:expr, $to:expr, |$args:tt| $body:expr, $($arg:tt)+) => {
+        match (&$from, &$to) {
+            (from, to) => $crate::__ensure_transition!(
+                $crate::__private::transition_allowed_by(|$args| $body, from, to),
+                from,
+                to,
+                $($arg)+
+            ),
+        }
+    };
+    ($from:expr, $to:expr, $table:expr, $($arg:tt)+) => {
+        match (&$from, &$to) {
+            (from, to) => $crate::__ensure_transition!(
+                {
+                    use $crate::__private::TransitionTable as _;
+                    ($table).allows_transition(from, to)
+                },
+                from,
+                to,
+                $($arg)+
+            ),
+        }
+    };
+}
+
+/// Checks whether a transition was allowed and picks the error message.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_transition {
+    ($allowed:expr, $from:expr, $to:expr, $kind:expr $(,)?) => {
+        $crate::ensure!(
+            $allowed,
+            $kind,
+            "invalid state transition from {:?} to {:?}",
+            $from,
+            $to
+        )
+    };
+    ($allowed:expr, $from:expr, $to:expr, $kind:expr, $($arg:tt)+) => {
+        $crate::ensure!($allowed, $kind, $($arg)+)
+    };
+}
@@ -0,0 +1,245 @@
+//! Macros for validating text.
+
+/// Exits a function early with an [`io::Error`] if the input does not match a
+/// regular expression.
+///
+/// The input can be any type implementing `AsRef<str>`. The pattern must be a
+/// string literal; it is compiled on first use and cached for all later calls
+/// made from the same call site. An invalid pattern panics on first use. When no
+/// message is given the error includes the input and the pattern.
+///
+/// This macro is only available with the `regex` feature enabled.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let input = "2020-01-31";
+/// ensure_regex!(input, r"^\d{4}-\d{2}-\d{2}$", ErrorKind::InvalidInput);
+/// ensure_regex!(input, r"^\d{4}-\d{2}-\d{2}$", ErrorKind::InvalidInput, "date must be YYYY-MM-DD format");
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "regex")]
+#[macro_export]
+macro_rules! ensure_regex {
+    ($input:expr, $pattern:literal, $kind:expr $(,)?) => {
+        match ::std::convert::AsRef::<str>::as_ref(&$input) {
+            input => {
+                $crate::ensure!(
+                    $crate::__regex_is_match!(input, $pattern),
+                    $kind,
+                    "{:?} does not match pattern {:?}",
+                    input,
+                    $pattern
+                );
+            }
+        }
+    };
+    ($input:expr, $pattern:literal, $kind:expr, $($arg:tt)+) => {
+        match ::std::convert::AsRef::<str>::as_ref(&$input) {
+            input => {
+                $crate::ensure!($crate::__regex_is_match!(input, $pattern), $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Matches a string against a regex literal, compiling it once per call site.
+#[cfg(feature = "regex")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __regex_is_match {
+    ($input:expr, $pattern:literal) => {{
+        static REGEX: ::std::sync::OnceLock<$crate::__private::regex::Regex> =
+            ::std::sync::OnceLock::new();
+        REGEX
+            .get_or_init(|| $crate::__private::regex::Regex::new($pattern).expect("invalid regex"))
+            .is_match($input)
+    }};
+}
+
+/// Exits a function early with an [`io::Error`] if a path does not match a
+/// shell-style glob pattern.
+///
+/// The input can be any type implementing `AsRef<Path>`, such as `&str` or
+/// `PathBuf`. The pattern must be a string literal using the syntax of
+/// [`glob::Pattern`]: `?`, `*`, `**`, and `[...]` character classes. Brace
+/// alternation such as `{json,yaml}` is not supported. Matching is case
+/// sensitive.
+///
+/// The pattern is compiled on first use and cached for all later calls made
+/// from the same call site. An invalid pattern panics on first use. When no
+/// message is given the error includes the path and the pattern.
+///
+/// This macro is only available with the `glob` feature enabled.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`glob::Pattern`]: https://docs.rs/glob/0.3/glob/struct.Pattern.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let filename = "config.json";
+/// ensure_glob!(filename, "*.json", ErrorKind::InvalidInput);
+/// ensure_glob!(filename, "*.[jy]*", ErrorKind::InvalidInput, "expected JSON or YAML file: {}", filename);
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "glob")]
+#[macro_export]
+macro_rules! ensure_glob {
+    ($path:expr, $pattern:literal, $kind:expr $(,)?) => {
+        match ::std::convert::AsRef::<::std::path::Path>::as_ref(&$path) {
+            path => {
+                $crate::ensure!(
+                    $crate::__glob_matches!(path, $pattern),
+                    $kind,
+                    "{} does not match pattern {:?}",
+                    path.display(),
+                    $pattern
+                );
+            }
+        }
+    };
+    ($path:expr, $pattern:literal, $kind:expr, $($arg:tt)+) => {
+        match ::std::convert::AsRef::<::std::path::Path>::as_ref(&$path) {
+            path => {
+                $crate::ensure!($crate::__glob_matches!(path, $pattern), $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Matches a path against a glob literal, compiling it once per call site.
+#[cfg(feature = "glob")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __glob_matches {
+    ($path:expr, $pattern:literal) => {{
+        static PATTERN: ::std::sync::OnceLock<$crate::__private::glob::Pattern> =
+            ::std::sync::OnceLock::new();
+        PATTERN
+            .get_or_init(|| {
+                $crate::__private::glob::Pattern::new($pattern).expect("invalid glob pattern")
+            })
+            .matches_path($path)
+    }};
+}
+
+/// Decodes UTF-16LE bytes into a `String`, exiting a function early with an
+/// [`io::Error`] if they are not valid UTF-16.
+///
+/// The macro evaluates to the decoded string. The input may be anything
+/// implementing `AsRef<[u8]>`. It fails if it has an odd number of bytes or
+/// contains an unpaired surrogate, and the cause is attached as the error's
+/// source. This is the same as [`ensure_utf16_le_valid!`]; use
+/// [`ensure_utf16_be_valid!`] for big-endian data.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_utf16_le_valid!`]: crate::ensure_utf16_le_valid
+/// [`ensure_utf16_be_valid!`]: crate::ensure_utf16_be_valid
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let bytes_le = b"h\0i\0";
+/// ensure_utf16_valid!(bytes_le, ErrorKind::InvalidData);
+/// let s = ensure_utf16_valid!(bytes_le, ErrorKind::InvalidData, "invalid UTF-16LE data");
+/// assert_eq!(s, "hi");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_utf16_valid {
+    ($($arg:tt)+) => {
+        $crate::ensure_utf16_le_valid!($($arg)+)
+    };
+}
+
+/// Decodes UTF-16LE bytes into a `String`, exiting a function early with an
+/// [`io::Error`] if they are not valid UTF-16.
+///
+/// See [`ensure_utf16_valid!`] for details.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_utf16_valid!`]: crate::ensure_utf16_valid
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let name = ensure_utf16_le_valid!([0x3d, 0xd8, 0x00, 0xde], ErrorKind::InvalidData);
+/// assert_eq!(name, "\u{1f600}");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_utf16_le_valid {
+    ($($arg:tt)+) => {
+        $crate::__ensure_utf16!(u16::from_le_bytes, "UTF-16LE", $($arg)+)
+    };
+}
+
+/// Decodes UTF-16BE bytes into a `String`, exiting a function early with an
+/// [`io::Error`] if they are not valid UTF-16.
+///
+/// Like [`ensure_utf16_valid!`], but each pair of bytes is read big-endian.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure_utf16_valid!`]: crate::ensure_utf16_valid
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let s = ensure_utf16_be_valid!(b"\0h\0i", ErrorKind::InvalidData, "invalid UTF-16BE data");
+/// assert_eq!(s, "hi");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_utf16_be_valid {
+    ($($arg:tt)+) => {
+        $crate::__ensure_utf16!(u16::from_be_bytes, "UTF-16BE", $($arg)+)
+    };
+}
+
+/// Decodes UTF-16 bytes with the given byte order.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_utf16 {
+    ($from_bytes:path, $what:literal, $bytes:expr, $kind:expr $(,)?) => {
+        $crate::__ensure_utf16!(
+            $from_bytes,
+            $what,
+            $bytes,
+            $kind,
+            concat!("data is not valid ", $what)
+        )
+    };
+    ($from_bytes:path, $what:literal, $bytes:expr, $kind:expr, $($arg:tt)+) => {
+        match ::std::convert::AsRef::<[u8]>::as_ref(&$bytes) {
+            bytes => $crate::__ensure_unwrap!(
+                $bytes,
+                $crate::__private::decode_utf16(bytes, $from_bytes),
+                $kind,
+                $($arg)+
+            ),
+        }
+    };
+}
@@ -0,0 +1,121 @@
+//! Macros validating deadlines and expiry times.
+
+/// Exits a function early with an [`io::Error`] if a deadline has passed.
+///
+/// The check passes when [`Instant::now`] is before the deadline, so it uses
+/// the monotonic clock and is unaffected by changes to the system time. This
+/// fits at the top of each iteration of a retry loop. When no message is given
+/// the error reports how long ago the deadline passed.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`Instant::now`]: https://doc.rust-lang.org/std/time/struct.Instant.html#method.now
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+/// use std::time::{Duration, Instant};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let deadline = Instant::now() + Duration::from_secs(30);
+/// ensure_timeout_not_elapsed!(deadline, ErrorKind::TimedOut);
+/// ensure_timeout_not_elapsed!(deadline, ErrorKind::TimedOut, "operation deadline exceeded by {:?}", Instant::now().saturating_duration_since(deadline));
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_timeout_not_elapsed {
+    ($deadline:expr, $kind:expr $(,)?) => {
+        match ($deadline, ::std::time::Instant::now()) {
+            (deadline, now) => {
+                let deadline: ::std::time::Instant = deadline;
+                $crate::ensure!(
+                    now < deadline,
+                    $kind,
+                    "deadline exceeded by {:?}",
+                    now.duration_since(deadline)
+                );
+            }
+        }
+    };
+    ($deadline:expr, $kind:expr, $($arg:tt)+) => {
+        match $deadline {
+            deadline => {
+                let deadline: ::std::time::Instant = deadline;
+                $crate::ensure!(::std::time::Instant::now() < deadline, $kind, $($arg)+);
+            }
+        }
+    };
+}
+
+/// Exits a function early with an [`io::Error`] if a time-to-live has run out.
+///
+/// The check passes while the current time is before `issued_at + ttl`. The
+/// issue time can be a [`SystemTime`], for timestamps that come from outside
+/// the process such as token claims, or an [`Instant`]. If adding the TTL
+/// overflows the time type the check fails with `ErrorKind::InvalidInput`
+/// instead. When no message is given the error reports the expiry time and
+/// the current time.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`SystemTime`]: https://doc.rust-lang.org/std/time/struct.SystemTime.html
+/// [`Instant`]: https://doc.rust-lang.org/std/time/struct.Instant.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+/// use std::time::{Duration, SystemTime};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let issued_at = SystemTime::now();
+/// ensure_not_expired!(issued_at, Duration::from_secs(3600), ErrorKind::TimedOut);
+/// ensure_not_expired!(issued_at, Duration::from_secs(3600), ErrorKind::TimedOut, "token expired");
+/// # Ok(()) }
+/// ```
+#[macro_export]
+macro_rules! ensure_not_expired {
+    ($issued_at:expr, $ttl:expr, $kind:expr $(,)?) => {
+        match $crate::__ensure_expiry!($issued_at, $ttl) {
+            expires_at => match $crate::__private::Expiry::now(&expires_at) {
+                now => $crate::ensure!(
+                    now < expires_at,
+                    $kind,
+                    "expired at {:?}, now {:?}",
+                    expires_at,
+                    now
+                ),
+            },
+        }
+    };
+    ($issued_at:expr, $ttl:expr, $kind:expr, $($arg:tt)+) => {
+        match $crate::__ensure_expiry!($issued_at, $ttl) {
+            expires_at => $crate::ensure!(
+                $crate::__private::Expiry::now(&expires_at) < expires_at,
+                $kind,
+                $($arg)+
+            ),
+        }
+    };
+}
+
+/// Computes `issued_at + ttl`, bailing with `InvalidInput` on overflow.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_expiry {
+    ($issued_at:expr, $ttl:expr) => {
+        match ($issued_at, $ttl) {
+            (issued_at, ttl) => match $crate::__private::Expiry::checked_expiry(&issued_at, ttl) {
+                ::std::option::Option::Some(expires_at) => expires_at,
+                ::std::option::Option::None => $crate::__ensure_bail!(
+                    $issued_at,
+                    ::std::io::ErrorKind::InvalidInput,
+                    "expiry time overflows: {:?} + {:?}",
+                    issued_at,
+                    ttl
+                ),
+            },
+        }
+    };
+}
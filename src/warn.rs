@@ -0,0 +1,70 @@
+//! Macros reporting errors without returning early.
+
+/// Logs an [`io::Error`] as a warning without returning early.
+///
+/// Takes the same arguments as [`format_err!`]. The error is constructed and
+/// then reported, after which execution continues. This is useful for
+/// non-fatal violations that should be observable in logs but should not abort
+/// the current operation.
+///
+/// Where the warning goes depends on the enabled features:
+///
+/// - with `tracing`, it is emitted as a [`tracing`] event at `WARN` level;
+/// - otherwise with `log`, it is logged through [`log::warn!`];
+/// - otherwise with `warn-stderr`, it is printed to standard error;
+/// - otherwise nothing is reported.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`format_err!`]: crate::format_err
+/// [`tracing`]: https://docs.rs/tracing
+/// [`log::warn!`]: https://docs.rs/log/0.4/log/macro.warn.html
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// let state_info = "half-open";
+/// io_warn!(ErrorKind::Other, "unexpected state detected: {}", state_info);
+/// io_warn!(ErrorKind::UnexpectedEof);
+/// ```
+#[macro_export]
+macro_rules! io_warn {
+    ($($arg:tt)+) => {{
+        let err = $crate::format_err!($($arg)+);
+        $crate::__io_warn!(err);
+    }};
+}
+
+/// Logs an [`io::Error`] as a warning if the condition is not satisfied,
+/// without returning early.
+///
+/// Takes the same arguments as [`ensure!`], but reports the error the way
+/// [`io_warn!`] does instead of returning it. This is useful for graceful
+/// degradation, where a failed check warrants a warning but not a hard
+/// failure. With the `tracing` feature the event also includes the failing
+/// condition as a structured field.
+///
+/// [`io::Error`]: https://doc.rust-lang.org/std/io/struct.Error.html
+/// [`ensure!`]: crate::ensure
+/// [`io_warn!`]: crate::io_warn
+///
+/// # Examples
+///
+/// ```
+/// use io_ensure::*;
+/// use std::io::ErrorKind;
+///
+/// let pool_is_healthy = false;
+/// ensure_or_warn!(pool_is_healthy, ErrorKind::Other, "pool health check failed, continuing with degraded service");
+/// ```
+#[macro_export]
+macro_rules! ensure_or_warn {
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            let err = $crate::format_err!($($arg)+);
+            $crate::__io_warn!(err, $cond);
+        }
+    };
+}
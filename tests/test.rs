@@ -0,0 +1,4300 @@
+use io_ensure::*;
+use std::io::{self, ErrorKind};
+
+fn reserve(buf: &Vec<u8>, needed: usize) -> io::Result<()> {
+    ensure_capacity!(buf, needed, ErrorKind::Other);
+    Ok(())
+}
+
+#[test]
+fn ensure_capacity() {
+    let mut buf = Vec::with_capacity(8);
+    buf.extend_from_slice(&[0; 4]);
+    let spare = buf.capacity() - buf.len();
+
+    assert!(reserve(&buf, spare).is_ok());
+    assert!(reserve(&buf, spare - 1).is_ok());
+
+    let err = reserve(&buf, spare + 1).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+    let msg = err.to_string();
+    assert!(msg.contains(&(spare + 1).to_string()));
+    assert!(msg.contains(&spare.to_string()));
+}
+
+#[test]
+fn ensure_capacity_ge() {
+    fn check(buf: &Vec<u8>, needed: usize) -> io::Result<()> {
+        ensure_capacity_ge!(
+            buf,
+            needed,
+            ErrorKind::Other,
+            "write would cause reallocation"
+        );
+        Ok(())
+    }
+
+    let buf = Vec::with_capacity(16);
+    let spare = buf.capacity() - buf.len();
+    assert!(check(&buf, spare).is_ok());
+    assert!(check(&buf, 1).is_ok());
+    let err = check(&buf, spare + 1).unwrap_err();
+    assert_eq!(err.to_string(), "write would cause reallocation");
+}
+
+#[test]
+fn format_err_fields() {
+    let id = "abc123";
+    let err = format_err!(ErrorKind::Other, "request failed"; request_id = id, retry = 3);
+    assert_eq!(
+        err.to_string(),
+        "request failed [request_id=abc123, retry=3]"
+    );
+
+    let err = format_err!(ErrorKind::Other, "dial {} failed", "localhost"; attempt = 2,);
+    assert_eq!(err.to_string(), "dial localhost failed [attempt=2]");
+}
+
+#[test]
+fn format_err_field_block() {
+    let (addr, attempts) = (std::net::Ipv4Addr::new(10, 0, 0, 1), 3u32);
+    let err = format_err!(ErrorKind::TimedOut, "dial failed", { addr = %addr, attempt = attempts });
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert_eq!(err.to_string(), "dial failed");
+    let payload = downcast_io_err!(err, IoEnsureError).unwrap();
+    let fields: Vec<_> = payload.fields().iter().collect();
+    assert_eq!(
+        fields,
+        [
+            ("addr", &FieldValue::Str("10.0.0.1".into())),
+            ("attempt", &FieldValue::U64(3)),
+        ]
+    );
+    assert!(format!("{:#}", err).starts_with("dial failed [addr=10.0.0.1, attempt=3] at "));
+
+    let err =
+        format_err!(ErrorKind::Other, "dial {} failed", "db", { path = ?"/tmp", ok = false, },);
+    assert_eq!(err.to_string(), "dial db failed");
+    let fields = downcast_io_err!(err, IoEnsureError).unwrap().fields();
+    assert_eq!(
+        fields.get("path"),
+        Some(&FieldValue::Str("\"/tmp\"".into()))
+    );
+    assert_eq!(fields.get("ok"), Some(&FieldValue::Bool(false)));
+    assert_eq!(fields.len(), 2);
+
+    let err = format_err!("timed out", { seconds = 1.5 });
+    assert_eq!(err.kind(), ErrorKind::Other);
+    let fields = downcast_io_err!(err, IoEnsureError).unwrap().fields();
+    assert_eq!(fields.get("seconds"), Some(&FieldValue::F64(1.5)));
+
+    fn check(len: i64) -> io::Result<()> {
+        ensure!(len >= 0, ErrorKind::InvalidInput, "negative length", {
+            len = len
+        });
+        Ok(())
+    }
+    let err = check(-1).unwrap_err();
+    let fields = downcast_io_err!(err, IoEnsureError).unwrap().fields();
+    assert_eq!(fields.get("len"), Some(&FieldValue::I64(-1)));
+
+    // A block as the last format argument is not a field block.
+    let err = format_err!(ErrorKind::Other, "{}", { 1 + 1 });
+    assert_eq!(err.to_string(), "2");
+    assert!(downcast_io_err!(err, IoEnsureError)
+        .unwrap()
+        .fields()
+        .is_empty());
+}
+
+#[derive(Debug)]
+struct Layer(&'static str, Option<Box<Layer>>);
+
+impl std::fmt::Display for Layer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for Layer {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.1.as_ref().map(|l| &**l as _)
+    }
+}
+
+#[test]
+fn format_err_source() {
+    let inner = format_err!(ErrorKind::UnexpectedEof, "stream ended");
+    let err = format_err!(ErrorKind::InvalidData, "decoding frame {}", 7; source = inner);
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "decoding frame 7");
+    let source = err.get_ref().and_then(|err| err.source()).unwrap();
+    assert_eq!(source.to_string(), "stream ended");
+    let source = source.downcast_ref::<io::Error>().unwrap();
+    assert_eq!(source.kind(), ErrorKind::UnexpectedEof);
+
+    let err = format_err!(ErrorKind::Other, "no args"; source = "boxed message");
+    assert_eq!(err.to_string(), "no args");
+    let source = err.get_ref().and_then(|err| err.source()).unwrap();
+    assert_eq!(source.to_string(), "boxed message");
+
+    let err = format_err!("kindless {}", 1; source = String::from("cause"));
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(err.to_string(), "kindless 1");
+
+    // `source` is only special on its own, other keys are still fields.
+    let err = format_err!(ErrorKind::Other, "failed"; sources = 2);
+    assert_eq!(err.to_string(), "failed [sources=2]");
+}
+
+#[test]
+fn pretty_io_error() {
+    let err = format_err!(ErrorKind::NotFound, "config file missing").pretty();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert_eq!(
+        err.to_string(),
+        "kind: NotFound\nmessage: config file missing"
+    );
+
+    let chain = Layer(
+        "loading config",
+        Some(Box::new(Layer(
+            "reading file",
+            Some(Box::new(Layer("disk on fire", None))),
+        ))),
+    );
+    let err = PrettyIoError::new(format_err!(ErrorKind::Other, chain));
+    let lines: Vec<_> = err.to_string().lines().map(String::from).collect();
+    assert_eq!(
+        lines,
+        [
+            "kind: Other",
+            "message: loading config",
+            "caused by:",
+            "    0: reading file",
+            "    1: disk on fire",
+        ]
+    );
+
+    let err: io::Error = err.into();
+    assert_eq!(err.kind(), ErrorKind::Other);
+}
+
+mod shadowed_format_err {
+    use std::io::{self, ErrorKind};
+
+    #[allow(unused_macros)]
+    macro_rules! format_err {
+        ($($arg:tt)*) => {
+            compile_error!("the user's `format_err!` was called")
+        };
+    }
+
+    fn check(a: u8) -> io::Result<()> {
+        io_ensure::ensure!(a == 1, ErrorKind::Other, "a is {}", a);
+        io_ensure::ensure_eq!(a, 1, ErrorKind::Other, "a is {}", a);
+        io_ensure::ensure_ne!(a, 2, ErrorKind::Other, "a is {}", a);
+        io_ensure::ensure_capacity!(Vec::<u8>::new(), a as usize, ErrorKind::Other, "a is {}", a);
+        io_ensure::ensure_matches!(a, 1 | 2, ErrorKind::Other, "a is {}", a);
+        io_ensure::ensure_gt!(a, 0, ErrorKind::Other, "a is {}", a);
+        if a == 3 {
+            io_ensure::bail!(ErrorKind::Other, "a is {}", a);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn macros_use_crate_paths() {
+        assert!(check(2).is_err());
+    }
+}
+
+#[test]
+fn ensure_matches() {
+    #[allow(dead_code)]
+    #[derive(Debug)]
+    enum Frame {
+        Data(Vec<u8>),
+        Ping,
+        Close(u16),
+    }
+
+    fn check(frame: Option<Frame>) -> io::Result<()> {
+        ensure_matches!(
+            frame,
+            Some(Frame::Data(_)) | Some(Frame::Ping),
+            ErrorKind::InvalidData
+        );
+        Ok(())
+    }
+    assert!(check(Some(Frame::Data(vec![]))).is_ok());
+    assert!(check(Some(Frame::Ping)).is_ok());
+    let err = check(Some(Frame::Close(1000))).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(
+        err.to_string(),
+        "Some(Close(1000)) does not match Some(Frame::Data(_)) | Some(Frame::Ping)"
+    );
+    assert!(check(None).is_err());
+
+    fn guarded(frame: &Frame, max: usize) -> io::Result<()> {
+        ensure_matches!(
+            frame,
+            Frame::Data(payload) if payload.len() <= max,
+            ErrorKind::InvalidData,
+            "unexpected frame {:?}",
+            frame
+        );
+        Ok(())
+    }
+    assert!(guarded(&Frame::Data(vec![1, 2]), 2).is_ok());
+    assert_eq!(
+        guarded(&Frame::Data(vec![1, 2, 3]), 2)
+            .unwrap_err()
+            .to_string(),
+        "unexpected frame Data([1, 2, 3])"
+    );
+    assert_eq!(
+        guarded(&Frame::Ping, 2).unwrap_err().to_string(),
+        "unexpected frame Ping"
+    );
+
+    fn in_range(n: u8) -> io::Result<()> {
+        ensure_matches!(n, 1..=5 if n != 3, ErrorKind::InvalidInput);
+        Ok(())
+    }
+    assert!(in_range(1).is_ok());
+    assert!(in_range(3).is_err());
+    assert_eq!(
+        in_range(9).unwrap_err().to_string(),
+        "9 does not match 1..=5 if n != 3"
+    );
+}
+
+#[test]
+fn ensure_ordering() {
+    fn check(len: usize) -> io::Result<()> {
+        ensure_gt!(len, 2, ErrorKind::UnexpectedEof);
+        ensure_ge!(len, 4, ErrorKind::UnexpectedEof);
+        ensure_lt!(len, 16, ErrorKind::InvalidData);
+        ensure_le!(len, 8, ErrorKind::InvalidData);
+        Ok(())
+    }
+    assert!(check(4).is_ok());
+    assert!(check(8).is_ok());
+
+    let err = check(2).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(
+        err.to_string(),
+        "check `len > 2` failed (left: 2, right: 2)"
+    );
+    assert_eq!(
+        check(3).unwrap_err().to_string(),
+        "check `len >= 4` failed (left: 3, right: 4)"
+    );
+    let err = check(16).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(
+        err.to_string(),
+        "check `len < 16` failed (left: 16, right: 16)"
+    );
+    assert_eq!(
+        check(9).unwrap_err().to_string(),
+        "check `len <= 8` failed (left: 9, right: 8)"
+    );
+
+    fn custom(offset: u64, buf: &[u8]) -> io::Result<()> {
+        ensure_lt!(
+            offset,
+            buf.len() as u64,
+            ErrorKind::InvalidInput,
+            "offset {} is past the end",
+            offset
+        );
+        ensure_ge!(buf, &b"a"[..], ErrorKind::InvalidInput, "bad prefix");
+        ensure_gt!(offset, 0, ErrorKind::InvalidInput, "offset is zero");
+        ensure_le!(
+            buf.len(),
+            4,
+            ErrorKind::InvalidInput,
+            String::from("too long")
+        );
+        Ok(())
+    }
+    assert!(custom(1, b"abc").is_ok());
+    assert_eq!(
+        custom(3, b"abc").unwrap_err().to_string(),
+        "offset 3 is past the end"
+    );
+    assert_eq!(custom(0, b"b").unwrap_err().to_string(), "offset is zero");
+    assert_eq!(custom(1, b"AB").unwrap_err().to_string(), "bad prefix");
+    assert_eq!(custom(1, b"abcde").unwrap_err().to_string(), "too long");
+
+    let mut calls = 0;
+    let mut next = || {
+        calls += 1;
+        calls
+    };
+    let res: io::Result<()> = (|| {
+        ensure_gt!(next(), 1, ErrorKind::Other);
+        Ok(())
+    })();
+    assert!(res.is_err());
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn ensure_let() {
+    enum Frame {
+        Data(u32, Vec<u8>),
+        Ping(u64),
+        Pong(u64),
+    }
+
+    fn payload(frame: Frame) -> io::Result<Vec<u8>> {
+        ensure_let!(Frame::Data(stream, payload) = frame, ErrorKind::InvalidData);
+        assert_eq!(stream, 7);
+        Ok(payload)
+    }
+    assert_eq!(payload(Frame::Data(7, vec![1])).unwrap(), vec![1]);
+    let err = payload(Frame::Ping(0)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(
+        err.to_string(),
+        "frame does not match Frame::Data(stream, payload)"
+    );
+
+    fn nonce(frame: &Frame, expected: u64) -> io::Result<u64> {
+        ensure_let!(
+            Frame::Ping(nonce) | Frame::Pong(nonce) = frame,
+            ErrorKind::InvalidData,
+            "expected a ping or pong, waiting for {}",
+            expected
+        );
+        Ok(*nonce)
+    }
+    assert_eq!(nonce(&Frame::Ping(3), 3).unwrap(), 3);
+    assert_eq!(nonce(&Frame::Pong(4), 4).unwrap(), 4);
+    assert_eq!(
+        nonce(&Frame::Data(0, vec![]), 5).unwrap_err().to_string(),
+        "expected a ping or pong, waiting for 5"
+    );
+}
+
+#[test]
+fn ensure_some() {
+    fn check(entries: &[(&str, u32)], key: &str) -> io::Result<u32> {
+        let entry = ensure_some!(entries.iter().find(|(k, _)| *k == key), ErrorKind::NotFound);
+        Ok(entry.1)
+    }
+    let entries = [("a", 1), ("b", 2)];
+    assert_eq!(check(&entries, "b").unwrap(), 2);
+    let err = check(&entries, "c").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert_eq!(
+        err.to_string(),
+        "entries.iter().find(|(k, _)| *k == key) is None"
+    );
+
+    fn custom(value: Option<String>, key: &str) -> io::Result<String> {
+        Ok(ensure_some!(
+            value,
+            ErrorKind::NotFound,
+            "missing entry {}",
+            key
+        ))
+    }
+    assert_eq!(custom(Some("x".into()), "k").unwrap(), "x");
+    assert_eq!(
+        custom(None, "k").unwrap_err().to_string(),
+        "missing entry k"
+    );
+}
+
+#[test]
+fn ensure_ok() {
+    fn check(header: &str) -> io::Result<u16> {
+        Ok(ensure_ok!(header.parse::<u16>(), ErrorKind::InvalidData))
+    }
+    assert_eq!(check("80").unwrap(), 80);
+    let err = check("x").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "header.parse::<u16>() failed");
+    let source = err.get_ref().and_then(|err| err.source()).unwrap();
+    assert!(source.is::<std::num::ParseIntError>());
+
+    fn custom(bytes: Vec<u8>) -> io::Result<String> {
+        Ok(ensure_ok!(
+            String::from_utf8(bytes),
+            ErrorKind::InvalidData,
+            "bad header"
+        ))
+    }
+    assert_eq!(custom(b"ok".to_vec()).unwrap(), "ok");
+    let err = custom(vec![0xff]).unwrap_err();
+    assert_eq!(err.to_string(), "bad header");
+    let source = err.get_ref().and_then(|err| err.source()).unwrap();
+    assert!(source.is::<std::string::FromUtf8Error>());
+}
+
+#[test]
+fn kindless_forms() {
+    let err = format_err!("plain");
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(err.to_string(), "plain");
+
+    let err = format_err!("offset {} out of range", 12);
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(err.to_string(), "offset 12 out of range");
+
+    let err = format_err!("request {} failed", "abc"; retry = 3);
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(err.to_string(), "request abc failed [retry=3]");
+
+    fn check(len: usize) -> io::Result<()> {
+        ensure!(len > 0, "empty input");
+        ensure!(len < 8, "input of {} bytes is too long", len);
+        Ok(())
+    }
+    assert!(check(4).is_ok());
+    let err = check(0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(err.to_string(), "empty input");
+    assert_eq!(
+        check(9).unwrap_err().to_string(),
+        "input of 9 bytes is too long"
+    );
+
+    fn bails(fail: bool) -> io::Result<()> {
+        if fail {
+            bail!("failed with {}", 1);
+        }
+        err!("failed")
+    }
+    assert_eq!(bails(true).unwrap_err().to_string(), "failed with 1");
+    assert_eq!(bails(false).unwrap_err().kind(), ErrorKind::Other);
+
+    // The explicit-kind forms are unaffected.
+    let err = format_err!(ErrorKind::InvalidData, "count {}", 2);
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(format_err!(ErrorKind::NotFound).kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn option_ext() {
+    assert_eq!(Some(3).ok_or_kind(ErrorKind::NotFound).unwrap(), 3);
+    let err = None::<u8>.ok_or_kind(ErrorKind::NotFound).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert!(err.get_ref().is_none());
+
+    let mut calls = 0;
+    let value = Some("x")
+        .ok_or_err(ErrorKind::InvalidData, || {
+            calls += 1;
+            String::from("unused")
+        })
+        .unwrap();
+    assert_eq!(value, "x");
+    assert_eq!(calls, 0);
+
+    let lens: io::Result<Vec<u8>> = ["1", "2", "x"]
+        .iter()
+        .map(|s| {
+            s.parse()
+                .ok()
+                .ok_or_err(ErrorKind::InvalidData, || format!("bad length {:?}", s))
+        })
+        .collect();
+    let err = lens.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "bad length \"x\"");
+}
+
+#[test]
+fn ensure_eq_messages() {
+    fn eq(a: u8, b: u8) -> io::Result<()> {
+        ensure_eq!(a, b, ErrorKind::InvalidData);
+        Ok(())
+    }
+    assert!(eq(1, 1).is_ok());
+    let err = eq(1, 2).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "check `a == b` failed (left: 1, right: 2)");
+
+    fn ne(a: &str, b: &str) -> io::Result<()> {
+        ensure_ne!(a, b, ErrorKind::InvalidData);
+        Ok(())
+    }
+    assert!(ne("x", "y").is_ok());
+    assert_eq!(
+        ne("x", "x").unwrap_err().to_string(),
+        "check `a != b` failed (left: \"x\", right: \"x\")"
+    );
+
+    fn custom(magic: [u8; 2]) -> io::Result<()> {
+        ensure_eq!(magic, *b"PK", ErrorKind::InvalidData, "bad magic");
+        ensure_ne!(magic[0], 0, ErrorKind::InvalidData, String::from("{owned}"));
+        ensure_eq!(
+            magic.len(),
+            2,
+            ErrorKind::InvalidData,
+            "in {} header",
+            "zip"
+        );
+        Ok(())
+    }
+    assert!(custom(*b"PK").is_ok());
+    assert_eq!(
+        custom(*b"GZ").unwrap_err().to_string(),
+        "check `magic == *b\"PK\"` failed (left: [71, 90], right: [80, 75]): bad magic"
+    );
+}
+
+#[test]
+fn ensure_eq_evaluates_once() {
+    fn check(iter: &mut impl Iterator<Item = u8>) -> io::Result<()> {
+        ensure_eq!(iter.next(), Some(1), ErrorKind::Other);
+        ensure_ne!(iter.next(), Some(1), ErrorKind::Other, "got {}", 1);
+        Ok(())
+    }
+
+    let mut iter = [1, 2, 3].iter().copied();
+    assert!(check(&mut iter).is_ok());
+    assert_eq!(iter.next(), Some(3));
+
+    let mut calls = 0;
+    let mut next = || {
+        calls += 1;
+        calls
+    };
+    let res: io::Result<()> = (|| {
+        ensure_eq!(next(), 2, ErrorKind::Other);
+        Ok(())
+    })();
+    assert!(res.is_err());
+    assert_eq!(calls, 1);
+}
+
+#[derive(Debug)]
+struct Throttled;
+
+impl IntoIoError for Throttled {
+    fn into_io_error(self) -> io::Error {
+        io::Error::new(ErrorKind::WouldBlock, "throttled")
+    }
+}
+
+#[test]
+fn error_location() {
+    fn check(len: usize) -> io::Result<()> {
+        ensure!(len <= 64, "length {} is too large", len);
+        Ok(())
+    }
+    let line = line!() - 3;
+
+    let err = check(100).unwrap_err();
+    assert_eq!(err.to_string(), "length 100 is too large");
+    let payload = downcast_io_err!(err, IoEnsureError).unwrap();
+    assert_eq!(payload.message(), "length 100 is too large");
+    assert_eq!(payload.location().file(), file!());
+    assert_eq!(payload.location().line(), line);
+    assert_eq!(
+        format!("{:#}", err),
+        format!("length 100 is too large at {}", payload.location())
+    );
+    assert!(format!("{:?}", err).contains(&format!("location: {}", payload.location())));
+
+    let (err, line) = (format_err!("kindless"), line!());
+    let payload = downcast_io_err!(err, IoEnsureError).unwrap();
+    assert_eq!(payload.location().line(), line);
+
+    // Errors without a message have no payload to record the location in.
+    assert!(format_err!(ErrorKind::NotFound).get_ref().is_none());
+}
+
+#[test]
+fn error_location_non_literal_messages() {
+    fn line_of(err: &io::Error) -> u32 {
+        let payload = downcast_io_err!(err, IoEnsureError).unwrap();
+        assert_eq!(payload.location().file(), file!());
+        payload.location().line()
+    }
+
+    let message = String::from("owned message");
+    let (err, line) = (format_err!(ErrorKind::Other, message), line!());
+    assert_eq!(err.to_string(), "owned message");
+    assert_eq!(line_of(&err), line);
+    let message = "borrowed".to_string();
+    let (err, line) = (format_err!(ErrorKind::Other, &*message), line!());
+    assert_eq!(line_of(&err), line);
+
+    fn check_let(value: Option<u8>) -> io::Result<u8> {
+        ensure_let!(Some(v) = value, ErrorKind::Other);
+        Ok(v)
+    }
+    let line = line!() - 3;
+    let err = check_let(None).unwrap_err();
+    assert_eq!(err.to_string(), "value does not match Some(v)");
+    assert_eq!(line_of(&err), line);
+
+    fn check_some(value: Option<u8>) -> io::Result<u8> {
+        Ok(ensure_some!(value, ErrorKind::NotFound))
+    }
+    assert_eq!(line_of(&check_some(None).unwrap_err()), line!() - 2);
+
+    let line = line!() + 1;
+    let res = None::<u8>.ok_or_err(ErrorKind::NotFound, || "gone".into());
+    assert_eq!(line_of(&res.unwrap_err()), line);
+
+    let line = line!() + 1;
+    let chain = IoEnsureChain::new().add(false, ErrorKind::Other, "eager");
+    assert_eq!(line_of(&chain.run().unwrap_err()), line);
+    let line = line!() + 1;
+    let chain = IoEnsureChain::new().add_lazy(|| (false, ErrorKind::Other, "lazy"));
+    assert_eq!(line_of(&chain.run().unwrap_err()), line);
+
+    let line = line!() + 1;
+    let mut iter = [0].iter().ensure_all(|n| **n > 0, ErrorKind::Other, "zero");
+    assert_eq!(line_of(&iter.next().unwrap().unwrap_err()), line);
+
+    // Errors given as the payload are kept as is.
+    let err = IoEnsureChain::new()
+        .add(
+            false,
+            ErrorKind::Other,
+            io::Error::from(ErrorKind::NotFound),
+        )
+        .run()
+        .unwrap_err();
+    assert!(downcast_io_err!(err, io::Error).is_some());
+}
+
+#[test]
+fn io_ensure_error() {
+    use std::error::Error;
+
+    fn parse(input: &str) -> io::Result<u8> {
+        let byte = ensure_ok!(input.parse::<u8>(), ErrorKind::InvalidData, "bad");
+        Ok(byte)
+    }
+    let line = line!() - 3;
+
+    let err = parse("300").unwrap_err();
+    let payload = err
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<IoEnsureError>()
+        .unwrap();
+    assert_eq!(payload.kind(), ErrorKind::InvalidData);
+    assert_eq!(payload.message(), "bad");
+    assert_eq!(payload.location().line(), line);
+    assert_eq!(
+        payload.source().unwrap().to_string(),
+        "number too large to fit in target type"
+    );
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        payload.source().unwrap().to_string()
+    );
+
+    let inner = format_err!(ErrorKind::UnexpectedEof, "stream ended");
+    let err = format_err!(ErrorKind::InvalidData, "decoding frame", { frame = 7 }; source = inner);
+    let payload = downcast_io_err!(err, IoEnsureError).unwrap();
+    assert_eq!(payload.fields().get("frame"), Some(&FieldValue::I64(7)));
+    assert!(payload.source().unwrap().is::<io::Error>());
+
+    let (payload, line) = (IoEnsureError::new(ErrorKind::NotFound, "missing"), line!());
+    let err: io::Error = payload.with_source("lookup failed").into();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert_eq!(err.to_string(), "missing");
+    let payload = downcast_io_err!(err, IoEnsureError).unwrap();
+    assert_eq!(payload.location().line(), line);
+    assert!(payload.fields().is_empty());
+
+    // Errors wrapping another error keep it as their payload.
+    let err = format_err!(ErrorKind::Other, io::Error::from(ErrorKind::NotFound));
+    assert!(downcast_io_err!(err, IoEnsureError).is_none());
+}
+
+#[test]
+fn static_messages() {
+    fn payload(err: &io::Error) -> &IoEnsureError {
+        downcast_io_err!(err, IoEnsureError).unwrap()
+    }
+
+    let err = format_err!(ErrorKind::InvalidData, "bad header");
+    assert_eq!(payload(&err).static_message(), Some("bad header"));
+    let err = format_err!("bad header");
+    assert_eq!(payload(&err).static_message(), Some("bad header"));
+    // A literal is not copied when a source is attached either.
+    let err = format_err!(ErrorKind::InvalidData, "bad header"; source = "eof");
+    assert_eq!(payload(&err).static_message(), Some("bad header"));
+
+    fn check(ok: bool) -> io::Result<()> {
+        ensure!(ok, ErrorKind::InvalidInput, "not ok");
+        Ok(())
+    }
+    assert_eq!(
+        payload(&check(false).unwrap_err()).static_message(),
+        Some("not ok")
+    );
+
+    let id = 1;
+    let err = format_err!(ErrorKind::InvalidData, "bad header {}", id);
+    assert_eq!(payload(&err).static_message(), None);
+    assert_eq!(payload(&err).message(), "bad header 1");
+}
+
+#[test]
+fn literal_format_strings() {
+    let len = 4;
+    let err = format_err!(ErrorKind::UnexpectedEof, "expected {len} bytes");
+    assert_eq!(err.to_string(), "expected 4 bytes");
+    let err = format_err!("expected {len:>3} bytes");
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(err.to_string(), "expected   4 bytes");
+
+    let err = format_err!(ErrorKind::InvalidData, "unbalanced {{ in input");
+    assert_eq!(err.to_string(), "unbalanced { in input");
+
+    fn check(len: usize) -> io::Result<()> {
+        ensure!(
+            len == 8,
+            ErrorKind::InvalidData,
+            "expected 8 bytes, got {len}"
+        );
+        Ok(())
+    }
+    assert_eq!(check(3).unwrap_err().to_string(), "expected 8 bytes, got 3");
+
+    let err = format_err!(ErrorKind::InvalidData, "no placeholders");
+    let payload = downcast_io_err!(err, IoEnsureError).unwrap();
+    assert_eq!(payload.static_message(), Some("no placeholders"));
+}
+
+#[test]
+fn literal_format_strings_ensure_eq() {
+    fn check_eq(got: usize, len: usize) -> io::Result<()> {
+        ensure_eq!(
+            got,
+            len,
+            ErrorKind::InvalidData,
+            "expected {len} bytes, {{ok}}"
+        );
+        Ok(())
+    }
+    assert_eq!(
+        check_eq(3, 8).unwrap_err().to_string(),
+        "check `got == len` failed (left: 3, right: 8): expected 8 bytes, {ok}"
+    );
+
+    fn check_ne(got: usize, len: usize) -> io::Result<()> {
+        ensure_ne!(got, len, ErrorKind::InvalidData, "unexpected {len} bytes");
+        Ok(())
+    }
+    assert_eq!(
+        check_ne(8, 8).unwrap_err().to_string(),
+        "check `got != len` failed (left: 8, right: 8): unexpected 8 bytes"
+    );
+
+    // Messages that are not literals are never format strings.
+    let message = String::from("literal {len}");
+    let res: io::Result<()> = (|| {
+        ensure_eq!(1, 2, ErrorKind::Other, message);
+        Ok(())
+    })();
+    assert!(res.unwrap_err().to_string().ends_with(": literal {len}"));
+}
+
+#[cfg(feature = "backtrace")]
+mod backtrace {
+    use io_ensure::*;
+    use std::backtrace::{Backtrace, BacktraceStatus};
+    use std::io::ErrorKind;
+
+    #[test]
+    fn error_backtrace() {
+        let err = format_err!(ErrorKind::Other, "captured");
+        let payload = downcast_io_err!(err, IoEnsureError).unwrap();
+        let expected = Backtrace::capture().status();
+        assert_eq!(payload.backtrace().status(), expected);
+
+        let debug = format!("{:#?}", err);
+        assert!(debug.contains("backtrace"));
+        if expected == BacktraceStatus::Captured {
+            assert!(debug.contains("error_backtrace"));
+        }
+        assert!(!format!("{:?}", err).contains("backtrace"));
+    }
+}
+
+#[test]
+fn debug_ensure() {
+    fn check(len: usize) -> io::Result<()> {
+        debug_ensure!(
+            len.is_multiple_of(2),
+            ErrorKind::InvalidData,
+            "odd length {}",
+            len
+        );
+        debug_ensure_eq!(len % 4, 0, ErrorKind::InvalidData);
+        debug_ensure_ne!(len, 12, ErrorKind::InvalidInput);
+        Ok(())
+    }
+    assert!(check(8).is_ok());
+    if cfg!(debug_assertions) {
+        assert_eq!(check(3).unwrap_err().to_string(), "odd length 3");
+        assert_eq!(check(6).unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(check(12).unwrap_err().kind(), ErrorKind::InvalidInput);
+    } else {
+        assert!(check(3).is_ok());
+        assert!(check(6).is_ok());
+        assert!(check(12).is_ok());
+    }
+}
+
+#[test]
+fn panic_mode() {
+    // The mode is read once per process, so run the check in a child process.
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "panic_mode_child", "--ignored", "--nocapture"])
+        .env("IO_ENSURE_PANIC", "1")
+        .env("RUST_BACKTRACE", "0")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("check failed: length 9 is too large"));
+    assert!(stderr.contains(file!()));
+
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "panic_mode_child", "--ignored"])
+        .env("IO_ENSURE_PANIC", "0")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+}
+
+#[test]
+#[ignore = "run by the panic_mode test"]
+fn panic_mode_child() {
+    fn check(len: usize) -> io::Result<()> {
+        ensure!(
+            len < 8,
+            ErrorKind::InvalidInput,
+            "length {} is too large",
+            len
+        );
+        Ok(())
+    }
+    assert!(check(9).is_err());
+}
+
+#[test]
+fn set_hook() {
+    use std::cell::Cell;
+    use std::sync::Mutex;
+
+    thread_local! {
+        static RECORDING: Cell<bool> = const { Cell::new(false) };
+    }
+    static FAILURES: Mutex<Vec<(String, u32)>> = Mutex::new(Vec::new());
+
+    // Other tests fail checks concurrently, so only record this thread.
+    io_ensure::set_hook(|err, location| {
+        if RECORDING.with(Cell::get) {
+            FAILURES
+                .lock()
+                .unwrap()
+                .push((err.to_string(), location.line()));
+        }
+    });
+    RECORDING.with(|recording| recording.set(true));
+
+    fn check(len: usize) -> io::Result<()> {
+        ensure!(len > 0, ErrorKind::InvalidInput, "empty input");
+        Ok(())
+    }
+    let line = line!() - 3;
+    assert!(check(1).is_ok());
+    assert!(check(0).is_err());
+    // Unconditional errors are not checks.
+    let _: io::Result<()> = err!("not a check");
+    assert_eq!(
+        *FAILURES.lock().unwrap(),
+        [("empty input".to_string(), line)]
+    );
+
+    let hook = io_ensure::take_hook();
+    assert!(check(0).is_err());
+    assert_eq!(FAILURES.lock().unwrap().len(), 1);
+    hook(&format_err!("direct"), std::panic::Location::caller());
+    assert_eq!(FAILURES.lock().unwrap().len(), 2);
+
+    // A hook can replace the hook while it runs.
+    io_ensure::set_hook(|err, location| {
+        if RECORDING.with(Cell::get) {
+            let hook = io_ensure::take_hook();
+            io_ensure::set_hook(hook);
+            FAILURES
+                .lock()
+                .unwrap()
+                .push((err.to_string(), location.line()));
+        }
+    });
+    assert!(check(0).is_err());
+    assert_eq!(FAILURES.lock().unwrap().len(), 3);
+    let _ = io_ensure::take_hook();
+    RECORDING.with(|recording| recording.set(false));
+}
+
+#[test]
+fn into_io_error() {
+    let err = format_err!(Throttled);
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    assert_eq!(err.to_string(), "throttled");
+
+    let err = format_err!(ErrorKind::UnexpectedEof);
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert!(err.get_ref().is_none());
+
+    let err = format_err!(String::from("owned"));
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(err.to_string(), "owned");
+
+    let boxed: Box<dyn std::error::Error + Send + Sync> = "boxed".into();
+    assert_eq!(format_err!(boxed).to_string(), "boxed");
+
+    fn check(ok: bool) -> io::Result<()> {
+        ensure!(ok, Throttled);
+        Ok(())
+    }
+    assert_eq!(check(false).unwrap_err().kind(), ErrorKind::WouldBlock);
+}
+
+#[test]
+fn into_io_error_payload() {
+    use std::error::Error;
+
+    // `Throttled` only implements `IntoIoError`, not `Error`.
+    let (err, line) = (format_err!(ErrorKind::Other, Throttled), line!());
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(err.to_string(), "throttled");
+    let payload = downcast_io_err!(err, IoEnsureError).unwrap();
+    assert_eq!(payload.location().line(), line);
+    let source = payload.source().unwrap();
+    let source = source.downcast_ref::<io::Error>().unwrap();
+    assert_eq!(source.kind(), ErrorKind::WouldBlock);
+
+    fn check(ok: bool) -> io::Result<()> {
+        ensure!(ok, ErrorKind::ResourceBusy, Throttled);
+        Ok(())
+    }
+    let err = check(false).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ResourceBusy);
+    assert_eq!(err.to_string(), "throttled");
+}
+
+#[derive(Debug, PartialEq)]
+struct Corrupt(u32);
+
+impl std::fmt::Display for Corrupt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "corrupt block {}", self.0)
+    }
+}
+
+impl std::error::Error for Corrupt {}
+
+impl IntoIoError for Corrupt {
+    fn into_io_error(self) -> io::Error {
+        io::Error::new(ErrorKind::InvalidData, self)
+    }
+}
+
+#[test]
+fn from_io_error() {
+    let err = format_err!(Corrupt(7));
+    assert_eq!(downcast_io_err!(err, Corrupt), Some(&Corrupt(7)));
+    assert_eq!(Corrupt::from_io_error(err).unwrap(), Corrupt(7));
+
+    let err = format_err!(ErrorKind::InvalidData, "not corrupt");
+    assert_eq!(downcast_io_err!(err, Corrupt), None);
+    let err = Corrupt::from_io_error(err).unwrap_err();
+    assert_eq!(err.to_string(), "not corrupt");
+
+    let err = format_err!(ErrorKind::InvalidData);
+    assert!(downcast_io_err!(err, Corrupt).is_none());
+}
+
+#[test]
+fn format_err_stack() {
+    let err = format_err_stack!(ErrorKind::Other, "offset {} is out of range", 12);
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(err.to_string(), "offset 12 is out of range");
+
+    let long = "x".repeat(300);
+    let msg = format_err_stack!(ErrorKind::Other, "{}{}", long, "tail").to_string();
+    assert_eq!(msg.len(), 256);
+    assert!(msg.ends_with("x..."));
+
+    let wide = "é".repeat(200);
+    let msg = format_err_stack!(ErrorKind::Other, "{}", wide).to_string();
+    assert!(msg.len() <= 256);
+    assert!(msg.ends_with("é..."));
+
+    let msg = format_err_stack!(ErrorKind::Other, "{}", "y".repeat(256)).to_string();
+    assert_eq!(msg, "y".repeat(256));
+}
+
+#[cfg(feature = "tracing")]
+mod tracing_events {
+    use io_ensure::*;
+    use std::io::{self, ErrorKind};
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Level, Metadata, Subscriber};
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Recorded {
+        level: Option<Level>,
+        message: String,
+        condition: String,
+        kind: String,
+    }
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<Recorded>>>);
+
+    impl Visit for Recorded {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            let value = format!("{:?}", value);
+            match field.name() {
+                "message" => self.message = value,
+                "condition" => self.condition = value,
+                "kind" => self.kind = value,
+                _ => {}
+            }
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut recorded = Recorded {
+                level: Some(*event.metadata().level()),
+                ..Recorded::default()
+            };
+            event.record(&mut recorded);
+            self.0.lock().unwrap().push(recorded);
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    fn record(f: impl FnOnce()) -> Vec<Recorded> {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), f);
+        let events = std::mem::take(&mut *recorder.0.lock().unwrap());
+        events
+    }
+
+    #[test]
+    fn io_warn_emits_warning() {
+        let events = record(|| {
+            io_warn!(ErrorKind::Other, "unexpected state: {}", "half-open");
+        });
+        assert_eq!(
+            events,
+            [Recorded {
+                level: Some(Level::WARN),
+                message: "unexpected state: half-open".to_string(),
+                condition: String::new(),
+                kind: "Other".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn ensure_or_warn_emits_warning() {
+        let events = record(|| {
+            let healthy = true;
+            ensure_or_warn!(healthy, ErrorKind::Other, "pool degraded");
+            ensure_or_warn!(!healthy, ErrorKind::Other, "pool degraded");
+        });
+        assert_eq!(
+            events,
+            [Recorded {
+                level: Some(Level::WARN),
+                message: "pool degraded".to_string(),
+                condition: "!healthy".to_string(),
+                kind: "Other".to_string(),
+            }]
+        );
+    }
+
+    #[cfg(feature = "tracing-warn")]
+    #[test]
+    fn ensure_emits_warning() {
+        fn check(n: u8) -> io::Result<()> {
+            ensure!(n > 1, ErrorKind::InvalidInput, "n is {}", n);
+            Ok(())
+        }
+
+        let events = record(|| {
+            assert!(check(2).is_ok());
+            assert!(check(0).is_err());
+        });
+        assert_eq!(
+            events,
+            [Recorded {
+                level: Some(Level::WARN),
+                message: "n is 0".to_string(),
+                condition: "n > 1".to_string(),
+                kind: "InvalidInput".to_string(),
+            }]
+        );
+    }
+}
+
+#[cfg(all(feature = "log", not(feature = "tracing")))]
+mod log_events {
+    use io_ensure::*;
+    use log::{Level, Log, Metadata, Record};
+    use std::io::ErrorKind;
+    use std::sync::Mutex;
+
+    static RECORDS: Mutex<Vec<(Level, String)>> = Mutex::new(Vec::new());
+
+    struct Logger;
+
+    impl Log for Logger {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn log(&self, record: &Record<'_>) {
+            let msg = record.args().to_string();
+            RECORDS.lock().unwrap().push((record.level(), msg));
+        }
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn io_warn_logs_warning() {
+        log::set_logger(&Logger).unwrap();
+        log::set_max_level(log::LevelFilter::Warn);
+        io_warn!(ErrorKind::Other, "unexpected state: {}", "half-open");
+        assert_eq!(
+            *RECORDS.lock().unwrap(),
+            [(Level::Warn, "unexpected state: half-open".to_string())]
+        );
+    }
+}
+
+#[test]
+fn io_warn() {
+    io_warn!(ErrorKind::Other, "unexpected state: {}", 1);
+    io_warn!(ErrorKind::Other, "unexpected state");
+    io_warn!(ErrorKind::Other);
+}
+
+#[test]
+fn ensure_or_warn() {
+    fn check(healthy: bool) -> io::Result<u8> {
+        ensure_or_warn!(healthy, ErrorKind::Other, "degraded: {}", healthy);
+        ensure_or_warn!(healthy, ErrorKind::Other);
+        Ok(1)
+    }
+    assert_eq!(check(true).unwrap(), 1);
+    assert_eq!(check(false).unwrap(), 1);
+}
+
+#[test]
+fn retryable_error() {
+    for kind in [
+        ErrorKind::WouldBlock,
+        ErrorKind::TimedOut,
+        ErrorKind::Interrupted,
+    ] {
+        let err = RetryableError::if_transient(format_err!(kind, "try again")).unwrap();
+        assert_eq!(err.kind(), kind);
+        assert_eq!(err.to_string(), "try again");
+        let err: io::Error = err.into();
+        assert_eq!(err.kind(), kind);
+    }
+
+    assert!(RetryableError::if_transient(format_err!(ErrorKind::NotFound)).is_none());
+
+    let err = RetryableError::from(format_err!(ErrorKind::NotFound));
+    assert_eq!(err.into_inner().kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn ensure_hash_eq() {
+    fn check(computed: &[u8], expected: [u8; 4]) -> io::Result<()> {
+        ensure_hash_eq!(
+            computed,
+            expected,
+            ErrorKind::PermissionDenied,
+            "authentication failed"
+        );
+        Ok(())
+    }
+
+    let expected = [1, 2, 3, 4];
+    assert!(check(&[1, 2, 3, 4], expected).is_ok());
+    let err = check(&[1, 2, 3, 5], expected).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert_eq!(err.to_string(), "authentication failed");
+    assert!(check(&[1, 2, 3], expected).is_err());
+    assert!(check(&[], expected).is_err());
+
+    let res: io::Result<()> = (|| {
+        ensure_hash_eq!(vec![9u8; 32], [9u8; 32], ErrorKind::PermissionDenied);
+        Ok(())
+    })();
+    assert!(res.is_ok());
+}
+
+#[test]
+fn bail() {
+    fn check(state: u8) -> io::Result<u8> {
+        match state {
+            0 => Ok(0),
+            1 => bail!(ErrorKind::NotFound),
+            2 => bail!(ErrorKind::InvalidData, "bad state"),
+            3 => bail!(ErrorKind::InvalidData, String::from("owned")),
+            other => bail!(ErrorKind::InvalidInput, "state {} is out of range", other),
+        }
+    }
+    assert_eq!(check(0).unwrap(), 0);
+    let err = check(1).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert!(err.get_ref().is_none());
+    assert_eq!(check(2).unwrap_err().to_string(), "bad state");
+    assert_eq!(check(3).unwrap_err().to_string(), "owned");
+    let err = check(9).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "state 9 is out of range");
+
+    fn throttled() -> io::Result<()> {
+        bail!(Throttled);
+    }
+    assert_eq!(throttled().unwrap_err().kind(), ErrorKind::WouldBlock);
+}
+
+#[test]
+fn err() {
+    let res: io::Result<u8> = err!(ErrorKind::NotFound);
+    assert_eq!(res.unwrap_err().kind(), ErrorKind::NotFound);
+
+    let res: io::Result<u8> = match "12".parse::<u8>() {
+        Ok(n) if n > 10 => err!(ErrorKind::InvalidInput, "{} is too large", n),
+        Ok(n) => Ok(n),
+        Err(_) => err!(ErrorKind::InvalidData, "not a number"),
+    };
+    let err = res.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "12 is too large");
+
+    let res: io::Result<()> = err!(ErrorKind::Other, "plain");
+    assert_eq!(res.unwrap_err().to_string(), "plain");
+}
+
+#[test]
+fn bail_with() {
+    fn build(fail: bool) -> io::Result<()> {
+        bail_with!(|| {
+            if fail {
+                return Err(format_err!(ErrorKind::OutOfMemory, "report failed"));
+            }
+            Ok(format_err!(ErrorKind::InvalidData, "full report"))
+        })
+    }
+    assert_eq!(build(false).unwrap_err().kind(), ErrorKind::InvalidData);
+    assert_eq!(build(true).unwrap_err().kind(), ErrorKind::OutOfMemory);
+
+    fn simple() -> io::Result<u8> {
+        bail_with!(|| io::Error::new(ErrorKind::InvalidInput, "simple"));
+    }
+    assert_eq!(simple().unwrap_err().to_string(), "simple");
+}
+
+#[test]
+fn from_io_error_return_types() {
+    #[derive(Debug)]
+    enum AppError {
+        Io(io::Error),
+        #[allow(dead_code)]
+        Config(String),
+    }
+
+    impl From<io::Error> for AppError {
+        fn from(err: io::Error) -> Self {
+            AppError::Io(err)
+        }
+    }
+
+    fn load(len: usize) -> Result<usize, AppError> {
+        ensure!(len > 0, ErrorKind::InvalidInput, "empty config");
+        ensure_le!(len, 64, ErrorKind::InvalidInput);
+        let value = ensure_ok!("7".parse::<usize>(), ErrorKind::InvalidData);
+        if len == 13 {
+            bail!(ErrorKind::Other, "unlucky length");
+        }
+        Ok(len + value)
+    }
+    assert_eq!(load(1).unwrap(), 8);
+    match load(0).unwrap_err() {
+        AppError::Io(err) => assert_eq!(err.to_string(), "empty config"),
+        err => panic!("unexpected error {:?}", err),
+    }
+    match load(100).unwrap_err() {
+        AppError::Io(err) => assert_eq!(err.kind(), ErrorKind::InvalidInput),
+        err => panic!("unexpected error {:?}", err),
+    }
+    assert!(matches!(load(13), Err(AppError::Io(_))));
+
+    // Boxed errors, as used by anyhow-style error handling.
+    fn boxed(fail: bool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        ensure!(!fail, ErrorKind::InvalidData, "boxed failure");
+        Ok(())
+    }
+    let err = boxed(true).unwrap_err();
+    assert_eq!(err.to_string(), "boxed failure");
+    assert_eq!(
+        err.downcast_ref::<io::Error>().unwrap().kind(),
+        ErrorKind::InvalidData
+    );
+    assert!(boxed(false).is_ok());
+}
+
+#[test]
+fn poll_ensure() {
+    use std::task::Poll;
+
+    fn poll_read(buf: &mut [u8], data: &[u8]) -> Poll<io::Result<usize>> {
+        poll_ensure!(!buf.is_empty(), ErrorKind::InvalidInput);
+        poll_ensure!(
+            data.len() <= buf.len(),
+            "{} bytes do not fit in {}",
+            data.len(),
+            buf.len()
+        );
+        buf[..data.len()].copy_from_slice(data);
+        Poll::Ready(Ok(data.len()))
+    }
+    let mut buf = [0; 4];
+    assert!(matches!(poll_read(&mut buf, b"ab"), Poll::Ready(Ok(2))));
+    match poll_read(&mut [], b"ab") {
+        Poll::Ready(Err(err)) => assert_eq!(err.kind(), ErrorKind::InvalidInput),
+        poll => panic!("unexpected {:?}", poll),
+    }
+    match poll_read(&mut buf, b"abcdef") {
+        Poll::Ready(Err(err)) => assert_eq!(err.to_string(), "6 bytes do not fit in 4"),
+        poll => panic!("unexpected {:?}", poll),
+    }
+
+    fn poll_twice(first: Poll<io::Result<u8>>) -> Poll<io::Result<u16>> {
+        let value = ensure_ready!(first);
+        poll_ensure!(value != 0, ErrorKind::InvalidData, "zero");
+        Poll::Ready(Ok(u16::from(value) * 2))
+    }
+    assert!(poll_twice(Poll::Pending).is_pending());
+    assert!(matches!(poll_twice(Poll::Ready(Ok(4))), Poll::Ready(Ok(8))));
+    match poll_twice(Poll::Ready(Err(ErrorKind::TimedOut.into()))) {
+        Poll::Ready(Err(err)) => assert_eq!(err.kind(), ErrorKind::TimedOut),
+        poll => panic!("unexpected {:?}", poll),
+    }
+    match poll_twice(Poll::Ready(Ok(0))) {
+        Poll::Ready(Err(err)) => assert_eq!(err.to_string(), "zero"),
+        poll => panic!("unexpected {:?}", poll),
+    }
+}
+
+#[test]
+fn ensure_next() {
+    use std::task::Poll;
+
+    fn poll_next(items: &mut Vec<i32>) -> Poll<Option<io::Result<i32>>> {
+        let item = match items.pop() {
+            Some(item) => item,
+            None => return Poll::Ready(None),
+        };
+        ensure_next!(item >= 0, ErrorKind::InvalidData, "negative item {}", item);
+        if item == 0 {
+            bail_next!(ErrorKind::UnexpectedEof);
+        }
+        Poll::Ready(Some(Ok(item)))
+    }
+    let mut items = vec![0, -3, 5];
+    assert!(matches!(poll_next(&mut items), Poll::Ready(Some(Ok(5)))));
+    match poll_next(&mut items) {
+        Poll::Ready(Some(Err(err))) => {
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+            assert_eq!(err.to_string(), "negative item -3");
+        }
+        poll => panic!("unexpected {:?}", poll),
+    }
+    match poll_next(&mut items) {
+        Poll::Ready(Some(Err(err))) => assert_eq!(err.kind(), ErrorKind::UnexpectedEof),
+        poll => panic!("unexpected {:?}", poll),
+    }
+    assert!(matches!(poll_next(&mut items), Poll::Ready(None)));
+}
+
+#[test]
+fn ensure_in() {
+    use std::ops::ControlFlow;
+    use std::task::Poll;
+
+    fn result(ok: bool) -> io::Result<u8> {
+        ensure_in!(ok, ErrorKind::InvalidInput);
+        Ok(1)
+    }
+    assert_eq!(result(true).unwrap(), 1);
+    assert_eq!(result(false).unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    fn poll(ok: bool) -> Poll<io::Result<u8>> {
+        ensure_in!(ok, "not ready: {}", ok);
+        Poll::Ready(Ok(1))
+    }
+    match poll(false) {
+        Poll::Ready(Err(err)) => assert_eq!(err.to_string(), "not ready: false"),
+        poll => panic!("unexpected {:?}", poll),
+    }
+
+    fn poll_next(ok: bool) -> Poll<Option<Result<u8, Box<dyn std::error::Error>>>> {
+        ensure_in!(ok, ErrorKind::InvalidData, "bad item");
+        Poll::Ready(None)
+    }
+    assert!(matches!(poll_next(true), Poll::Ready(None)));
+    assert!(matches!(poll_next(false), Poll::Ready(Some(Err(_)))));
+
+    fn control(len: usize) -> ControlFlow<io::Error, usize> {
+        ensure_in!(
+            len < 4,
+            ErrorKind::InvalidData,
+            "length {} is too large",
+            len
+        );
+        ControlFlow::Continue(len)
+    }
+    assert!(matches!(control(2), ControlFlow::Continue(2)));
+    match control(9) {
+        ControlFlow::Break(err) => assert_eq!(err.to_string(), "length 9 is too large"),
+        flow => panic!("unexpected {:?}", flow),
+    }
+
+    // A user-defined output type.
+    #[derive(Debug, PartialEq)]
+    enum Parsed {
+        Done(u8),
+        Failed(ErrorKind),
+    }
+    impl FromIoEnsureError for Parsed {
+        fn from_io_ensure_error(err: io::Error) -> Self {
+            Parsed::Failed(err.kind())
+        }
+    }
+    fn parse(byte: u8) -> Parsed {
+        ensure_in!(byte.is_ascii_digit(), ErrorKind::InvalidData);
+        Parsed::Done(byte - b'0')
+    }
+    assert_eq!(parse(b'7'), Parsed::Done(7));
+    assert_eq!(parse(b'x'), Parsed::Failed(ErrorKind::InvalidData));
+}
+
+#[test]
+fn ensure_then() {
+    fn check(valid: bool, released: &mut bool) -> io::Result<()> {
+        ensure_then!(valid, ErrorKind::Other, || *released = true);
+        Ok(())
+    }
+    let mut released = false;
+    assert!(check(true, &mut released).is_ok());
+    assert!(!released);
+    assert!(check(false, &mut released).is_err());
+    assert!(released);
+
+    fn rewrite(n: u8) -> io::Result<()> {
+        ensure_then!(
+            n > 0,
+            ErrorKind::Other,
+            "n is {}",
+            n,
+            |err: &mut io::Error| {
+                assert_eq!(err.to_string(), "n is 0");
+                *err = format_err!(ErrorKind::InvalidInput, "rewritten");
+            }
+        );
+        Ok(())
+    }
+    let err = rewrite(0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "rewritten");
+
+    let mut calls = 0;
+    let res: io::Result<()> = (|| {
+        ensure_then!(false, ErrorKind::Other, "lock invalidated", || calls += 1);
+        Ok(())
+    })();
+    assert_eq!(res.unwrap_err().to_string(), "lock invalidated");
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn ensure_with() {
+    fn check(valid: bool, calls: &mut u32) -> io::Result<()> {
+        ensure_with!(valid, ErrorKind::InvalidData, || {
+            *calls += 1;
+            "invalid frame"
+        });
+        Ok(())
+    }
+    let mut calls = 0;
+    assert!(check(true, &mut calls).is_ok());
+    assert_eq!(calls, 0);
+    let err = check(false, &mut calls).unwrap_err();
+    assert_eq!(calls, 1);
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "invalid frame");
+    assert!(downcast_io_err!(err, IoEnsureError).is_some());
+
+    let id = 7;
+    let err = format_err_with!(ErrorKind::NotFound, || format!("no stream {}", id));
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert_eq!(err.to_string(), "no stream 7");
+}
+
+#[test]
+fn ensure_all_of() {
+    fn check(val: i32) -> io::Result<()> {
+        ensure_all_of!([val > 0, val % 2 == 0, val < 100], ErrorKind::InvalidData);
+        Ok(())
+    }
+    assert!(check(4).is_ok());
+    let err = check(3).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "condition 1 failed: val % 2 == 0");
+    assert_eq!(
+        check(-2).unwrap_err().to_string(),
+        "condition 0 failed: val > 0"
+    );
+
+    fn labeled(val: i32) -> io::Result<()> {
+        ensure_all_of!(["must be positive" => val > 0, "must be even" => val % 2 == 0], ErrorKind::InvalidData);
+        Ok(())
+    }
+    assert!(labeled(2).is_ok());
+    assert_eq!(labeled(-1).unwrap_err().to_string(), "must be positive");
+    assert_eq!(labeled(1).unwrap_err().to_string(), "must be even");
+
+    fn with_message(val: i32) -> io::Result<()> {
+        ensure_all_of!(
+            [val > 0, val % 2 == 0],
+            ErrorKind::InvalidData,
+            "bad value {}",
+            val
+        );
+        Ok(())
+    }
+    assert_eq!(with_message(1).unwrap_err().to_string(), "bad value 1");
+
+    let mut evaluated = 0;
+    let mut probe = |result| {
+        evaluated += 1;
+        result
+    };
+    let res: io::Result<()> = (|| {
+        ensure_all_of!([probe(true), probe(false), probe(true)], ErrorKind::Other);
+        Ok(())
+    })();
+    assert!(res.is_err());
+    assert_eq!(evaluated, 2);
+}
+
+#[test]
+fn ensure_any_of() {
+    fn check(conds: [bool; 3]) -> io::Result<()> {
+        ensure_any_of!([conds[0], conds[1], conds[2]], ErrorKind::PermissionDenied);
+        Ok(())
+    }
+    let err = check([false, false, false]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert_eq!(err.to_string(), "all 3 conditions failed");
+    assert!(check([true, false, false]).is_ok());
+    assert!(check([false, false, true]).is_ok());
+    assert!(check([true, true, true]).is_ok());
+
+    let mut evaluated = 0;
+    let mut probe = |result| {
+        evaluated += 1;
+        result
+    };
+    let res: io::Result<()> = (|| {
+        ensure_any_of!(
+            [probe(false), probe(true), probe(false)],
+            ErrorKind::Other,
+            "denied"
+        );
+        Ok(())
+    })();
+    assert!(res.is_ok());
+    assert_eq!(evaluated, 2);
+}
+
+#[test]
+fn normalize_io_err() {
+    fn normalize(err: io::Error) -> io::Error {
+        normalize_io_err!(err,
+            ErrorKind::TimedOut => ErrorKind::WouldBlock,
+            ErrorKind::Interrupted | ErrorKind::ConnectionReset => ErrorKind::BrokenPipe,
+        )
+    }
+
+    let (err, line) = (format_err!(ErrorKind::TimedOut, "read timed out"), line!());
+    let err = normalize(err);
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    assert_eq!(err.to_string(), "read timed out");
+    // The payload is given the new kind, and keeps its location.
+    let payload = downcast_io_err!(err, IoEnsureError).unwrap();
+    assert_eq!(payload.kind(), ErrorKind::WouldBlock);
+    assert_eq!(payload.location().line(), line);
+
+    let err = normalize(format_err!(ErrorKind::ConnectionReset));
+    assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+
+    let err = normalize(format_err!(Corrupt(3)));
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(downcast_io_err!(err, Corrupt), Some(&Corrupt(3)));
+
+    let err = normalize(format_err!(ErrorKind::Interrupted, Corrupt(4)));
+    assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+    assert_eq!(downcast_io_err!(err, Corrupt), Some(&Corrupt(4)));
+
+    let os = io::Error::from_raw_os_error(2);
+    let msg = os.to_string();
+    let err = normalize_io_err!(os, ErrorKind::NotFound => ErrorKind::Other);
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(err.to_string(), msg);
+}
+
+#[test]
+fn io_error_ext() {
+    type Predicate = fn(&io::Error) -> bool;
+    let cases: &[(ErrorKind, Predicate)] = &[
+        (ErrorKind::NotFound, |e| e.is_not_found()),
+        (ErrorKind::PermissionDenied, |e| e.is_permission_denied()),
+        (ErrorKind::WouldBlock, |e| e.is_would_block()),
+        (ErrorKind::TimedOut, |e| e.is_timed_out()),
+        (ErrorKind::BrokenPipe, |e| e.is_broken_pipe()),
+        (ErrorKind::ConnectionRefused, |e| e.is_connection_refused()),
+        (ErrorKind::AlreadyExists, |e| e.is_already_exists()),
+        (ErrorKind::UnexpectedEof, |e| e.is_unexpected_eof()),
+        (ErrorKind::Interrupted, |e| e.is_interrupted()),
+    ];
+    for (kind, predicate) in cases {
+        assert!(predicate(&format_err!(*kind)));
+        assert!(!predicate(&format_err!(ErrorKind::Other)));
+    }
+
+    let res: io::Result<()> = Err(format_err!(ErrorKind::TimedOut));
+    assert!(res.is_timed_out());
+    assert!(!res.is_not_found());
+    let res: io::Result<()> = Ok(());
+    assert!(!res.is_timed_out());
+}
+
+#[test]
+fn io_error_category() {
+    let transient = [
+        ErrorKind::WouldBlock,
+        ErrorKind::TimedOut,
+        ErrorKind::Interrupted,
+    ];
+    for kind in transient {
+        assert_eq!(IoErrorCategory::from(kind), IoErrorCategory::Transient);
+    }
+    let permanent = [
+        ErrorKind::NotFound,
+        ErrorKind::PermissionDenied,
+        ErrorKind::AlreadyExists,
+        ErrorKind::InvalidInput,
+        ErrorKind::InvalidData,
+        ErrorKind::BrokenPipe,
+        ErrorKind::ConnectionReset,
+        ErrorKind::ConnectionAborted,
+    ];
+    for kind in permanent {
+        assert_eq!(IoErrorCategory::from(kind), IoErrorCategory::Permanent);
+    }
+    assert_eq!(
+        IoErrorCategory::from(ErrorKind::Other),
+        IoErrorCategory::Unknown
+    );
+    assert_eq!(
+        IoErrorCategory::from(ErrorKind::OutOfMemory),
+        IoErrorCategory::Unknown
+    );
+
+    let err = format_err!(ErrorKind::TimedOut, "slow");
+    assert_eq!(err.category(), IoErrorCategory::Transient);
+    assert_eq!(IoErrorCategory::from(err), IoErrorCategory::Transient);
+}
+
+#[test]
+fn categorize_io_err() {
+    fn action(err: &io::Error) -> &'static str {
+        match categorize_io_err!(err) {
+            IoErrorCategory::Transient => "retry",
+            IoErrorCategory::Permanent => "fail",
+            IoErrorCategory::Unknown => "log",
+        }
+    }
+    assert_eq!(action(&format_err!(ErrorKind::Interrupted)), "retry");
+    assert_eq!(action(&format_err!(ErrorKind::NotFound)), "fail");
+    assert_eq!(action(&format_err!(ErrorKind::Other, "?")), "log");
+
+    let err = format_err!(ErrorKind::TimedOut);
+    assert_eq!(categorize_io_err!(err), IoErrorCategory::Transient);
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert_eq!(
+        categorize_io_err!(ErrorKind::BrokenPipe),
+        IoErrorCategory::Permanent
+    );
+}
+
+#[test]
+fn error_chain() {
+    let chain = ErrorChain::new(format_err!(ErrorKind::Other, "handling request"))
+        .chain(format_err!(ErrorKind::InvalidData, "parsing body"))
+        .chain(format_err!(ErrorKind::UnexpectedEof));
+    let kinds: Vec<_> = chain.iter().map(io::Error::kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            ErrorKind::Other,
+            ErrorKind::InvalidData,
+            ErrorKind::UnexpectedEof
+        ]
+    );
+    assert_eq!(chain.head().to_string(), "handling request");
+
+    let err: io::Error = chain.into();
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "handling request: parsing body: {}",
+            ErrorKind::UnexpectedEof
+        )
+    );
+    assert_eq!(downcast_io_err!(err, ErrorChain).unwrap().iter().count(), 3);
+
+    // Each source is the rest of the chain, headed by the next error.
+    let mut heads = vec![];
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&err);
+    while let Some(next) = source {
+        if let Some(chain) = next.downcast_ref::<ErrorChain>() {
+            heads.push(chain.head().kind());
+        }
+        source = next.source();
+    }
+    assert_eq!(heads, [ErrorKind::InvalidData, ErrorKind::UnexpectedEof]);
+    let source = std::error::Error::source(&err).unwrap();
+    assert_eq!(
+        source.to_string(),
+        format!("parsing body: {}", ErrorKind::UnexpectedEof)
+    );
+}
+
+#[test]
+fn ensure_all_ext() {
+    let mut inner = vec![1, 2, -3, 4, 5].into_iter();
+    let mut iter = inner
+        .by_ref()
+        .ensure_all(|n| *n > 0, ErrorKind::InvalidData, "negative value");
+    assert_eq!(iter.next().unwrap().unwrap(), 1);
+    assert_eq!(iter.next().unwrap().unwrap(), 2);
+    let err = iter.next().unwrap().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "negative value: element 2 is -3");
+    assert!(iter.next().is_none());
+    assert_eq!(inner.next(), Some(4));
+
+    let all: io::Result<Vec<_>> = (0..4)
+        .ensure_all(|n| *n < 4, ErrorKind::InvalidData, "too large")
+        .collect();
+    assert_eq!(all.unwrap(), [0, 1, 2, 3]);
+}
+
+#[cfg(feature = "regex")]
+mod regex {
+    use io_ensure::*;
+    use std::io::{self, ErrorKind};
+
+    fn date(input: &str) -> io::Result<()> {
+        ensure_regex!(input, r"^\d{4}-\d{2}-\d{2}$", ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_regex() {
+        assert!(date("2020-01-31").is_ok());
+        assert!(date("2020-01-31").is_ok());
+        let err = date("31/01/2020").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(
+            err.to_string(),
+            r#""31/01/2020" does not match pattern "^\\d{4}-\\d{2}-\\d{2}$""#
+        );
+
+        let res: io::Result<()> = (|| {
+            ensure_regex!(
+                String::from("abc"),
+                "^[a-z]+$",
+                ErrorKind::InvalidInput,
+                "bad"
+            );
+            ensure_regex!("ABC", "^[a-z]+$", ErrorKind::InvalidInput, "bad {}", "case");
+            Ok(())
+        })();
+        assert_eq!(res.unwrap_err().to_string(), "bad case");
+    }
+
+    // Clippy's `invalid_regex` lint already rejects this at compile time.
+    #[test]
+    #[allow(clippy::invalid_regex)]
+    #[should_panic(expected = "invalid regex")]
+    fn ensure_regex_invalid_pattern() {
+        let _ = (|| -> io::Result<()> {
+            ensure_regex!("x", "(unclosed", ErrorKind::InvalidInput);
+            Ok(())
+        })();
+    }
+}
+
+#[cfg(feature = "glob")]
+mod glob {
+    use io_ensure::*;
+    use std::io::{self, ErrorKind};
+    use std::path::Path;
+
+    fn json(path: impl AsRef<Path>) -> io::Result<()> {
+        ensure_glob!(path, "*.json", ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_glob() {
+        assert!(json("config.json").is_ok());
+        assert!(json(Path::new("nested/config.json")).is_ok());
+        let err = json("config.yaml").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(
+            err.to_string(),
+            r#"config.yaml does not match pattern "*.json""#
+        );
+        assert!(json("CONFIG.JSON").is_err());
+
+        let res: io::Result<()> = (|| {
+            let filename = "notes.txt";
+            ensure_glob!(
+                filename,
+                "*.[jy]*",
+                ErrorKind::InvalidInput,
+                "expected JSON or YAML file: {}",
+                filename
+            );
+            Ok(())
+        })();
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "expected JSON or YAML file: notes.txt"
+        );
+    }
+}
+
+#[test]
+fn batch_ensure() {
+    fn validate(a: u8, b: u8, c: i8) -> io::Result<()> {
+        batch_ensure! {
+            check!(a == b, ErrorKind::InvalidInput, "a != b");
+            check!(c > 0, ErrorKind::PermissionDenied, "c is {}", c);
+            check!(c < 100, ErrorKind::InvalidData)
+        }
+    }
+
+    assert!(validate(1, 1, 1).is_ok());
+
+    let err = validate(1, 2, 1).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "1 check failed: a != b");
+
+    let err = validate(1, 2, -1).unwrap_err();
+    assert_eq!(err.to_string(), "2 checks failed: a != b; c is -1");
+    let multi = downcast_io_err!(err, MultiError).unwrap();
+    let kinds: Vec<_> = multi.errors().iter().map(io::Error::kind).collect();
+    assert_eq!(
+        kinds,
+        [ErrorKind::InvalidInput, ErrorKind::PermissionDenied]
+    );
+}
+
+#[test]
+fn ensure_monotonic() {
+    fn check(values: &[i32]) -> io::Result<()> {
+        ensure_monotonic!(values.iter(), ErrorKind::InvalidData);
+        Ok(())
+    }
+    fn check_strict(values: &[i32]) -> io::Result<()> {
+        ensure_strictly_monotonic!(values, ErrorKind::InvalidData, "not strictly increasing");
+        Ok(())
+    }
+
+    assert!(check(&[]).is_ok());
+    assert!(check(&[7]).is_ok());
+    assert!(check_strict(&[7]).is_ok());
+    assert!(check(&[1, 2, 2, 3]).is_ok());
+    assert!(check_strict(&[1, 2, 3]).is_ok());
+    assert_eq!(
+        check_strict(&[1, 2, 2, 3]).unwrap_err().to_string(),
+        "not strictly increasing"
+    );
+
+    let err = check(&[1, 5, 3]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "elements 1 and 2 are out of order: 5, 3");
+}
+
+#[test]
+fn ensure_monotonic_decreasing() {
+    fn check(values: &[i32]) -> io::Result<()> {
+        ensure_monotonic_decreasing!(values.iter(), ErrorKind::InvalidData);
+        Ok(())
+    }
+    fn check_strict(values: Vec<f64>) -> io::Result<()> {
+        ensure_strictly_monotonic_decreasing!(
+            values,
+            ErrorKind::InvalidData,
+            "not strictly decreasing"
+        );
+        Ok(())
+    }
+
+    assert!(check(&[]).is_ok());
+    assert!(check(&[9, 7, 2]).is_ok());
+    assert!(check(&[4, 4, 4]).is_ok());
+    assert!(check_strict(vec![9.0, 7.5, 2.0]).is_ok());
+    assert_eq!(
+        check_strict(vec![4.0, 4.0]).unwrap_err().to_string(),
+        "not strictly decreasing"
+    );
+
+    let err = check(&[5, 3, 4]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "elements 1 and 2 are out of order: 3, 4");
+    assert!(check(&[1, 2, 3]).is_err());
+    assert!(check_strict(vec![1.0, 2.0]).is_err());
+}
+
+#[test]
+fn ensure_window() {
+    fn check(data: &[i64]) -> io::Result<()> {
+        ensure_window!(
+            data.iter().copied(),
+            3,
+            |window: &[i64]| window.iter().sum::<i64>() >= 0,
+            ErrorKind::InvalidData
+        );
+        Ok(())
+    }
+    assert!(check(&[]).is_ok());
+    assert!(check(&[-5, -5]).is_ok());
+    assert!(check(&[3, -1, 2, -1, 5, 1]).is_ok());
+
+    let err = check(&[-1, -1, 1, 5, 5]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(
+        err.to_string(),
+        "window of 3 elements at index 0 failed the check"
+    );
+    assert_eq!(
+        check(&[5, 1, -4, -3, 9, 9]).unwrap_err().to_string(),
+        "window of 3 elements at index 1 failed the check"
+    );
+    assert_eq!(
+        check(&[1, 1, 1, 1, -1, -2]).unwrap_err().to_string(),
+        "window of 3 elements at index 3 failed the check"
+    );
+
+    fn custom(seqs: Vec<u32>) -> io::Result<()> {
+        ensure_window!(
+            seqs,
+            2,
+            |pair: &[u32]| pair[1] == pair[0] + 1,
+            ErrorKind::InvalidData,
+            "gap in sequence numbers"
+        );
+        Ok(())
+    }
+    assert!(custom(vec![4, 5, 6]).is_ok());
+    assert_eq!(
+        custom(vec![4, 5, 7]).unwrap_err().to_string(),
+        "gap in sequence numbers"
+    );
+
+    fn sized(data: &[i64], size: usize) -> io::Result<()> {
+        ensure_window!(
+            data.iter().copied(),
+            size,
+            |window: &[i64]| window.iter().sum::<i64>() >= 0,
+            ErrorKind::InvalidData
+        );
+        Ok(())
+    }
+    assert!(sized(&[1, -1, 2], 2).is_ok());
+    let err = sized(&[1, -1, 2], 0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "window size must be non-zero");
+}
+
+#[test]
+fn io_error_metadata() {
+    let err = IoErrorMetadata::new(format_err!(ErrorKind::TimedOut, "upstream timed out"))
+        .with("request_id", "abc-123")
+        .with("attempt", 3)
+        .wrap();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert_eq!(
+        err.to_string(),
+        "upstream timed out [request_id=abc-123, attempt=3]"
+    );
+
+    let metadata = downcast_io_err!(err, IoErrorMetadata).unwrap();
+    assert_eq!(metadata.get("request_id"), Some("abc-123"));
+    assert_eq!(metadata.get("attempt"), Some("3"));
+    assert_eq!(metadata.get("missing"), None);
+    assert_eq!(metadata.pairs().len(), 2);
+    assert_eq!(metadata.error().to_string(), "upstream timed out");
+
+    let err: io::Error = IoErrorMetadata::new(format_err!(ErrorKind::NotFound, "gone")).into();
+    assert_eq!(err.to_string(), "gone");
+}
+
+#[test]
+fn ensure_path_component_count() {
+    use std::path::{Path, PathBuf};
+
+    fn check(path: impl AsRef<Path>, max: usize) -> io::Result<()> {
+        ensure_path_component_count!(path, max, ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    assert!(check("a/b/c", 3).is_ok());
+    assert!(check(PathBuf::from("a/b/c"), 2).is_err());
+    // `..` components still count towards the limit.
+    assert!(check("a/../b", 2).is_err());
+    assert!(check("a/../b", 3).is_ok());
+
+    #[cfg(unix)]
+    {
+        // The root is a component of its own.
+        assert!(check("/etc/passwd", 3).is_ok());
+        let err = check("/etc/passwd", 2).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(
+            err.to_string(),
+            "path /etc/passwd has 3 components, maximum is 2"
+        );
+    }
+    #[cfg(windows)]
+    {
+        // The prefix and the root are separate components.
+        assert!(check(r"C:\Windows\System32", 4).is_ok());
+        assert!(check(r"C:\Windows\System32", 3).is_err());
+    }
+
+    fn custom(path: &Path) -> io::Result<()> {
+        ensure_path_component_count!(
+            path,
+            1,
+            ErrorKind::InvalidInput,
+            "path {} is too deep",
+            path.display()
+        );
+        Ok(())
+    }
+    let err = custom(Path::new("a/b")).unwrap_err();
+    assert_eq!(err.to_string(), "path a/b is too deep");
+}
+
+#[cfg(unix)]
+#[test]
+fn ensure_unix_mode() {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    fn check(path: &Path, mode: u32) -> io::Result<()> {
+        ensure_unix_mode!(path, mode, ErrorKind::PermissionDenied);
+        Ok(())
+    }
+
+    let path = std::env::temp_dir().join(format!("io-ensure-mode-{}", std::process::id()));
+    fs::write(&path, b"secret").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+    assert!(check(&path, 0o600).is_ok());
+    let err = check(&path, 0o644).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert_eq!(err.to_string(), "expected mode 0o644, got 0o600");
+
+    fn custom(path: &Path) -> io::Result<()> {
+        ensure_unix_mode!(
+            path,
+            0o400,
+            ErrorKind::PermissionDenied,
+            "key must be read-only"
+        );
+        Ok(())
+    }
+    assert_eq!(
+        custom(&path).unwrap_err().to_string(),
+        "key must be read-only"
+    );
+
+    fs::remove_file(&path).unwrap();
+    assert_eq!(check(&path, 0o600).unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn ensure_magic_bytes() {
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    fn check(data: &[u8]) -> io::Result<()> {
+        ensure_magic_bytes!(data, PNG, ErrorKind::InvalidData);
+        Ok(())
+    }
+
+    assert!(check(PNG).is_ok());
+    assert!(check(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").is_ok());
+
+    let err = check(b"GIF89a\0\0").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(
+        err.to_string(),
+        "expected magic bytes 89 50 4e 47 0d 0a 1a 0a, found 47 49 46 38 39 61 00 00"
+    );
+
+    let err = check(b"\x89PN").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected magic bytes 89 50 4e 47 0d 0a 1a 0a, found 89 50 4e"
+    );
+
+    fn custom(data: Vec<u8>) -> io::Result<()> {
+        ensure_magic_bytes!(
+            data,
+            b"PK\x03\x04",
+            ErrorKind::InvalidData,
+            "not a zip archive"
+        );
+        Ok(())
+    }
+    assert!(custom(b"PK\x03\x04rest".to_vec()).is_ok());
+    assert_eq!(
+        custom(Vec::new()).unwrap_err().to_string(),
+        "not a zip archive"
+    );
+}
+
+#[test]
+fn ensure_ip_valid() {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    fn ip(input: &str) -> io::Result<IpAddr> {
+        Ok(ensure_ip_valid!(input, ErrorKind::InvalidInput))
+    }
+    fn v4(input: String) -> io::Result<Ipv4Addr> {
+        Ok(ensure_ipv4_valid!(input, ErrorKind::InvalidInput))
+    }
+    fn v6(input: &str) -> io::Result<Ipv6Addr> {
+        Ok(ensure_ipv6_valid!(
+            input,
+            ErrorKind::InvalidInput,
+            "bad address: {}",
+            input
+        ))
+    }
+
+    assert_eq!(ip("127.0.0.1").unwrap(), IpAddr::from([127, 0, 0, 1]));
+    assert_eq!(ip("::1").unwrap(), IpAddr::from(Ipv6Addr::LOCALHOST));
+    let err = ip("256.0.0.1").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "\"256.0.0.1\" is not a valid IP address");
+    assert!(err
+        .get_ref()
+        .unwrap()
+        .source()
+        .unwrap()
+        .is::<std::net::AddrParseError>());
+
+    assert_eq!(v4("10.1.2.3".into()).unwrap(), Ipv4Addr::new(10, 1, 2, 3));
+    let err = v4("::1".into()).unwrap_err();
+    assert_eq!(err.to_string(), "\"::1\" is not a valid IPv4 address");
+
+    assert_eq!(
+        v6("fe80::1").unwrap(),
+        "fe80::1".parse::<Ipv6Addr>().unwrap()
+    );
+    let err = v6("10.0.0.1").unwrap_err();
+    assert_eq!(err.to_string(), "bad address: 10.0.0.1");
+    assert!(err.get_ref().unwrap().source().is_some());
+}
+
+#[cfg(feature = "serde_json")]
+mod json {
+    use io_ensure::*;
+    use std::io::{self, ErrorKind};
+
+    fn value(payload: &[u8]) -> io::Result<serde_json::Value> {
+        Ok(ensure_json_valid!(payload, ErrorKind::InvalidData))
+    }
+
+    #[test]
+    fn valid() {
+        let value = value(br#"{"id": 7, "tags": ["a"]}"#).unwrap();
+        assert_eq!(value["id"], 7);
+        assert!(self::value(b"null").is_ok());
+    }
+
+    #[test]
+    fn invalid() {
+        let err = value(b"{\"id\": ").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "payload is not valid JSON");
+        let source = err.get_ref().unwrap().source().unwrap();
+        assert!(source.is::<serde_json::Error>());
+
+        assert!(value(b"").is_err());
+    }
+
+    #[test]
+    fn custom_message() {
+        fn check(payload: Vec<u8>) -> io::Result<()> {
+            ensure_json_valid!(
+                payload,
+                ErrorKind::InvalidData,
+                "request body is not valid JSON"
+            );
+            Ok(())
+        }
+        let err = check(b"nope".to_vec()).unwrap_err();
+        assert_eq!(err.to_string(), "request body is not valid JSON");
+    }
+
+    #[test]
+    fn typed() {
+        fn ports(payload: &str) -> io::Result<Vec<u16>> {
+            Ok(ensure_json_valid_as!(
+                payload,
+                Vec<u16>,
+                ErrorKind::InvalidData
+            ))
+        }
+        assert_eq!(ports("[80, 443]").unwrap(), vec![80, 443]);
+        let err = ports("[80, 70000]").unwrap_err();
+        let message = err.to_string();
+        assert!(message.starts_with("payload is not valid JSON for "));
+        assert!(message.ends_with("Vec<u16>"));
+    }
+}
+
+#[cfg(feature = "url")]
+mod url {
+    use io_ensure::*;
+    use std::io::{self, ErrorKind};
+
+    fn any(input: &str) -> io::Result<::url::Url> {
+        Ok(ensure_url_valid!(input, ErrorKind::InvalidInput))
+    }
+
+    #[test]
+    fn absolute() {
+        let url = any("https://example.com:8443/a?b=c").unwrap();
+        assert_eq!(url.port(), Some(8443));
+        assert!(any("mailto:ferris@example.com").is_ok());
+    }
+
+    #[test]
+    fn relative_and_invalid() {
+        let err = any("/relative/path").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), "\"/relative/path\" is not a valid URL");
+        let source = err.get_ref().unwrap().source().unwrap();
+        assert!(source.is::<::url::ParseError>());
+
+        assert!(any("").is_err());
+        assert!(any("http://").is_err());
+        assert!(any("https://exa mple.com").is_err());
+    }
+
+    #[test]
+    fn custom_message() {
+        fn check(input: String) -> io::Result<()> {
+            ensure_url_valid!(input, ErrorKind::InvalidInput, "invalid URL: {}", input);
+            Ok(())
+        }
+        assert_eq!(
+            check("nope".into()).unwrap_err().to_string(),
+            "invalid URL: nope"
+        );
+    }
+
+    #[test]
+    fn scheme() {
+        fn https(input: &str) -> io::Result<::url::Url> {
+            Ok(ensure_https_url!(input, ErrorKind::InvalidInput))
+        }
+        fn http(input: &str) -> io::Result<::url::Url> {
+            Ok(ensure_http_url!(
+                input,
+                ErrorKind::InvalidInput,
+                "not a web URL"
+            ))
+        }
+
+        assert!(https("https://example.com").is_ok());
+        let err = https("http://example.com").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "\"http://example.com\" is not an https URL"
+        );
+        assert!(err.get_ref().unwrap().source().is_none());
+        assert_eq!(
+            https("example.com").unwrap_err().to_string(),
+            "\"example.com\" is not a valid URL"
+        );
+
+        assert!(http("http://example.com").is_ok());
+        assert!(http("https://example.com").is_ok());
+        assert_eq!(
+            http("ftp://example.com").unwrap_err().to_string(),
+            "not a web URL"
+        );
+    }
+}
+
+#[cfg(feature = "uuid")]
+mod uuid {
+    use io_ensure::*;
+    use std::io::{self, ErrorKind};
+
+    fn parse(input: &str) -> io::Result<::uuid::Uuid> {
+        Ok(ensure_uuid_valid!(input, ErrorKind::InvalidInput))
+    }
+
+    #[test]
+    fn formats() {
+        let expected = parse("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        assert_eq!(parse("67e5504410b1426f9247bb680e5fe0c8").unwrap(), expected);
+        assert_eq!(
+            parse("{67e55044-10b1-426f-9247-bb680e5fe0c8}").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse("urn:uuid:67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap(),
+            expected
+        );
+        assert!(parse("00000000-0000-0000-0000-000000000000")
+            .unwrap()
+            .is_nil());
+    }
+
+    #[test]
+    fn invalid() {
+        let err = parse("67e55044-10b1-426f-9247").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(
+            err.to_string(),
+            "\"67e55044-10b1-426f-9247\" is not a valid UUID"
+        );
+        let source = err.get_ref().unwrap().source().unwrap();
+        assert!(source.is::<::uuid::Error>());
+
+        assert!(parse("").is_err());
+        assert!(parse("67e55044-10b1-426f-9247-bb680e5fe0cz").is_err());
+    }
+
+    #[test]
+    fn custom_message() {
+        fn check(id: String) -> io::Result<()> {
+            ensure_uuid_valid!(id, ErrorKind::InvalidInput, "invalid UUID: {}", id);
+            Ok(())
+        }
+        assert_eq!(
+            check("nope".into()).unwrap_err().to_string(),
+            "invalid UUID: nope"
+        );
+    }
+}
+
+#[test]
+fn ensure_state_transition() {
+    use std::collections::HashSet;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Tcp {
+        Listen,
+        SynReceived,
+        Established,
+        Closed,
+    }
+
+    const TABLE: [(Tcp, Tcp); 3] = [
+        (Tcp::Listen, Tcp::SynReceived),
+        (Tcp::SynReceived, Tcp::Established),
+        (Tcp::Established, Tcp::Closed),
+    ];
+
+    fn slice(from: Tcp, to: Tcp) -> io::Result<()> {
+        let table: &[(Tcp, Tcp)] = &TABLE;
+        ensure_state_transition!(from, to, table, ErrorKind::Other);
+        Ok(())
+    }
+    assert!(slice(Tcp::Listen, Tcp::SynReceived).is_ok());
+    let err = slice(Tcp::Listen, Tcp::Established).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert_eq!(
+        err.to_string(),
+        "invalid state transition from Listen to Established"
+    );
+
+    fn array(from: Tcp, to: Tcp) -> io::Result<()> {
+        ensure_state_transition!(
+            from,
+            to,
+            TABLE,
+            ErrorKind::Other,
+            "bad move from {:?}",
+            from
+        );
+        Ok(())
+    }
+    assert!(array(Tcp::Established, Tcp::Closed).is_ok());
+    assert_eq!(
+        array(Tcp::Closed, Tcp::Listen).unwrap_err().to_string(),
+        "bad move from Closed"
+    );
+
+    let set: HashSet<(Tcp, Tcp)> = TABLE.iter().copied().collect();
+    let vec = TABLE.to_vec();
+    let check = |from: Tcp, to: Tcp| -> io::Result<()> {
+        ensure_state_transition!(from, to, &set, ErrorKind::Other);
+        ensure_state_transition!(from, to, vec, ErrorKind::Other);
+        Ok(())
+    };
+    assert!(check(Tcp::SynReceived, Tcp::Established).is_ok());
+    assert!(check(Tcp::Established, Tcp::SynReceived).is_err());
+
+    fn closure(from: Tcp, to: Tcp) -> io::Result<()> {
+        ensure_state_transition!(
+            from,
+            to,
+            |(from, to)| to == from || *to == Tcp::Closed,
+            ErrorKind::Other
+        );
+        Ok(())
+    }
+    assert!(closure(Tcp::Listen, Tcp::Closed).is_ok());
+    assert!(closure(Tcp::Listen, Tcp::Listen).is_ok());
+    assert!(closure(Tcp::Listen, Tcp::Established).is_err());
+
+    fn annotated(from: Tcp, to: Tcp) -> io::Result<()> {
+        let allowed = |(_, to): (&Tcp, &Tcp)| *to != Tcp::Listen;
+        ensure_state_transition!(from, to, allowed, ErrorKind::Other);
+        Ok(())
+    }
+    assert!(annotated(Tcp::Closed, Tcp::Listen).is_err());
+}
+
+#[test]
+fn ensure_checksum() {
+    fn xor(data: &[u8]) -> u8 {
+        data.iter().fold(0, |acc, b| acc ^ b)
+    }
+
+    fn check(data: &[u8], expected: u8) -> io::Result<()> {
+        ensure_checksum!(data, expected, xor, ErrorKind::InvalidData);
+        Ok(())
+    }
+    assert!(check(&[0x0f, 0xf0], 0xff).is_ok());
+    let err = check(&[0x0f, 0xf0], 0x00).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "checksum mismatch: expected 0x0, got 0xff");
+
+    fn custom(data: Vec<u8>, expected: u64) -> io::Result<()> {
+        let len = |data: &[u8]| data.len() as u64;
+        ensure_checksum!(
+            data,
+            expected,
+            len,
+            ErrorKind::InvalidData,
+            "length {} is wrong",
+            data.len()
+        );
+        Ok(())
+    }
+    assert!(custom(vec![1, 2, 3], 3).is_ok());
+    assert_eq!(
+        custom(vec![1, 2], 3).unwrap_err().to_string(),
+        "length 2 is wrong"
+    );
+}
+
+#[cfg(feature = "crc")]
+#[test]
+fn ensure_crc32() {
+    fn check(data: &[u8], expected: u32) -> io::Result<()> {
+        ensure_crc32!(data, expected, ErrorKind::InvalidData);
+        Ok(())
+    }
+    assert!(check(b"123456789", 0xcbf4_3926).is_ok());
+    assert!(check(b"", 0).is_ok());
+    let err = check(b"123456780", 0xcbf4_3926).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("checksum mismatch: expected 0xcbf43926, got 0x"));
+}
+
+#[test]
+fn ensure_aligned() {
+    fn check(addr: usize, align: usize) -> io::Result<()> {
+        ensure_aligned!(addr, align, ErrorKind::InvalidInput);
+        Ok(())
+    }
+    assert!(check(0x1000, 4).is_ok());
+    assert!(check(0, 8).is_ok());
+    let err = check(0x1001, 4).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "address 0x1001 is not aligned to 4 bytes");
+    assert!(check(0x1003, 1).is_ok());
+
+    fn custom(addr: usize) -> io::Result<()> {
+        ensure_aligned!(
+            addr,
+            std::mem::align_of::<u64>(),
+            ErrorKind::InvalidInput,
+            "address 0x{:x} is not u64-aligned",
+            addr
+        );
+        Ok(())
+    }
+    let misaligned = std::mem::align_of::<u64>() + 1;
+    assert_eq!(
+        custom(misaligned).unwrap_err().to_string(),
+        format!("address 0x{:x} is not u64-aligned", misaligned)
+    );
+
+    let err = check(0x1000, 0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "alignment must be non-zero");
+}
+
+#[test]
+fn ensure_port_valid() {
+    fn any(port: u32) -> io::Result<()> {
+        ensure_port_valid!(port, ErrorKind::InvalidInput);
+        Ok(())
+    }
+    fn unprivileged(port: i64) -> io::Result<()> {
+        ensure_unprivileged_port!(port, ErrorKind::PermissionDenied);
+        Ok(())
+    }
+
+    let err = any(0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "port 0 is out of range 1-65535");
+    assert!(any(1).is_ok());
+    assert!(any(1023).is_ok());
+    assert!(any(65535).is_ok());
+    assert_eq!(
+        any(65536).unwrap_err().to_string(),
+        "port 65536 is out of range 1-65535"
+    );
+
+    assert!(unprivileged(-1).is_err());
+    let err = unprivileged(1023).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert_eq!(err.to_string(), "port 1023 is out of range 1024-65535");
+    assert!(unprivileged(1024).is_ok());
+    assert!(unprivileged(65535).is_ok());
+    assert!(unprivileged(65536).is_err());
+
+    fn custom(port: u16) -> io::Result<()> {
+        ensure_port_valid!(port, ErrorKind::InvalidInput, "bad port {}", port);
+        Ok(())
+    }
+    assert_eq!(custom(0).unwrap_err().to_string(), "bad port 0");
+}
+
+#[test]
+fn ensure_seek_valid() {
+    fn check(offset: u64, len: u64, mode: SeekMode) -> io::Result<()> {
+        ensure_seek_valid!(offset, len, mode, ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    assert!(check(10, 100, SeekMode::Read).is_ok());
+    assert!(check(10, 100, SeekMode::Write).is_ok());
+    assert!(check(0, 100, SeekMode::Read).is_ok());
+
+    let err = check(100, 100, SeekMode::Read).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(
+        err.to_string(),
+        "seek offset 100 is out of range for length 100"
+    );
+    assert!(check(100, 100, SeekMode::Write).is_ok());
+
+    assert!(check(101, 100, SeekMode::Read).is_err());
+    assert!(check(101, 100, SeekMode::Write).is_err());
+    assert!(check(0, 0, SeekMode::Read).is_err());
+    assert!(check(0, 0, SeekMode::Write).is_ok());
+
+    fn custom(offset: u64, len: u64) -> io::Result<()> {
+        ensure_seek_valid!(
+            offset,
+            len,
+            SeekMode::Write,
+            ErrorKind::InvalidInput,
+            "seek offset {} exceeds file length {}",
+            offset,
+            len
+        );
+        Ok(())
+    }
+    assert_eq!(
+        custom(9, 8).unwrap_err().to_string(),
+        "seek offset 9 exceeds file length 8"
+    );
+}
+
+#[test]
+fn ensure_read_exact() {
+    fn check(bytes_read: usize, expected: usize) -> io::Result<()> {
+        ensure_read_exact!(bytes_read, expected, ErrorKind::UnexpectedEof);
+        Ok(())
+    }
+    assert!(check(8, 8).is_ok());
+    let err = check(3, 8).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(err.to_string(), "short read: got 3 of 8 bytes");
+    assert_eq!(
+        check(0, 8).unwrap_err().to_string(),
+        "short read: got 0 of 8 bytes"
+    );
+    assert!(check(0, 0).is_ok());
+
+    fn custom(bytes_read: usize) -> io::Result<()> {
+        ensure_read_exact!(bytes_read, 4, ErrorKind::UnexpectedEof, "truncated header");
+        Ok(())
+    }
+    assert_eq!(custom(2).unwrap_err().to_string(), "truncated header");
+}
+
+#[test]
+fn ensure_write_exact() {
+    fn check(bytes_written: usize, buf: &[u8]) -> io::Result<()> {
+        ensure_write_exact!(bytes_written, buf.len(), ErrorKind::WriteZero);
+        Ok(())
+    }
+    assert!(check(5, b"hello").is_ok());
+    let err = check(2, b"hello").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WriteZero);
+    assert_eq!(err.to_string(), "short write: 2 of 5 bytes written");
+    assert_eq!(
+        check(0, b"hello").unwrap_err().to_string(),
+        "short write: 0 of 5 bytes written"
+    );
+    assert!(check(0, b"").is_ok());
+
+    fn custom(bytes_written: usize, buf: &[u8]) -> io::Result<()> {
+        ensure_write_exact!(
+            bytes_written,
+            buf.len(),
+            ErrorKind::WriteZero,
+            "short write: {} of {} bytes written",
+            bytes_written,
+            buf.len()
+        );
+        Ok(())
+    }
+    assert_eq!(
+        custom(1, b"abc").unwrap_err().to_string(),
+        "short write: 1 of 3 bytes written"
+    );
+}
+
+#[test]
+fn ensure_within_bounds() {
+    fn check(index: i64) -> io::Result<()> {
+        ensure_within_bounds!(index, 0, 48, ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    let err = check(-1).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "index -1 is below lower bound 0");
+    assert!(check(0).is_ok());
+    assert!(check(24).is_ok());
+    assert!(check(48).is_ok());
+    assert_eq!(
+        check(50).unwrap_err().to_string(),
+        "index 50 exceeds upper bound 48"
+    );
+
+    fn custom(offset: usize, buf: &[u8]) -> io::Result<()> {
+        ensure_within_bounds!(
+            offset,
+            1,
+            buf.len(),
+            ErrorKind::InvalidInput,
+            "offset {} out of range",
+            offset
+        );
+        Ok(())
+    }
+    assert!(custom(3, b"abc").is_ok());
+    assert_eq!(
+        custom(0, b"abc").unwrap_err().to_string(),
+        "offset 0 out of range"
+    );
+    assert_eq!(
+        custom(4, b"abc").unwrap_err().to_string(),
+        "offset 4 out of range"
+    );
+}
+
+#[test]
+fn ensure_exclusive_range() {
+    fn check(value: i32) -> io::Result<()> {
+        ensure_exclusive_range!(value, 0, 100, ErrorKind::InvalidInput);
+        Ok(())
+    }
+    assert!(check(1).is_ok());
+    assert!(check(50).is_ok());
+    assert!(check(99).is_ok());
+    let err = check(0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "value 0 is not strictly between 0 and 100");
+    assert_eq!(
+        check(100).unwrap_err().to_string(),
+        "value 100 is not strictly between 0 and 100"
+    );
+    assert!(check(-5).is_err());
+
+    fn custom(ratio: f64) -> io::Result<()> {
+        ensure_exclusive_range!(
+            ratio,
+            0.0,
+            1.0,
+            ErrorKind::InvalidInput,
+            "ratio must be in (0, 1)"
+        );
+        Ok(())
+    }
+    assert!(custom(0.5).is_ok());
+    assert_eq!(
+        custom(1.0).unwrap_err().to_string(),
+        "ratio must be in (0, 1)"
+    );
+}
+
+#[test]
+fn ensure_overlap() {
+    use std::ops::Range;
+
+    fn check(a: Range<u64>, b: Range<u64>) -> io::Result<()> {
+        ensure_overlap!(a, b, ErrorKind::InvalidData);
+        Ok(())
+    }
+    assert!(check(0..10, 5..15).is_ok());
+    assert!(check(5..15, 0..10).is_ok());
+    assert!(check(0..10, 2..3).is_ok());
+
+    let err = check(0..4, 4..8).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "range 0..4 does not overlap 4..8");
+    assert!(check(4..8, 0..4).is_err());
+    assert!(check(0..2, 6..8).is_err());
+
+    fn custom(a: Range<f64>, b: Range<f64>) -> io::Result<()> {
+        ensure_overlap!(a, b, ErrorKind::InvalidData, "ranges must overlap");
+        Ok(())
+    }
+    assert!(custom(0.0..1.0, 0.5..2.0).is_ok());
+    assert_eq!(
+        custom(0.0..1.0, 2.0..3.0).unwrap_err().to_string(),
+        "ranges must overlap"
+    );
+}
+
+#[test]
+fn ensure_disjoint() {
+    use std::ops::Range;
+
+    fn check(a: Range<usize>, b: Range<usize>) -> io::Result<()> {
+        ensure_disjoint!(a, b, ErrorKind::InvalidData);
+        Ok(())
+    }
+    assert!(check(0..2, 6..8).is_ok());
+    assert!(check(0..4, 4..8).is_ok());
+    assert!(check(4..8, 0..4).is_ok());
+
+    let err = check(0..10, 5..15).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "range 0..10 overlaps 5..15");
+    assert!(check(0..10, 2..3).is_err());
+
+    fn custom(a: Range<usize>, b: Range<usize>) -> io::Result<()> {
+        ensure_disjoint!(
+            a,
+            b,
+            ErrorKind::InvalidData,
+            "allocation regions must not overlap"
+        );
+        Ok(())
+    }
+    assert_eq!(
+        custom(0x1000..0x2000, 0x1800..0x2800)
+            .unwrap_err()
+            .to_string(),
+        "allocation regions must not overlap"
+    );
+}
+
+#[test]
+fn ensure_divisible_by() {
+    fn check(len: u64) -> io::Result<()> {
+        ensure_divisible_by!(len, 512, ErrorKind::InvalidInput);
+        Ok(())
+    }
+    assert!(check(0).is_ok());
+    assert!(check(4096).is_ok());
+    let err = check(4097).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "len 4097 is not divisible by 512");
+
+    fn custom(len: u64) -> io::Result<()> {
+        ensure_divisible_by!(len, 2, ErrorKind::InvalidInput, "odd length {}", len);
+        Ok(())
+    }
+    assert_eq!(custom(3).unwrap_err().to_string(), "odd length 3");
+
+    fn zero(len: u64, divisor: u64) -> io::Result<()> {
+        ensure_divisible_by!(len, divisor, ErrorKind::InvalidData, "bad length");
+        Ok(())
+    }
+    let err = zero(4096, 0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "divisor must be non-zero");
+}
+
+#[test]
+fn ensure_multiple_of() {
+    fn check(stride: usize) -> io::Result<()> {
+        ensure_multiple_of!(stride, 16, ErrorKind::InvalidInput);
+        Ok(())
+    }
+    assert!(check(32).is_ok());
+    let err = check(24).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "stride 24 must be a multiple of 16");
+
+    fn custom(stride: usize) -> io::Result<()> {
+        ensure_multiple_of!(stride, 16, ErrorKind::InvalidInput, "misaligned stride");
+        Ok(())
+    }
+    assert_eq!(custom(8).unwrap_err().to_string(), "misaligned stride");
+
+    fn zero(stride: i32, n: i32) -> io::Result<()> {
+        ensure_multiple_of!(stride, n, ErrorKind::InvalidData);
+        Ok(())
+    }
+    let err = zero(0, 0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "divisor must be non-zero");
+    assert!(zero(-32, -16).is_ok());
+}
+
+#[test]
+fn ensure_type_size_eq() {
+    #[allow(dead_code)]
+    struct Header {
+        magic: u32,
+        len: u32,
+    }
+
+    fn check(buf: &[u8]) -> io::Result<()> {
+        ensure_type_size_eq!(buf, Header, ErrorKind::InvalidData);
+        Ok(())
+    }
+    assert!(check(&[0; 8]).is_ok());
+    assert!(check(&[0; 12]).is_ok());
+    let err = check(&[0; 7]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(
+        err.to_string(),
+        "buffer of 7 bytes is too small for Header (8 bytes)"
+    );
+
+    fn custom(buf: Vec<u8>) -> io::Result<()> {
+        ensure_type_size_eq!(buf, u64, ErrorKind::InvalidData, "buffer too small for u64");
+        Ok(())
+    }
+    assert!(custom(vec![0; 8]).is_ok());
+    assert_eq!(
+        custom(vec![0; 2]).unwrap_err().to_string(),
+        "buffer too small for u64"
+    );
+}
+
+#[test]
+fn ensure_chunk_count_eq() {
+    fn check(data: &[u8], count: usize) -> io::Result<()> {
+        ensure_chunk_count_eq!(data, 16, count, ErrorKind::InvalidData);
+        Ok(())
+    }
+    assert!(check(&[0; 64], 4).is_ok());
+    assert!(check(&[], 0).is_ok());
+
+    let err = check(&[0; 65], 4).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(
+        err.to_string(),
+        "data length 65 is not divisible by chunk size 16"
+    );
+
+    let err = check(&[0; 48], 4).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "wrong number of chunks: expected 4, got 3");
+
+    fn custom(data: Vec<u8>) -> io::Result<()> {
+        ensure_chunk_count_eq!(
+            data,
+            8,
+            2,
+            ErrorKind::InvalidData,
+            "expected {} blocks of {} bytes",
+            2,
+            8
+        );
+        Ok(())
+    }
+    assert!(custom(vec![0; 16]).is_ok());
+    assert_eq!(
+        custom(vec![0; 15]).unwrap_err().to_string(),
+        "expected 2 blocks of 8 bytes"
+    );
+    assert_eq!(
+        custom(vec![0; 24]).unwrap_err().to_string(),
+        "expected 2 blocks of 8 bytes"
+    );
+
+    fn zero(data: &[u8], chunk_size: usize) -> io::Result<()> {
+        ensure_chunk_count_eq!(data, chunk_size, 0, ErrorKind::InvalidData);
+        Ok(())
+    }
+    fn zero_custom(data: &[u8], chunk_size: usize) -> io::Result<()> {
+        ensure_chunk_count_eq!(data, chunk_size, 0, ErrorKind::InvalidData, "bad blocks");
+        Ok(())
+    }
+    for err in [
+        zero(&[0; 4], 0).unwrap_err(),
+        zero(&[], 0).unwrap_err(),
+        zero_custom(&[0; 4], 0).unwrap_err(),
+    ] {
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), "chunk size must be non-zero");
+    }
+}
+
+#[test]
+fn ensure_len_range() {
+    fn range(len: usize) -> io::Result<()> {
+        ensure_len_range!(vec![0u8; len], 2..4, ErrorKind::InvalidInput);
+        Ok(())
+    }
+    assert!(range(2).is_ok());
+    assert!(range(3).is_ok());
+    assert!(range(1).is_err());
+    let err = range(4).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "length 4 is not in range 2..4");
+
+    fn inclusive(len: usize) -> io::Result<()> {
+        ensure_len_range!(vec![0u8; len], 2..=4, ErrorKind::InvalidInput);
+        Ok(())
+    }
+    assert!(inclusive(2).is_ok());
+    assert!(inclusive(4).is_ok());
+    assert!(inclusive(1).is_err());
+    assert_eq!(
+        inclusive(5).unwrap_err().to_string(),
+        "length 5 is not in range 2..=4"
+    );
+
+    fn from(len: usize) -> io::Result<()> {
+        ensure_len_range!(vec![0u8; len], 2.., ErrorKind::InvalidInput);
+        Ok(())
+    }
+    assert!(from(2).is_ok());
+    assert!(from(1000).is_ok());
+    assert_eq!(
+        from(1).unwrap_err().to_string(),
+        "length 1 is not in range 2.."
+    );
+
+    fn to(len: usize) -> io::Result<()> {
+        ensure_len_range!(vec![0u8; len], ..4, ErrorKind::InvalidInput);
+        Ok(())
+    }
+    assert!(to(0).is_ok());
+    assert!(to(3).is_ok());
+    assert_eq!(
+        to(4).unwrap_err().to_string(),
+        "length 4 is not in range ..4"
+    );
+
+    fn to_inclusive(len: usize) -> io::Result<()> {
+        ensure_len_range!(vec![0u8; len], ..=4, ErrorKind::InvalidInput);
+        Ok(())
+    }
+    assert!(to_inclusive(4).is_ok());
+    assert_eq!(
+        to_inclusive(5).unwrap_err().to_string(),
+        "length 5 is not in range ..=4"
+    );
+
+    fn full(len: usize) -> io::Result<()> {
+        ensure_len_range!(vec![0u8; len], .., ErrorKind::InvalidInput);
+        Ok(())
+    }
+    assert!(full(0).is_ok());
+    assert!(full(1000).is_ok());
+
+    fn custom(input: &str) -> io::Result<()> {
+        ensure_len_range!(
+            input,
+            1..=8,
+            ErrorKind::InvalidInput,
+            "length {} not in allowed range 1..=8",
+            input.len()
+        );
+        Ok(())
+    }
+    assert!(custom("host").is_ok());
+    assert_eq!(
+        custom("").unwrap_err().to_string(),
+        "length 0 not in allowed range 1..=8"
+    );
+}
+
+#[test]
+fn ensure_utf16_valid() {
+    fn check(bytes: &[u8]) -> io::Result<String> {
+        Ok(ensure_utf16_valid!(bytes, ErrorKind::InvalidData))
+    }
+    assert_eq!(check(b"").unwrap(), "");
+    assert_eq!(check(b"h\0i\0").unwrap(), "hi");
+    assert_eq!(check(&[0x3d, 0xd8, 0x00, 0xde]).unwrap(), "\u{1f600}");
+
+    let err = check(&[0x00, 0xd8, b'a', 0x00]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "data is not valid UTF-16LE");
+    assert!(err
+        .get_ref()
+        .and_then(|err| err.source())
+        .unwrap()
+        .to_string()
+        .contains("unpaired surrogate"));
+
+    let err = check(b"h\0i").unwrap_err();
+    assert_eq!(
+        err.get_ref()
+            .and_then(|err| err.source())
+            .unwrap()
+            .to_string(),
+        "odd number of bytes: 3"
+    );
+
+    fn le(bytes: Vec<u8>) -> io::Result<String> {
+        Ok(ensure_utf16_le_valid!(
+            bytes,
+            ErrorKind::InvalidData,
+            "invalid UTF-16LE data"
+        ))
+    }
+    assert_eq!(le(b"o\0k\0".to_vec()).unwrap(), "ok");
+    assert_eq!(
+        le(vec![0x00, 0xdc]).unwrap_err().to_string(),
+        "invalid UTF-16LE data"
+    );
+
+    fn be(bytes: &[u8]) -> io::Result<String> {
+        Ok(ensure_utf16_be_valid!(bytes, ErrorKind::InvalidData))
+    }
+    assert_eq!(be(b"\0o\0k").unwrap(), "ok");
+    assert_eq!(be(&[0xd8, 0x3d, 0xde, 0x00]).unwrap(), "\u{1f600}");
+    assert_eq!(be(b"o\0k\0").unwrap(), "\u{6f00}\u{6b00}");
+    assert_eq!(
+        be(&[0xdc, 0x00]).unwrap_err().to_string(),
+        "data is not valid UTF-16BE"
+    );
+    assert!(be(b"\0").is_err());
+}
+
+#[test]
+fn ensure_timeout_not_elapsed() {
+    use std::time::{Duration, Instant};
+
+    fn check(deadline: Instant) -> io::Result<()> {
+        ensure_timeout_not_elapsed!(deadline, ErrorKind::TimedOut);
+        Ok(())
+    }
+
+    let start = Instant::now();
+    assert!(check(start + Duration::from_secs(3600)).is_ok());
+
+    std::thread::sleep(Duration::from_millis(1));
+    let err = check(start).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert!(err.to_string().starts_with("deadline exceeded by "));
+
+    fn custom(deadline: Instant) -> io::Result<()> {
+        ensure_timeout_not_elapsed!(deadline, ErrorKind::TimedOut, "handshake took too long");
+        Ok(())
+    }
+    assert_eq!(
+        custom(start).unwrap_err().to_string(),
+        "handshake took too long"
+    );
+}
+
+#[derive(Debug)]
+struct Flaky {
+    interrupts: usize,
+    calls: usize,
+}
+
+impl Flaky {
+    fn new(interrupts: usize) -> Self {
+        Self {
+            interrupts,
+            calls: 0,
+        }
+    }
+
+    fn call(&mut self, len: usize) -> io::Result<usize> {
+        self.calls += 1;
+        if self.interrupts > 0 {
+            self.interrupts -= 1;
+            return Err(format_err!(ErrorKind::Interrupted, "signal"));
+        }
+        Ok(len)
+    }
+}
+
+impl io::Read for Flaky {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.call(buf.len())
+    }
+}
+
+impl io::Write for Flaky {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.call(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.call(0).map(drop)
+    }
+}
+
+#[test]
+fn retry_io_unlimited() {
+    use std::io::{Read, Write};
+
+    let mut io = RetryIo::new(Flaky::new(5));
+    assert_eq!(io.read(&mut [0; 4]).unwrap(), 4);
+    assert_eq!(io.get_ref().calls, 6);
+
+    io.get_mut().interrupts = 2;
+    assert_eq!(io.write(b"abc").unwrap(), 3);
+    io.get_mut().interrupts = 1;
+    io.flush().unwrap();
+    assert_eq!(io.into_inner().calls, 11);
+}
+
+#[test]
+fn retry_io_limited() {
+    use std::io::{Read, Write};
+
+    let mut io = RetryIo::with_max_retries(Flaky::new(2), 2);
+    assert_eq!(io.read(&mut [0; 4]).unwrap(), 4);
+
+    io.get_mut().interrupts = 3;
+    let err = io.write(b"abc").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Interrupted);
+    assert_eq!(io.get_ref().calls, 6);
+
+    let mut io = RetryIo::with_max_retries(Flaky::new(1), 0);
+    assert!(io.read(&mut [0; 1]).unwrap_err().is_interrupted());
+}
+
+#[test]
+fn multi_context() {
+    fn read_header() -> io::Result<()> {
+        Err(format_err!(ErrorKind::UnexpectedEof, "early eof"))
+    }
+    fn open_index() -> io::Result<()> {
+        read_header().context("reading index header")
+    }
+    fn open_database() -> io::Result<()> {
+        open_index()
+            .context("opening index")
+            .context("opening database")
+    }
+
+    let err = open_database().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(
+        err.to_string(),
+        "early eof\ncontext:\n    0: reading index header\n    1: opening index\n    2: opening database"
+    );
+
+    let multi = downcast_io_err!(err, MultiContext).unwrap();
+    assert_eq!(
+        multi.contexts(),
+        ["reading index header", "opening index", "opening database"]
+    );
+    // The original error is not wrapped more than once.
+    assert!(multi
+        .get_ref()
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<MultiContext>()
+        .is_none());
+    assert_eq!(multi.get_ref().to_string(), "early eof");
+    assert_eq!(
+        std::error::Error::source(multi).unwrap().to_string(),
+        "early eof"
+    );
+
+    let mut multi = MultiContext::new(format_err!(ErrorKind::UnexpectedEof, "early eof"));
+    multi.push("reading index header");
+    assert_eq!(
+        multi.to_string(),
+        "early eof\ncontext:\n    0: reading index header"
+    );
+    let err: io::Error = multi.into();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+    let ok: io::Result<u8> = Ok(1);
+    assert_eq!(ok.context("unused").unwrap(), 1);
+}
+
+#[test]
+fn io_context_with_context() {
+    let mut calls = 0;
+    let ok: io::Result<u8> = Ok(1);
+    let value = ok
+        .with_context(|| {
+            calls += 1;
+            "unused"
+        })
+        .unwrap();
+    assert_eq!(value, 1);
+    assert_eq!(calls, 0);
+
+    let index = 3;
+    let res: io::Result<()> = Err(format_err!(ErrorKind::UnexpectedEof, "early eof"));
+    let err = res
+        .with_context(|| format!("reading block {}", index))
+        .context("opening database")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    let multi = downcast_io_err!(err, MultiContext).unwrap();
+    assert_eq!(multi.contexts(), ["reading block 3", "opening database"]);
+    assert_eq!(multi.get_ref().to_string(), "early eof");
+
+    // The previous name of the trait still works.
+    fn old_name(res: io::Result<()>) -> io::Result<()> {
+        IoResultExt::context(res, "old name")
+    }
+    assert!(old_name(Err(format_err!(ErrorKind::Other))).is_err());
+}
+
+#[test]
+fn io_context_location() {
+    fn read_header() -> io::Result<()> {
+        Err(format_err!(ErrorKind::UnexpectedEof, "early eof"))
+    }
+
+    let line = line!() + 1;
+    let res = read_header().context("reading index header");
+    let err = res.context("opening database").unwrap_err();
+    let multi = downcast_io_err!(err, MultiContext).unwrap();
+    assert_eq!(multi.location().file(), file!());
+    assert_eq!(multi.location().line(), line);
+
+    // Each error in the chain is reached through `source`.
+    let mut chain = vec![];
+    let mut source = std::error::Error::source(multi);
+    while let Some(err) = source {
+        chain.push(err.to_string());
+        source = err.source();
+    }
+    assert_eq!(chain, ["early eof"]);
+}
+
+#[cfg(unix)]
+fn temp_fifo(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("io-ensure-{}-{}", name, std::process::id()));
+    let status = std::process::Command::new("mkfifo")
+        .arg(&path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    path
+}
+
+#[cfg(unix)]
+fn temp_file_with_mode(name: &str, mode: u32) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = std::env::temp_dir().join(format!("io-ensure-{}-{}", name, std::process::id()));
+    std::fs::write(&path, b"#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+    path
+}
+
+#[cfg(unix)]
+fn set_mode(path: &std::path::Path, mode: u32) {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn ensure_path_readable() {
+    use std::path::Path;
+
+    fn check(path: &Path) -> io::Result<()> {
+        ensure_path_readable!(path, ErrorKind::PermissionDenied);
+        Ok(())
+    }
+
+    let path = temp_file_with_mode("readable", 0o644);
+    assert!(check(&path).is_ok());
+    assert!(check(&std::env::temp_dir()).is_ok());
+
+    set_mode(&path, 0o000);
+    // Privileged users can read the file regardless of its mode.
+    let privileged = std::fs::File::open(&path).is_ok();
+    if !privileged {
+        let err = check(&path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert!(err.to_string().starts_with("cannot read "));
+        assert!(std::error::Error::source(err.get_ref().unwrap()).is_some());
+    }
+    set_mode(&path, 0o644);
+    assert!(check(&path).is_ok());
+    std::fs::remove_file(&path).unwrap();
+
+    // FIFOs are checked without opening them, which would block until a
+    // writer appears.
+    let fifo = temp_fifo("readable-fifo");
+    set_mode(&fifo, 0o644);
+    assert!(check(&fifo).is_ok());
+    set_mode(&fifo, 0o200);
+    if !privileged {
+        assert_eq!(
+            check(&fifo).unwrap_err().kind(),
+            ErrorKind::PermissionDenied
+        );
+    }
+    std::fs::remove_file(&fifo).unwrap();
+
+    let err = check(&path).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert!(err
+        .to_string()
+        .starts_with(&format!("cannot read {}: ", path.display())));
+    let source = std::error::Error::source(err.get_ref().unwrap()).unwrap();
+    assert_eq!(
+        source.downcast_ref::<io::Error>().unwrap().kind(),
+        ErrorKind::NotFound
+    );
+
+    fn custom(path: &Path) -> io::Result<()> {
+        ensure_path_readable!(path, ErrorKind::NotFound, "cannot read {}", path.display());
+        Ok(())
+    }
+    let err = custom(&path).unwrap_err();
+    assert_eq!(err.to_string(), format!("cannot read {}", path.display()));
+}
+
+#[cfg(unix)]
+#[test]
+fn ensure_path_writable() {
+    use std::path::Path;
+
+    fn check(path: &Path) -> io::Result<()> {
+        ensure_path_writable!(path, ErrorKind::PermissionDenied);
+        Ok(())
+    }
+
+    let path = temp_file_with_mode("writable", 0o644);
+    assert!(check(&path).is_ok());
+    assert!(check(&std::env::temp_dir()).is_ok());
+    // Checking does not truncate the file.
+    assert_eq!(std::fs::read(&path).unwrap(), b"#!/bin/sh\n");
+
+    set_mode(&path, 0o000);
+    // Privileged users can write the file regardless of its mode.
+    let privileged = std::fs::OpenOptions::new().write(true).open(&path).is_ok();
+    if !privileged {
+        let err = check(&path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert!(err
+            .to_string()
+            .starts_with(&format!("cannot write to {}: ", path.display())));
+    }
+    set_mode(&path, 0o644);
+    assert!(check(&path).is_ok());
+    std::fs::remove_file(&path).unwrap();
+
+    // The owner's permission bits apply to the owner, even when the group and
+    // others could write.
+    let dir = std::env::temp_dir().join(format!("io-ensure-writable-dir-{}", std::process::id()));
+    std::fs::create_dir(&dir).unwrap();
+    let modified = std::fs::metadata(&dir).unwrap().modified().unwrap();
+    assert!(check(&dir).is_ok());
+    // Checking leaves the directory untouched.
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    assert_eq!(
+        std::fs::metadata(&dir).unwrap().modified().unwrap(),
+        modified
+    );
+    set_mode(&dir, 0o577);
+    // Privileged users can write to the directory regardless of its mode.
+    if !privileged {
+        assert_eq!(check(&dir).unwrap_err().kind(), ErrorKind::PermissionDenied);
+        // A directory owned by another user, such as `/`, is not writable
+        // even though its owner's write bit is set.
+        assert!(check(Path::new("/")).is_err());
+    }
+    set_mode(&dir, 0o755);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // FIFOs are checked without opening them, which would block until a
+    // reader appears.
+    let fifo = temp_fifo("writable-fifo");
+    set_mode(&fifo, 0o644);
+    assert!(check(&fifo).is_ok());
+    set_mode(&fifo, 0o444);
+    if !privileged {
+        assert_eq!(
+            check(&fifo).unwrap_err().kind(),
+            ErrorKind::PermissionDenied
+        );
+    }
+    std::fs::remove_file(&fifo).unwrap();
+
+    fn custom(path: &Path) -> io::Result<()> {
+        ensure_path_writable!(
+            path,
+            ErrorKind::PermissionDenied,
+            "cannot write to {}",
+            path.display()
+        );
+        Ok(())
+    }
+    let err = custom(&path).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("cannot write to {}", path.display())
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn ensure_path_executable() {
+    use std::path::Path;
+
+    fn check(path: &Path) -> io::Result<()> {
+        ensure_path_executable!(path, ErrorKind::PermissionDenied);
+        Ok(())
+    }
+
+    let path = temp_file_with_mode("executable", 0o755);
+    assert!(check(&path).is_ok());
+    set_mode(&path, 0o700);
+    assert!(check(&path).is_ok());
+
+    set_mode(&path, 0o644);
+    let err = check(&path).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert_eq!(
+        err.to_string(),
+        format!("cannot execute {}: permission denied", path.display())
+    );
+
+    // Only the owner's bit applies to the owner.
+    set_mode(&path, 0o070);
+    let privileged = std::fs::File::open(&path).is_ok();
+    if !privileged {
+        assert!(check(&path).is_err());
+    }
+    set_mode(&path, 0o644);
+
+    // Directories are searched, not executed.
+    let err = check(&std::env::temp_dir()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "cannot execute {}: is a directory",
+            std::env::temp_dir().display()
+        )
+    );
+
+    fn custom(path: &Path) -> io::Result<()> {
+        ensure_path_executable!(
+            path,
+            ErrorKind::PermissionDenied,
+            "{} is not executable",
+            path.display()
+        );
+        Ok(())
+    }
+    let err = custom(&path).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("{} is not executable", path.display())
+    );
+    std::fs::remove_file(&path).unwrap();
+    assert!(check(&path).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn path_credentials() {
+    let user = __private::Credentials {
+        uid: 1000,
+        gid: 1000,
+        groups: vec![1000, 20],
+    };
+    // A file owned by someone else, which only its owner may execute.
+    assert!(!user.permits(0o700, 0, 0, 0o1));
+    assert!(user.permits(0o700, 1000, 0, 0o1));
+    // The owner's bits apply to the owner, even when others may execute.
+    assert!(!user.permits(0o077, 1000, 1000, 0o1));
+    assert!(user.permits(0o750, 0, 20, 0o1));
+    assert!(!user.permits(0o705, 0, 20, 0o1));
+    assert!(user.permits(0o701, 0, 0, 0o1));
+    assert!(user.permits(0o640, 0, 1000, 0o4));
+    assert!(!user.permits(0o640, 0, 1000, 0o2));
+
+    let root = __private::Credentials {
+        uid: 0,
+        gid: 0,
+        groups: vec![],
+    };
+    assert!(root.permits(0o000, 1000, 1000, 0o4));
+    assert!(root.permits(0o000, 1000, 1000, 0o2));
+    assert!(!root.permits(0o600, 1000, 1000, 0o1));
+    assert!(root.permits(0o001, 1000, 1000, 0o1));
+}
+
+#[test]
+fn ensure_non_zero() {
+    fn check(count: i32) -> io::Result<()> {
+        ensure_non_zero!(count, ErrorKind::InvalidInput);
+        Ok(())
+    }
+    assert!(check(1).is_ok());
+    assert!(check(-1).is_ok());
+    let err = check(0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "count must not be zero");
+}
+
+#[test]
+fn ensure_non_null() {
+    fn check(addr: usize) -> io::Result<()> {
+        ensure_non_null!(addr, ErrorKind::InvalidInput);
+        Ok(())
+    }
+    assert!(check(0x1000).is_ok());
+    let err = check(0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "addr is a null pointer");
+
+    fn custom(ptr: *const u8) -> io::Result<()> {
+        ensure_non_null!(
+            ptr as usize,
+            ErrorKind::InvalidInput,
+            "FFI returned null pointer"
+        );
+        Ok(())
+    }
+    assert!(custom(b"x".as_ptr()).is_ok());
+    assert_eq!(
+        custom(std::ptr::null()).unwrap_err().to_string(),
+        "FFI returned null pointer"
+    );
+}
+
+#[test]
+fn rate_limiter() {
+    use std::time::Duration;
+
+    let mut limiter = RateLimiter::new(3, Duration::from_secs(3600));
+    assert_eq!(limiter.current_rate(), 0.0);
+    assert!(limiter.try_acquire());
+    assert!(limiter.try_acquire());
+    assert!(limiter.try_acquire());
+    assert!(!limiter.try_acquire());
+    assert!(limiter.current_rate() > 0.0);
+
+    let mut limiter = RateLimiter::new(1000, Duration::from_secs(1));
+    while limiter.try_acquire() {}
+    std::thread::sleep(Duration::from_millis(20));
+    assert!(limiter.try_acquire());
+}
+
+#[test]
+fn ensure_rate_within() {
+    use std::time::Duration;
+
+    fn handle(limiter: &mut RateLimiter) -> io::Result<()> {
+        ensure_rate_within!(limiter, ErrorKind::WouldBlock);
+        Ok(())
+    }
+
+    let mut limiter = RateLimiter::new(2, Duration::from_secs(60));
+    assert!(handle(&mut limiter).is_ok());
+    assert!(handle(&mut limiter).is_ok());
+    let err = handle(&mut limiter).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    assert_eq!(err.to_string(), "rate limit of 2 per 60s exceeded");
+
+    fn custom(limiter: &mut RateLimiter) -> io::Result<()> {
+        ensure_rate_within!(
+            &mut *limiter,
+            ErrorKind::WouldBlock,
+            "rate limit exceeded: {:.0} req/min",
+            limiter.current_rate() * 60.0
+        );
+        Ok(())
+    }
+    assert_eq!(
+        custom(&mut limiter).unwrap_err().to_string(),
+        "rate limit exceeded: 2 req/min"
+    );
+}
+
+#[test]
+fn ensure_env_var() {
+    const NAME: &str = "IO_ENSURE_TEST_ENV_VAR";
+
+    fn read() -> io::Result<String> {
+        Ok(ensure_env_var!(NAME, ErrorKind::NotFound))
+    }
+    fn read_os(name: String) -> io::Result<std::ffi::OsString> {
+        Ok(ensure_env_var_os!(
+            name,
+            ErrorKind::NotFound,
+            "{} must be set",
+            name
+        ))
+    }
+
+    std::env::set_var(NAME, "postgres://localhost");
+    assert_eq!(read().unwrap(), "postgres://localhost");
+    assert_eq!(read_os(NAME.into()).unwrap(), "postgres://localhost");
+
+    std::env::remove_var(NAME);
+    let err = read().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert_eq!(
+        err.to_string(),
+        "environment variable \"IO_ENSURE_TEST_ENV_VAR\" is not set"
+    );
+    assert_eq!(
+        read_os(NAME.into()).unwrap_err().to_string(),
+        "IO_ENSURE_TEST_ENV_VAR must be set"
+    );
+
+    #[cfg(unix)]
+    {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        std::env::set_var(NAME, OsStr::from_bytes(b"\xff\xfe"));
+        let err = read().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "environment variable \"IO_ENSURE_TEST_ENV_VAR\" is not valid UTF-8"
+        );
+        assert!(read_os(NAME.into()).is_ok());
+        std::env::remove_var(NAME);
+    }
+}
+
+#[test]
+fn ensure_not_expired() {
+    use std::time::{Duration, Instant, SystemTime};
+
+    fn system(issued_at: SystemTime, ttl: Duration) -> io::Result<()> {
+        ensure_not_expired!(issued_at, ttl, ErrorKind::TimedOut);
+        Ok(())
+    }
+    fn instant(issued_at: Instant, ttl: Duration) -> io::Result<()> {
+        ensure_not_expired!(issued_at, ttl, ErrorKind::TimedOut, "session expired");
+        Ok(())
+    }
+
+    let hour = Duration::from_secs(3600);
+    assert!(system(SystemTime::now(), hour).is_ok());
+    let err = system(SystemTime::now() - 2 * hour, hour).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert!(err.to_string().starts_with("expired at "));
+
+    assert!(instant(Instant::now(), hour).is_ok());
+    let start = Instant::now();
+    std::thread::sleep(Duration::from_millis(1));
+    assert_eq!(
+        instant(start, Duration::ZERO).unwrap_err().to_string(),
+        "session expired"
+    );
+
+    let err = system(SystemTime::now(), Duration::MAX).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(err.to_string().starts_with("expiry time overflows: "));
+    let err = instant(Instant::now(), Duration::MAX).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn ensure_path_absolute() {
+    use std::path::Path;
+
+    fn check(path: &str) -> io::Result<()> {
+        ensure_path_absolute!(path, ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    assert!(check("/etc/app.toml").is_ok());
+    #[cfg(windows)]
+    assert!(check(r"C:\app.toml").is_ok());
+    let err = check("app.toml").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "path app.toml is not absolute");
+    assert!(check("./app.toml").is_err());
+    // Tilde expansion is done by shells, not by `Path`.
+    assert!(check("~/app.toml").is_err());
+
+    fn custom(config_path: &Path) -> io::Result<()> {
+        ensure_path_absolute!(
+            config_path,
+            ErrorKind::InvalidInput,
+            "config path must be absolute: {}",
+            config_path.display()
+        );
+        Ok(())
+    }
+    assert_eq!(
+        custom(Path::new("conf")).unwrap_err().to_string(),
+        "config path must be absolute: conf"
+    );
+}
+
+#[test]
+fn ensure_path_relative() {
+    use std::path::PathBuf;
+
+    fn check(path: PathBuf) -> io::Result<()> {
+        ensure_path_relative!(path, ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    assert!(check("welcome.html".into()).is_ok());
+    assert!(check("./emails/welcome.html".into()).is_ok());
+    let absolute = std::env::temp_dir();
+    let err = check(absolute.clone()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(
+        err.to_string(),
+        format!("path {} is not relative", absolute.display())
+    );
+
+    fn custom(template_path: PathBuf) -> io::Result<()> {
+        ensure_path_relative!(
+            template_path,
+            ErrorKind::InvalidInput,
+            "template path must be relative: {}",
+            template_path.display()
+        );
+        Ok(())
+    }
+    assert!(custom(absolute).is_err());
+}
+
+#[test]
+fn ensure_path_no_traversal() {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn check(path: &str, base: &Path) -> io::Result<PathBuf> {
+        Ok(ensure_path_no_traversal!(
+            path,
+            base,
+            ErrorKind::PermissionDenied
+        ))
+    }
+
+    let root = std::env::temp_dir().join(format!("io-ensure-traversal-{}", std::process::id()));
+    let base = root.join("www");
+    fs::create_dir_all(base.join("a/b/c")).unwrap();
+    fs::write(root.join("secret"), b"").unwrap();
+    let canonical = base.canonicalize().unwrap();
+
+    assert_eq!(check("a/b/c", &base).unwrap(), canonical.join("a/b/c"));
+    assert_eq!(
+        check("a/b/c/d/e/new.txt", &base).unwrap(),
+        canonical.join("a/b/c/d/e/new.txt")
+    );
+    assert_eq!(check("a/../a/./b", &base).unwrap(), canonical.join("a/b"));
+
+    let err = check("../secret", &base).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "path ../secret escapes base directory {}",
+            canonical.display()
+        )
+    );
+    assert!(check("a/b/../../../secret", &base).is_err());
+    assert!(check("missing/../../secret", &base).is_err());
+    assert!(check(root.join("secret").to_str().unwrap(), &base).is_err());
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(&root, base.join("escape")).unwrap();
+        std::os::unix::fs::symlink(base.join("a/b"), base.join("inside")).unwrap();
+        assert!(check("escape/secret", &base).is_err());
+        assert!(check("missing/../escape/secret", &base).is_err());
+        assert!(check("escape/www/a", &base).is_ok());
+        assert_eq!(check("inside/c", &base).unwrap(), canonical.join("a/b/c"));
+        assert_eq!(check("inside/..", &base).unwrap(), canonical.join("a"));
+    }
+
+    fn custom(path: &str, base: &Path) -> io::Result<()> {
+        ensure_path_no_traversal!(
+            path,
+            base,
+            ErrorKind::PermissionDenied,
+            "path traversal detected"
+        );
+        Ok(())
+    }
+    assert_eq!(
+        custom("../secret", &base).unwrap_err().to_string(),
+        "path traversal detected"
+    );
+    let err = custom("a", &root.join("missing")).unwrap_err();
+    assert_eq!(err.to_string(), "path traversal detected");
+    assert!(std::error::Error::source(err.get_ref().unwrap()).is_some());
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn ensure_symlink_target() {
+    use std::path::Path;
+
+    fn check(link: &Path, expected: &str) -> io::Result<()> {
+        ensure_symlink_target!(link, expected, ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    let dir = std::env::temp_dir().join(format!("io-ensure-symlink-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let link = dir.join("current");
+    std::os::unix::fs::symlink("releases/v2", &link).unwrap();
+
+    assert!(check(&link, "releases/v2").is_ok());
+    assert!(check(&link, "releases/v2/").is_ok());
+    assert!(check(&link, "./releases/./v2").is_ok());
+    let err = check(&link, "releases/v1").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "symlink {} points to releases/v2, expected releases/v1",
+            link.display()
+        )
+    );
+
+    // Regular files are not symlinks.
+    let file = dir.join("file");
+    std::fs::write(&file, b"").unwrap();
+    let err = check(&file, "releases/v2").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("cannot read symlink {}", file.display())
+    );
+    assert!(std::error::Error::source(err.get_ref().unwrap()).is_some());
+
+    fn custom(link_path: &Path) -> io::Result<()> {
+        ensure_symlink_target!(
+            link_path,
+            "/opt/app",
+            ErrorKind::InvalidInput,
+            "symlink {} points to wrong target",
+            link_path.display()
+        );
+        Ok(())
+    }
+    assert_eq!(
+        custom(&link).unwrap_err().to_string(),
+        format!("symlink {} points to wrong target", link.display())
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "semver")]
+mod semver {
+    use io_ensure::*;
+    use std::io::{self, ErrorKind};
+
+    fn check(version: &str) -> io::Result<::semver::Version> {
+        Ok(ensure_version_compat!(
+            version,
+            ">= 1.2, < 2.0",
+            ErrorKind::Other
+        ))
+    }
+
+    #[test]
+    fn matching() {
+        assert_eq!(check("1.2.0").unwrap(), ::semver::Version::new(1, 2, 0));
+        assert!(check("1.9.9").is_ok());
+    }
+
+    #[test]
+    fn not_matching() {
+        let err = check("2.0.0").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Other);
+        assert_eq!(
+            err.to_string(),
+            "version 2.0.0 does not satisfy \">= 1.2, < 2.0\""
+        );
+        assert!(check("1.1.9").is_err());
+    }
+
+    #[test]
+    fn pre_release() {
+        assert!(check("1.5.0-beta.1").is_err());
+        fn pre(version: &str) -> io::Result<::semver::Version> {
+            Ok(ensure_version_compat!(
+                version,
+                ">= 1.5.0-alpha",
+                ErrorKind::Other
+            ))
+        }
+        assert!(pre("1.5.0-beta.1").is_ok());
+        assert!(pre("1.6.0").is_ok());
+        assert!(pre("1.6.0-beta.1").is_err());
+    }
+
+    #[test]
+    fn invalid_version() {
+        let err = check("1.2").unwrap_err();
+        assert_eq!(err.to_string(), "\"1.2\" is not a valid version");
+        let source = err.get_ref().unwrap().source().unwrap();
+        assert!(source.is::<::semver::Error>());
+    }
+
+    #[test]
+    fn custom_message() {
+        fn plugin(plugin_name: &str, plugin_version: String) -> io::Result<()> {
+            ensure_version_compat!(
+                plugin_version,
+                "^1.2",
+                ErrorKind::Other,
+                "plugin {} requires version ^1.2, found {}",
+                plugin_name,
+                plugin_version
+            );
+            Ok(())
+        }
+        assert_eq!(
+            plugin("fmt", "0.9.0".into()).unwrap_err().to_string(),
+            "plugin fmt requires version ^1.2, found 0.9.0"
+        );
+    }
+
+    #[test]
+    fn requirement_validation() {
+        // Invalid requirements are rejected at compile time, by a check that
+        // has to agree with `semver`.
+        let reqs = [
+            "*",
+            " x ",
+            "X",
+            "1",
+            "1.2",
+            "1.2.3",
+            "=1.2.3",
+            ">= 1.2, < 2.0",
+            "<=1.2",
+            "~1",
+            "^0.1.2",
+            "1.*",
+            "1.x.X",
+            "1.2.*",
+            ">1.*",
+            "1.2.3-alpha.1",
+            "1.2.3-x-y.0",
+            "1.2.3+build.007",
+            "1.2.3-rc.1+sha",
+            "1.2 ,1.3",
+            "",
+            " ",
+            ">= one",
+            "*, 1.2",
+            "* 1",
+            "1.*.3",
+            "01.2",
+            "1.02",
+            "1.2.3-01",
+            "1.2.3-",
+            "1.2.3-a..b",
+            "1.2.3+",
+            "1.2-alpha",
+            "1.2.",
+            ">",
+            "1.2,",
+            "1.2 1.3",
+            "18446744073709551616",
+            "18446744073709551615",
+            "=>1",
+            "1.2.3.4",
+            "1, *",
+        ];
+        for req in reqs {
+            assert_eq!(
+                __private::is_valid_version_req(req),
+                ::semver::VersionReq::parse(req).is_ok(),
+                "{:?}",
+                req
+            );
+        }
+        let many = vec!["1"; 33].join(",");
+        assert!(!__private::is_valid_version_req(&many));
+        assert!(::semver::VersionReq::parse(&many).is_err());
+        let max = vec!["1"; 32].join(",");
+        assert!(__private::is_valid_version_req(&max));
+        assert!(::semver::VersionReq::parse(&max).is_ok());
+    }
+}
+
+#[test]
+fn io_ensure_ext() {
+    use std::io::Cursor;
+
+    let mut reader = Cursor::new(b"abcdef".to_vec());
+    let mut buf = [0; 4];
+    let frame = || format_err!(ErrorKind::UnexpectedEof, "incomplete frame");
+    assert_eq!(reader.read_exact_ensuring(&mut buf, frame).unwrap(), 4);
+    assert_eq!(&buf, b"abcd");
+    let err = reader.read_exact_ensuring(&mut buf, frame).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(err.to_string(), "incomplete frame");
+
+    let bad_seek = || format_err!(ErrorKind::InvalidInput, "bad seek");
+    assert_eq!(reader.seek_to_ensuring(1, bad_seek).unwrap(), 1);
+    assert_eq!(reader.read_exact_ensuring(&mut buf, frame).unwrap(), 4);
+    assert_eq!(&buf, b"bcde");
+
+    let mut out = [0u8; 3];
+    let mut writer = Cursor::new(&mut out[..]);
+    let short = || format_err!(ErrorKind::WriteZero, "short write");
+    assert_eq!(writer.write_all_ensuring(b"ab", short).unwrap(), 2);
+    let err = writer.write_all_ensuring(b"cd", short).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WriteZero);
+    assert_eq!(err.to_string(), "short write");
+
+    // Errors other than the one being checked are passed through.
+    struct Closed;
+    impl io::Write for Closed {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(format_err!(ErrorKind::BrokenPipe))
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    let err = Closed.write_all_ensuring(b"x", short).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+}
+
+#[test]
+fn io_ensure_chain() {
+    assert!(IoEnsureChain::new().run().is_ok());
+
+    let res = IoEnsureChain::new()
+        .add(true, ErrorKind::InvalidInput, "first")
+        .add_kind(true, ErrorKind::NotFound)
+        .add_fmt(true, ErrorKind::Other, format_args!("{}", 3))
+        .add_lazy(|| (true, ErrorKind::Other, "lazy"))
+        .run();
+    assert!(res.is_ok());
+
+    let err = IoEnsureChain::new()
+        .add(true, ErrorKind::InvalidInput, "first")
+        .add(false, ErrorKind::InvalidData, String::from("second"))
+        .add(false, ErrorKind::Other, "third")
+        .run()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "second");
+
+    let err = IoEnsureChain::new()
+        .add_kind(false, ErrorKind::NotFound)
+        .run()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert!(err.get_ref().is_none());
+
+    let limit = 4;
+    let err = IoEnsureChain::new()
+        .add_fmt(
+            5 <= limit,
+            ErrorKind::InvalidInput,
+            format_args!("{} exceeds {}", 5, limit),
+        )
+        .run()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "5 exceeds 4");
+
+    // Lazy checks run in order, and not at all after an earlier failure.
+    let calls = std::cell::Cell::new(0);
+    let chain = IoEnsureChain::new()
+        .add_lazy(|| {
+            calls.set(calls.get() + 1);
+            (false, ErrorKind::TimedOut, "lazy")
+        })
+        .add(false, ErrorKind::Other, "eager")
+        .add_lazy(|| {
+            calls.set(calls.get() + 1);
+            (false, ErrorKind::Other, "never")
+        });
+    assert_eq!(calls.get(), 0);
+    let err = chain.run().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert_eq!(err.to_string(), "lazy");
+    assert_eq!(calls.get(), 1);
+}
+
+#[test]
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn ensure_fd_valid() {
+    use std::os::unix::io::{AsRawFd, RawFd};
+
+    fn check(fd: RawFd) -> io::Result<()> {
+        ensure_fd_valid!(fd, ErrorKind::InvalidInput);
+        Ok(())
+    }
+    let file = std::fs::File::open(std::env::current_exe().unwrap()).unwrap();
+    assert!(check(file.as_raw_fd()).is_ok());
+    assert!(check(io::stderr().as_raw_fd()).is_ok());
+
+    let err = check(-1).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "invalid file descriptor: -1");
+    assert!(check(RawFd::MAX).is_err());
+
+    fn custom(fd: RawFd) -> io::Result<()> {
+        ensure_fd_valid!(
+            fd,
+            ErrorKind::InvalidInput,
+            "bad descriptor {} from caller",
+            fd
+        );
+        Ok(())
+    }
+    assert_eq!(
+        custom(-7).unwrap_err().to_string(),
+        "bad descriptor -7 from caller"
+    );
+}
+
+#[test]
+fn ensure_byte_order() {
+    fn check(mark: [u8; 2], expected: ByteOrder) -> io::Result<()> {
+        ensure_byte_order!(mark, expected, ErrorKind::InvalidData);
+        Ok(())
+    }
+    assert!(check(*b"MM", ByteOrder::BigEndian).is_ok());
+    assert!(check([0x49, 0x49], ByteOrder::LittleEndian).is_ok());
+
+    let err = check(*b"II", ByteOrder::BigEndian).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(
+        err.to_string(),
+        "expected big-endian byte order, found little-endian"
+    );
+    assert_eq!(
+        check([0xfe, 0xff], ByteOrder::LittleEndian)
+            .unwrap_err()
+            .to_string(),
+        "expected little-endian byte order, found unknown byte-order mark fe ff"
+    );
+
+    fn custom(order: ByteOrder) -> io::Result<()> {
+        ensure_byte_order!(
+            order,
+            ByteOrder::LittleEndian,
+            ErrorKind::InvalidData,
+            "unexpected byte order: expected little-endian"
+        );
+        Ok(())
+    }
+    assert!(custom(ByteOrder::LittleEndian).is_ok());
+    assert_eq!(
+        custom(ByteOrder::Unknown(*b"MI")).unwrap_err().to_string(),
+        "unexpected byte order: expected little-endian"
+    );
+}